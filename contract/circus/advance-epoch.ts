@@ -52,7 +52,7 @@ async function main() {
   const tx = await program.methods
     .advanceEpoch()
     .accountsPartial({
-      admin: provider.wallet.publicKey,
+      keeper: provider.wallet.publicKey,
       protocolState: protocolPda,
       roundState: roundPda,
     })
@@ -0,0 +1,416 @@
+//! Operator CLI for the rafa program. Every command prints JSON; transaction commands accept
+//! `--dry-run` to simulate instead of sending.
+
+mod view;
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+use rafa_client::accounts::ClaimTicket;
+use rafa_client::{instructions, pda, RpcClient};
+use serde_json::{json, Value};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use solana_sdk::signer::Signer;
+
+#[derive(Parser)]
+#[command(name = "rafa-cli", about = "Administer and inspect the rafa program")]
+struct Cli {
+    #[arg(long, global = true, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+    /// Signer and fee payer for transaction commands
+    #[arg(long, global = true, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+    /// Simulate transactions and print their logs instead of sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the protocol state; the signer becomes admin and holds every role
+    Initialize {
+        #[arg(long)]
+        validator: String,
+    },
+    /// Move lamports from the treasurer into the prize pool
+    Seed { lamports: u64 },
+    /// Buy tickets (whole-ticket lamports) in the current round for another wallet, which alone
+    /// can withdraw them: giveaways and team-funded entries
+    DepositFor { beneficiary: String, lamports: u64 },
+    /// Buy tickets (whole-ticket lamports) in the current round as a Token-2022 receipt instead of
+    /// a position of the signer's: whoever holds the receipt owns the tickets
+    DepositWithReceipt { lamports: u64 },
+    /// Cash in a receipt the signer holds once its round is over: the stake, plus the prize if it won
+    RedeemReceipt { mint: String },
+    /// Open a round (defaults to current_round + 1, starting now)
+    InitRound {
+        #[arg(long)]
+        round_id: Option<u64>,
+        /// Defaults to a fresh address
+        #[arg(long)]
+        stake_account: Option<String>,
+    },
+    /// Crank a round (defaults to the current one) with all its participants
+    Crank {
+        #[arg(long)]
+        round_id: Option<u64>,
+    },
+    /// Show an account
+    #[command(subcommand)]
+    Inspect(Inspect),
+    /// List the UserAccounts that joined a round (defaults to the current one)
+    Participants {
+        #[arg(long)]
+        round_id: Option<u64>,
+    },
+    /// Create the winner's claim ticket as treasurer. Prize and stake default to the round's
+    /// prize and the winner's stake, pending withdrawals included.
+    CreateClaimTicket {
+        round_id: u64,
+        #[arg(long)]
+        prize: Option<u64>,
+        #[arg(long)]
+        stake: Option<u64>,
+    },
+    /// Take lamports (whole tickets) out of the running round at once, paying the exit fee and
+    /// forfeiting those tickets
+    InstantWithdraw { lamports: u64 },
+    /// Hand the signer's position in the running round to another wallet, joining any position
+    /// it holds there
+    TransferPosition { recipient: String },
+    /// Carry what the signer has left in a finished round into the current one as new tickets
+    RollOver,
+    /// Pay the next batch of a complete round's queued withdrawals (anyone can)
+    PayWithdrawals { round_id: u64 },
+    /// Close a round's withdrawal queue once every request in it is processed
+    CloseWithdrawals { round_id: u64 },
+    /// Start tearing the protocol down: deposits and rounds stop, withdrawals stay open
+    WindDown,
+    /// Finish a wind-down once its grace period is over and nothing is owed: close the protocol
+    /// state and send what is left to the admin
+    CloseState,
+    /// Close a past round whose participants are all paid out; the rent goes back to its payer
+    CloseRound { round_id: u64 },
+    /// Close a claimed claim ticket; the rent goes back to its payer
+    CloseClaim { round_id: u64, winner: String },
+    /// Close the signer's user account once nothing is staked in it
+    CloseUser,
+    /// Count the open positions of a round migrated from the baseline so it can be closed
+    ReconcileRound { round_id: u64 },
+    /// Count the stake owed on a protocol migrated from the baseline so it can be closed
+    ReconcileLiabilities,
+    /// Upgrade an account written by an older program to the current layout, paying the rent
+    /// of the added bytes
+    #[command(subcommand)]
+    Migrate(Migrate),
+}
+
+#[derive(Subcommand)]
+enum Inspect {
+    Protocol,
+    /// Defaults to the current round
+    Round { round_id: Option<u64> },
+    User { owner: String },
+    Claim { round_id: u64, winner: String },
+    Withdrawals { round_id: u64 },
+}
+
+#[derive(Subcommand)]
+enum Migrate {
+    Protocol,
+    Round { round_id: u64 },
+    User { owner: String },
+    Claim { round_id: u64, winner: String },
+    ChangeQueue,
+    Multisig,
+    Proposal { proposal_id: u64 },
+}
+
+struct Ctx {
+    rpc: RpcClient,
+    keypair_path: String,
+    dry_run: bool,
+}
+
+impl Ctx {
+    fn signer(&self) -> Result<Keypair> {
+        let path = expand_home(&self.keypair_path);
+        read_keypair_file(&path).map_err(|e| anyhow!("read keypair {}: {e}", path.display()))
+    }
+
+    fn current_round(&self, round_id: Option<u64>) -> Result<u64> {
+        match round_id {
+            Some(id) => Ok(id),
+            None => Ok(self.rpc.protocol_state()?.current_round),
+        }
+    }
+
+    /// Send (or simulate) one instruction signed by the CLI keypair
+    fn submit(&self, name: &str, signer: &Keypair, ix: Instruction) -> Result<Value> {
+        let tx = self.rpc.build_transaction(&[ix], &[signer])?;
+        if self.dry_run {
+            let logs = self.rpc.simulate_transaction(&tx)?;
+            return Ok(json!({ "instruction": name, "dry_run": true, "logs": logs }));
+        }
+        let signature = self.rpc.send_transaction(&tx)?;
+        self.rpc.confirm(&signature, 60)?;
+        Ok(json!({ "instruction": name, "signature": signature.to_string() }))
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn parse_key(key: &str) -> Result<Pubkey> {
+    Pubkey::from_str(key).map_err(|e| anyhow!("invalid pubkey {key}: {e}"))
+}
+
+fn run(cli: Cli) -> Result<Value> {
+    let ctx = Ctx {
+        rpc: RpcClient::new(cli.rpc_url),
+        keypair_path: cli.keypair,
+        dry_run: cli.dry_run,
+    };
+
+    match cli.command {
+        Command::Initialize { validator } => {
+            let signer = ctx.signer()?;
+            let ix = instructions::initialize(&signer.pubkey(), parse_key(&validator)?);
+            ctx.submit("initialize", &signer, ix)
+        }
+        Command::Seed { lamports } => {
+            let signer = ctx.signer()?;
+            ctx.submit("seed_prize", &signer, instructions::seed_prize(&signer.pubkey(), lamports))
+        }
+        Command::DepositFor { beneficiary, lamports } => {
+            let signer = ctx.signer()?;
+            let beneficiary = parse_key(&beneficiary)?;
+            let round_id = ctx.current_round(None)?;
+            let participants: Vec<Pubkey> =
+                ctx.rpc.participants(round_id)?.into_iter().map(|(key, _)| key).collect();
+            let ix = instructions::deposit_for(&signer.pubkey(), &beneficiary, round_id, lamports, &participants);
+            ctx.submit("deposit_for", &signer, ix)
+        }
+        Command::DepositWithReceipt { lamports } => {
+            let signer = ctx.signer()?;
+            let round_id = ctx.current_round(None)?;
+            // The first nonce without a receipt position; redeemed ones are closed and free again
+            let mut nonce = 0;
+            while ctx.rpc.user(&pda::receipt_mint(round_id, &signer.pubkey(), nonce).0)?.is_some() {
+                nonce += 1;
+            }
+            let mint = pda::receipt_mint(round_id, &signer.pubkey(), nonce).0;
+            let participants: Vec<Pubkey> =
+                ctx.rpc.participants(round_id)?.into_iter().map(|(key, _)| key).collect();
+            let ix = instructions::deposit_with_receipt(&signer.pubkey(), round_id, nonce, lamports, &participants);
+            let mut out = ctx.submit("deposit_with_receipt", &signer, ix)?;
+            out["mint"] = json!(mint.to_string());
+            Ok(out)
+        }
+        Command::RedeemReceipt { mint } => {
+            let signer = ctx.signer()?;
+            let mint = parse_key(&mint)?;
+            let receipt = ctx.rpc.user(&mint)?.context("no receipt position for this mint")?;
+            let ix = instructions::redeem_receipt(&signer.pubkey(), &mint, receipt.round_joined);
+            ctx.submit("redeem_receipt", &signer, ix)
+        }
+        Command::InitRound { round_id, stake_account } => {
+            let signer = ctx.signer()?;
+            let round_id = match round_id {
+                Some(id) => id,
+                None => ctx.rpc.protocol_state()?.current_round + 1,
+            };
+            let stake_account = match stake_account {
+                Some(key) => parse_key(&key)?,
+                None => Keypair::new().pubkey(),
+            };
+            let start_ms = ctx.rpc.cluster_time_ms()?;
+            let ix = instructions::init_round(&signer.pubkey(), round_id, start_ms, &stake_account);
+            ctx.submit("init_round", &signer, ix)
+        }
+        Command::Crank { round_id } => {
+            let signer = ctx.signer()?;
+            let round_id = ctx.current_round(round_id)?;
+            let participants: Vec<Pubkey> =
+                ctx.rpc.participants(round_id)?.into_iter().map(|(key, _)| key).collect();
+            ctx.submit("crank", &signer, instructions::crank(round_id, &participants))
+        }
+        Command::Inspect(Inspect::Protocol) => {
+            let address = pda::protocol_state().0;
+            Ok(view::protocol(&address, &ctx.rpc.protocol_state()?))
+        }
+        Command::Inspect(Inspect::Round { round_id }) => {
+            let round_id = ctx.current_round(round_id)?;
+            let address = pda::round_state(round_id).0;
+            let round = ctx.rpc.round(round_id)?.with_context(|| format!("round {round_id} does not exist"))?;
+            Ok(view::round(&address, &round))
+        }
+        Command::Inspect(Inspect::User { owner }) => {
+            let owner = parse_key(&owner)?;
+            let address = pda::user_account(&owner).0;
+            let user = ctx.rpc.user(&owner)?.with_context(|| format!("{owner} has no user account"))?;
+            Ok(view::user(&address, &user))
+        }
+        Command::Inspect(Inspect::Claim { round_id, winner }) => {
+            let address = pda::claim_ticket(round_id, &parse_key(&winner)?).0;
+            let ticket: ClaimTicket = ctx
+                .rpc
+                .account(&address)?
+                .with_context(|| format!("no claim ticket for {winner} in round {round_id}"))?;
+            Ok(view::claim_ticket(&address, &ticket))
+        }
+        Command::Inspect(Inspect::Withdrawals { round_id }) => {
+            let address = pda::withdrawal_queue(round_id).0;
+            let queue = ctx
+                .rpc
+                .withdrawal_queue(round_id)?
+                .with_context(|| format!("no withdrawal queue for round {round_id}"))?;
+            Ok(view::withdrawal_queue(&address, &queue))
+        }
+        Command::Participants { round_id } => {
+            let round_id = ctx.current_round(round_id)?;
+            let participants = ctx.rpc.participants(round_id)?;
+            Ok(json!({
+                "round_id": round_id,
+                "participants": participants.iter().map(|(key, user)| view::user(key, user)).collect::<Vec<_>>(),
+            }))
+        }
+        Command::CreateClaimTicket { round_id, prize, stake } => {
+            let signer = ctx.signer()?;
+            let round = ctx.rpc.round(round_id)?.with_context(|| format!("round {round_id} does not exist"))?;
+            let Some(winner) = round.winner else {
+                bail!("round {round_id} has no winner yet");
+            };
+            let prize = prize.unwrap_or(round.total_prize_lamports);
+            let stake = match stake {
+                Some(stake) => stake,
+                None => ctx
+                    .rpc
+                    .user(&winner)?
+                    .filter(|user| user.round_joined == round_id)
+                    .map_or(0, |user| user.balance + user.pending_withdrawal_amount),
+            };
+            let ix = instructions::create_claim_ticket(&signer.pubkey(), round_id, &winner, prize, stake);
+            ctx.submit("create_claim_ticket", &signer, ix)
+        }
+        Command::InstantWithdraw { lamports } => {
+            let signer = ctx.signer()?;
+            let user = ctx.rpc.user(&signer.pubkey())?.context("signer has no user account")?;
+            let ix = instructions::instant_withdraw(&signer.pubkey(), user.round_joined, lamports);
+            ctx.submit("instant_withdraw", &signer, ix)
+        }
+        Command::TransferPosition { recipient } => {
+            let signer = ctx.signer()?;
+            let recipient = parse_key(&recipient)?;
+            let user = ctx.rpc.user(&signer.pubkey())?.context("signer has no user account")?;
+            let ix = instructions::transfer_position(&signer.pubkey(), &recipient, user.round_joined);
+            ctx.submit("transfer_position", &signer, ix)
+        }
+        Command::RollOver => {
+            let signer = ctx.signer()?;
+            let user = ctx.rpc.user(&signer.pubkey())?.context("signer has no user account")?;
+            let current = ctx.rpc.protocol_state()?.current_round;
+            let ix = instructions::roll_over(&signer.pubkey(), user.round_joined, current);
+            ctx.submit("roll_over", &signer, ix)
+        }
+        Command::PayWithdrawals { round_id } => {
+            let signer = ctx.signer()?;
+            let queue = ctx
+                .rpc
+                .withdrawal_queue(round_id)?
+                .with_context(|| format!("no withdrawal queue for round {round_id}"))?;
+            let waiting = &queue.entries[queue.head as usize..];
+            let batch = &waiting[..waiting.len().min(rafa::MAX_WITHDRAWALS_PER_BATCH)];
+            ctx.submit("process_withdrawals_batch", &signer, instructions::process_withdrawals_batch(round_id, batch))
+        }
+        Command::CloseWithdrawals { round_id } => {
+            let signer = ctx.signer()?;
+            ctx.submit("close_withdrawal_queue", &signer, instructions::close_withdrawal_queue(round_id))
+        }
+        Command::WindDown => {
+            let signer = ctx.signer()?;
+            ctx.submit("begin_wind_down", &signer, instructions::begin_wind_down(&signer.pubkey()))
+        }
+        Command::CloseState => {
+            let signer = ctx.signer()?;
+            ctx.submit("close_protocol_state", &signer, instructions::close_protocol_state(&signer.pubkey()))
+        }
+        Command::CloseRound { round_id } => {
+            let signer = ctx.signer()?;
+            let round = ctx.rpc.round(round_id)?.with_context(|| format!("round {round_id} does not exist"))?;
+            let rent_payer = rafa::rent_receiver(round.rent_payer, pda::protocol_state().0);
+            ctx.submit("close_round", &signer, instructions::close_round(round_id, &rent_payer))
+        }
+        Command::CloseClaim { round_id, winner } => {
+            let signer = ctx.signer()?;
+            let winner = parse_key(&winner)?;
+            let ticket: ClaimTicket = ctx
+                .rpc
+                .account(&pda::claim_ticket(round_id, &winner).0)?
+                .with_context(|| format!("no claim ticket for {winner} in round {round_id}"))?;
+            let rent_payer = rafa::rent_receiver(ticket.rent_payer, pda::protocol_state().0);
+            ctx.submit("close_claim_ticket", &signer, instructions::close_claim_ticket(round_id, &winner, &rent_payer))
+        }
+        Command::CloseUser => {
+            let signer = ctx.signer()?;
+            ctx.submit("close_user_account", &signer, instructions::close_user_account(&signer.pubkey()))
+        }
+        Command::ReconcileRound { round_id } => {
+            let signer = ctx.signer()?;
+            let positions: Vec<Pubkey> = ctx
+                .rpc
+                .participants(round_id)?
+                .into_iter()
+                .filter(|(_, user)| user.balance > 0 || user.pending_withdrawal_amount > 0)
+                .map(|(key, _)| key)
+                .collect();
+            let ix = instructions::reconcile_round(&signer.pubkey(), round_id, &positions);
+            ctx.submit("reconcile_round", &signer, ix)
+        }
+        Command::ReconcileLiabilities => {
+            let signer = ctx.signer()?;
+            let positions: Vec<Pubkey> = ctx.rpc.stakers()?.into_iter().map(|(key, _)| key).collect();
+            let ix = instructions::reconcile_liabilities(&signer.pubkey(), &positions);
+            ctx.submit("reconcile_liabilities", &signer, ix)
+        }
+        Command::Migrate(target) => {
+            let signer = ctx.signer()?;
+            let payer = signer.pubkey();
+            let (name, ix) = match target {
+                Migrate::Protocol => ("migrate_protocol_state", instructions::migrate_protocol_state(&payer)),
+                Migrate::Round { round_id } => {
+                    ("migrate_round_state", instructions::migrate_round_state(&payer, round_id))
+                }
+                Migrate::User { owner } => {
+                    ("migrate_user_account", instructions::migrate_user_account(&payer, &parse_key(&owner)?))
+                }
+                Migrate::Claim { round_id, winner } => (
+                    "migrate_claim_ticket",
+                    instructions::migrate_claim_ticket(&payer, round_id, &parse_key(&winner)?),
+                ),
+                Migrate::ChangeQueue => ("migrate_change_queue", instructions::migrate_change_queue(&payer)),
+                Migrate::Multisig => ("migrate_multisig", instructions::migrate_multisig(&payer)),
+                Migrate::Proposal { proposal_id } => {
+                    ("migrate_multisig_proposal", instructions::migrate_multisig_proposal(&payer, proposal_id))
+                }
+            };
+            ctx.submit(name, &signer, ix)
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let output = run(Cli::parse())?;
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
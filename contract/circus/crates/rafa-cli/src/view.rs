@@ -0,0 +1,101 @@
+//! JSON renderings of the program accounts for `inspect` output.
+
+use anchor_lang::prelude::Pubkey;
+use rafa::{ClaimTicket, ProtocolState, RoundState, UserAccount, WithdrawalQueue};
+use serde_json::{json, Value};
+
+fn key(key: Option<Pubkey>) -> Value {
+    key.map_or(Value::Null, |k| json!(k.to_string()))
+}
+
+pub fn protocol(address: &Pubkey, state: &ProtocolState) -> Value {
+    json!({
+        "address": address.to_string(),
+        "admin": state.admin.to_string(),
+        "pending_admin": key(state.pending_admin),
+        "keeper": state.keeper.to_string(),
+        "guardian": state.guardian.to_string(),
+        "treasurer": state.treasurer.to_string(),
+        "validator": state.validator.to_string(),
+        "status": format!("{:?}", state.status),
+        "current_round": state.current_round,
+        "prize_seed_amount": state.prize_seed_amount,
+        "total_unclaimed_prizes": state.total_unclaimed_prizes,
+        "cancelled_rounds": state.cancelled_rounds,
+        "timelock_delay_seconds": state.timelock_delay_seconds,
+        "outstanding_stake": state.outstanding_stake,
+        "wind_down_started_at": state.wind_down_started_at,
+        "liquid_buffer_bps": state.liquid_buffer_bps,
+        "liquid_buffer": state.liquid_buffer,
+        "exit_fee_bps": state.exit_fee_bps,
+        "max_tickets_per_user": state.max_tickets_per_user,
+        "max_tickets_per_round": state.max_tickets_per_round,
+        "min_tickets_per_deposit": state.min_tickets_per_deposit,
+        "version": state.version,
+    })
+}
+
+pub fn round(address: &Pubkey, round: &RoundState) -> Value {
+    json!({
+        "address": address.to_string(),
+        "round_id": round.round_id,
+        "epoch_in_round": round.epoch_in_round,
+        "start_time_ms": round.start_epoch,
+        "end_time_ms": round.end_epoch,
+        "stake_account": round.stake_account.to_string(),
+        "total_staked_lamports": round.total_staked_lamports,
+        "total_prize_lamports": round.total_prize_lamports,
+        "total_tickets_sold": round.total_tickets_sold,
+        "voided_tickets": round.voided_tickets,
+        "winner": key(round.winner),
+        "winning_ticket": round.winning_ticket,
+        "draw_slot": round.draw_slot,
+        "draw_seed": round.draw_seed,
+        "is_complete": round.is_complete,
+        "is_cancelled": round.is_cancelled,
+        "prize_claimed": round.prize_claimed,
+        "open_positions": round.open_positions,
+        "rent_payer": round.rent_payer.to_string(),
+        "version": round.version,
+    })
+}
+
+pub fn user(address: &Pubkey, user: &UserAccount) -> Value {
+    json!({
+        "address": address.to_string(),
+        "owner": user.owner.to_string(),
+        "balance": user.balance,
+        "round_joined": user.round_joined,
+        "ticket_start": user.ticket_start,
+        "ticket_end": user.ticket_end,
+        "extra_ranges": user.extra_ranges.iter().map(|range| [range.first, range.last]).collect::<Vec<_>>(),
+        "snapshot_balances": user.snapshot_balances,
+        "snapshots_recorded_mask": user.snapshots_recorded_mask,
+        "pending_withdrawal_amount": user.pending_withdrawal_amount,
+        "pending_withdrawal_round": user.pending_withdrawal_round,
+        "version": user.version,
+    })
+}
+
+pub fn claim_ticket(address: &Pubkey, ticket: &ClaimTicket) -> Value {
+    json!({
+        "address": address.to_string(),
+        "round_id": ticket.round_id,
+        "winner": ticket.winner.to_string(),
+        "prize_amount": ticket.prize_amount,
+        "stake_amount": ticket.stake_amount,
+        "claimed": ticket.claimed,
+        "rent_payer": ticket.rent_payer.to_string(),
+        "version": ticket.version,
+    })
+}
+
+pub fn withdrawal_queue(address: &Pubkey, queue: &WithdrawalQueue) -> Value {
+    json!({
+        "address": address.to_string(),
+        "round_id": queue.round_id,
+        "head": queue.head,
+        "waiting": queue.entries[queue.head as usize..].iter().map(|k| k.to_string()).collect::<Vec<_>>(),
+        "version": queue.version,
+    })
+}
@@ -0,0 +1,20 @@
+//! Decoding of raw account data into the program's account types.
+
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Result};
+pub use rafa::{
+    ChangeQueue, ClaimTicket, Multisig, MultisigProposal, ProtocolState, RoundState, UserAccount, WithdrawalQueue,
+};
+
+/// Byte offset of `UserAccount::round_joined` (discriminator + owner, balance, ticket_start,
+/// ticket_end, snapshot_balances, snapshots_recorded_mask), for `getProgramAccounts` memcmp filters
+pub const USER_ROUND_JOINED_OFFSET: usize = 8 + 32 + 8 + 8 + 8 + 3 * 8 + 1;
+
+/// Byte offset of `UserAccount::owner`
+pub const USER_OWNER_OFFSET: usize = 8;
+
+/// Decode account data, checking the discriminator
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    let mut slice = data;
+    T::try_deserialize(&mut slice).map_err(|e| anyhow!("decode {}: {e}", std::any::type_name::<T>()))
+}
@@ -0,0 +1,788 @@
+//! One builder per program instruction. Each fills in every PDA, the event-CPI accounts and the
+//! `remaining_accounts` the handler expects, so callers only pass the keys that vary.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::{associated_token, token_2022};
+use rafa::{accounts, instruction, ConfigChange, ProposalAccount, ProtocolRole, ProtocolStatus};
+
+use crate::pda;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData, remaining: Vec<AccountMeta>) -> Instruction {
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(remaining);
+    Instruction {
+        program_id: rafa::ID,
+        accounts: metas,
+        data: data.data(),
+    }
+}
+
+fn writable(keys: &[Pubkey]) -> Vec<AccountMeta> {
+    keys.iter().map(|k| AccountMeta::new(*k, false)).collect()
+}
+
+fn readonly(keys: &[Pubkey]) -> Vec<AccountMeta> {
+    keys.iter().map(|k| AccountMeta::new_readonly(*k, false)).collect()
+}
+
+fn event_authority() -> Pubkey {
+    pda::event_authority().0
+}
+
+pub fn initialize(admin: &Pubkey, validator: Pubkey) -> Instruction {
+    build(
+        accounts::Initialize {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::Initialize { validator },
+        vec![],
+    )
+}
+
+pub fn seed_prize(treasurer: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::SeedPrize {
+            treasurer: *treasurer,
+            protocol_state: pda::protocol_state().0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::SeedPrize { amount },
+        vec![],
+    )
+}
+
+pub fn init_round(keeper: &Pubkey, round_id: u64, start_epoch: u64, stake_account: &Pubkey) -> Instruction {
+    build(
+        accounts::InitRound {
+            payer: *keeper,
+            protocol_state: pda::protocol_state().0,
+            stake_account: *stake_account,
+            round_state: pda::round_state(round_id).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::InitRound { round_id, start_epoch },
+        vec![],
+    )
+}
+
+/// Deposit into `round_id` (the protocol's `current_round`).
+/// The next round's PDA is always passed so the deposit can open it if `round_id` is complete;
+/// `participants` (UserAccount addresses) let the deposit finalize an expired round.
+pub fn deposit(user: &Pubkey, round_id: u64, amount: u64, participants: &[Pubkey]) -> Instruction {
+    let mut remaining = writable(&[pda::round_state(round_id).0, pda::round_state(round_id + 1).0]);
+    remaining.extend(writable(participants));
+    build(
+        accounts::Deposit {
+            user: *user,
+            protocol_state: pda::protocol_state().0,
+            user_account: pda::user_account(user).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::Deposit { amount },
+        remaining,
+    )
+}
+
+/// Deposit paid by `payer` that credits the tickets, and the right to withdraw them, to `beneficiary`
+pub fn deposit_for(
+    payer: &Pubkey,
+    beneficiary: &Pubkey,
+    round_id: u64,
+    amount: u64,
+    participants: &[Pubkey],
+) -> Instruction {
+    let mut remaining = writable(&[pda::round_state(round_id).0, pda::round_state(round_id + 1).0]);
+    remaining.extend(writable(participants));
+    build(
+        accounts::DepositFor {
+            payer: *payer,
+            beneficiary: *beneficiary,
+            protocol_state: pda::protocol_state().0,
+            user_account: pda::user_account(beneficiary).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::DepositFor { amount },
+        remaining,
+    )
+}
+
+/// Deposit whose position is the ticket receipt at `pda::receipt_mint(round_id, payer, nonce)`;
+/// the receipt token lands in `payer`'s associated token account
+pub fn deposit_with_receipt(
+    payer: &Pubkey,
+    round_id: u64,
+    nonce: u64,
+    amount: u64,
+    participants: &[Pubkey],
+) -> Instruction {
+    let mint = &pda::receipt_mint(round_id, payer, nonce).0;
+    let mut remaining = writable(&[pda::round_state(round_id).0, pda::round_state(round_id + 1).0]);
+    remaining.extend(writable(participants));
+    build(
+        accounts::DepositWithReceipt {
+            payer: *payer,
+            protocol_state: pda::protocol_state().0,
+            receipt_mint: *mint,
+            receipt_account: pda::user_account(mint).0,
+            payer_receipt: pda::receipt_token_account(payer, mint),
+            token_program: token_2022::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::DepositWithReceipt { amount, nonce },
+        remaining,
+    )
+}
+
+/// `round_id` is the user's `round_joined`: the round the tickets are forfeited from while it is
+/// still open, and whose withdrawal queue the request joins
+pub fn request_withdrawal(user: &Pubkey, round_id: u64, amount: u64) -> Instruction {
+    build(
+        accounts::RequestWithdrawal {
+            user: *user,
+            protocol_state: pda::protocol_state().0,
+            user_account: pda::user_account(user).0,
+            round_state: pda::round_state(round_id).0,
+            withdrawal_queue: pda::withdrawal_queue(round_id).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::RequestWithdrawal { amount },
+        vec![],
+    )
+}
+
+/// Record the current epoch's balance snapshot for a batch of UserAccount addresses
+pub fn take_snapshot_batch(round_id: u64, user_accounts: &[Pubkey]) -> Instruction {
+    build(
+        accounts::TakeSnapshotBatch {
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+        },
+        instruction::TakeSnapshotBatch {},
+        writable(user_accounts),
+    )
+}
+
+pub fn advance_epoch(keeper: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::AdvanceEpoch {
+            keeper: *keeper,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::AdvanceEpoch {},
+        vec![],
+    )
+}
+
+pub fn select_winner_local(keeper: &Pubkey, round_id: u64, seed: u64, participants: &[Pubkey]) -> Instruction {
+    build(
+        accounts::SelectWinnerLocal {
+            keeper: *keeper,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::SelectWinnerLocal { seed },
+        writable(participants),
+    )
+}
+
+pub fn claim_prize(winner: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::ClaimPrize {
+            user: *winner,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            claim_ticket: pda::claim_ticket(round_id, winner).0,
+            user_account: pda::user_account(winner).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ClaimPrize { round_id },
+        vec![],
+    )
+}
+
+/// `round_id` is the user's `round_joined`, the running round the tickets are forfeited in
+pub fn instant_withdraw(user: &Pubkey, round_id: u64, amount: u64) -> Instruction {
+    build(
+        accounts::InstantWithdraw {
+            user: *user,
+            protocol_state: pda::protocol_state().0,
+            user_account: pda::user_account(user).0,
+            round_state: pda::round_state(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::InstantWithdraw { amount },
+        vec![],
+    )
+}
+
+/// Move `user`'s position in running round `round_id` (its `round_joined`) to `recipient`
+pub fn transfer_position(user: &Pubkey, recipient: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::TransferPosition {
+            user: *user,
+            recipient: *recipient,
+            protocol_state: pda::protocol_state().0,
+            user_account: pda::user_account(user).0,
+            recipient_account: pda::user_account(recipient).0,
+            round_state: pda::round_state(round_id).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::TransferPosition {},
+        vec![],
+    )
+}
+
+/// Move the balance left in completed round `from_round` into open round `round_id`
+pub fn roll_over(user: &Pubkey, from_round: u64, round_id: u64) -> Instruction {
+    build(
+        accounts::RollOver {
+            user: *user,
+            protocol_state: pda::protocol_state().0,
+            user_account: pda::user_account(user).0,
+            previous_round: pda::round_state(from_round).0,
+            round_state: pda::round_state(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::RollOver { from_round },
+        vec![],
+    )
+}
+
+pub fn process_withdrawal(user: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::ProcessWithdrawal {
+            user: *user,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            user_account: pda::user_account(user).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ProcessWithdrawal { round_id },
+        vec![],
+    )
+}
+
+/// Pay the queued withdrawals of `owners`, the queue's entries from its `head` on, in order
+pub fn process_withdrawals_batch(round_id: u64, owners: &[Pubkey]) -> Instruction {
+    let remaining = owners.iter().flat_map(|owner| writable(&[pda::user_account(owner).0, *owner])).collect();
+    build(
+        accounts::ProcessWithdrawalsBatch {
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            withdrawal_queue: pda::withdrawal_queue(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ProcessWithdrawalsBatch { round_id },
+        remaining,
+    )
+}
+
+pub fn create_claim_ticket(
+    treasurer: &Pubkey,
+    round_id: u64,
+    winner: &Pubkey,
+    prize_amount: u64,
+    stake_amount: u64,
+) -> Instruction {
+    build(
+        accounts::CreateClaimTicket {
+            treasurer: *treasurer,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            claim_ticket: pda::claim_ticket(round_id, winner).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CreateClaimTicket { round_id, prize_amount, stake_amount },
+        vec![],
+    )
+}
+
+pub fn create_claim_ticket_winner(winner: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::CreateClaimTicketWinner {
+            winner: *winner,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            user_account: pda::user_account(winner).0,
+            claim_ticket: pda::claim_ticket(round_id, winner).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CreateClaimTicketWinner { round_id },
+        vec![],
+    )
+}
+
+/// Crank `round_id` with its participants' UserAccount addresses. The next round's PDA and the
+/// system program are appended so a completed round rolls over into the next one.
+pub fn crank(round_id: u64, participants: &[Pubkey]) -> Instruction {
+    let mut remaining = writable(participants);
+    remaining.push(AccountMeta::new(pda::round_state(round_id + 1).0, false));
+    remaining.push(AccountMeta::new_readonly(system_program::ID, false));
+    build(
+        accounts::Crank {
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::Crank {},
+        remaining,
+    )
+}
+
+pub fn cancel_round(authority: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::CancelRound {
+            authority: *authority,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CancelRound { round_id },
+        vec![],
+    )
+}
+
+pub fn refund_cancelled(participant: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::RefundCancelled {
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            user_account: pda::user_account(participant).0,
+            participant: *participant,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::RefundCancelled { round_id },
+        vec![],
+    )
+}
+
+/// Redeem the ticket receipt `mint` held by `holder`; `round_id` is the round it was bought in
+pub fn redeem_receipt(holder: &Pubkey, mint: &Pubkey, round_id: u64) -> Instruction {
+    build(
+        accounts::RedeemReceipt {
+            holder: *holder,
+            protocol_state: pda::protocol_state().0,
+            receipt_mint: *mint,
+            holder_receipt: pda::receipt_token_account(holder, mint),
+            receipt_account: pda::user_account(mint).0,
+            round_state: pda::round_state(round_id).0,
+            token_program: token_2022::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::RedeemReceipt {},
+        vec![],
+    )
+}
+
+pub fn set_protocol_status(authority: &Pubkey, status: ProtocolStatus) -> Instruction {
+    build(
+        accounts::SetProtocolStatus {
+            authority: *authority,
+            protocol_state: pda::protocol_state().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::SetProtocolStatus { status },
+        vec![],
+    )
+}
+
+pub fn set_role(admin: &Pubkey, role: ProtocolRole, key: Pubkey) -> Instruction {
+    build(
+        accounts::SetRole {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::SetRole { role, key },
+        vec![],
+    )
+}
+
+pub fn propose_admin(admin: &Pubkey, new_admin: Pubkey) -> Instruction {
+    build(
+        accounts::ProposeAdmin {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ProposeAdmin { new_admin },
+        vec![],
+    )
+}
+
+pub fn accept_admin(new_admin: &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptAdmin {
+            new_admin: *new_admin,
+            protocol_state: pda::protocol_state().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::AcceptAdmin {},
+        vec![],
+    )
+}
+
+pub fn init_change_queue(admin: &Pubkey) -> Instruction {
+    build(
+        accounts::InitChangeQueue {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            change_queue: pda::change_queue().0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::InitChangeQueue {},
+        vec![],
+    )
+}
+
+pub fn propose_change(admin: &Pubkey, change: ConfigChange) -> Instruction {
+    build(
+        accounts::ProposeChange {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            change_queue: pda::change_queue().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ProposeChange { change },
+        vec![],
+    )
+}
+
+pub fn execute_change(admin: &Pubkey, change_id: u64) -> Instruction {
+    build(
+        accounts::ExecuteChange {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            change_queue: pda::change_queue().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ExecuteChange { change_id },
+        vec![],
+    )
+}
+
+pub fn cancel_change(authority: &Pubkey, change_id: u64) -> Instruction {
+    build(
+        accounts::CancelChange {
+            authority: *authority,
+            protocol_state: pda::protocol_state().0,
+            change_queue: pda::change_queue().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CancelChange { change_id },
+        vec![],
+    )
+}
+
+pub fn create_multisig(admin: &Pubkey, owners: Vec<Pubkey>, threshold: u8) -> Instruction {
+    build(
+        accounts::CreateMultisig {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            multisig: pda::multisig().0,
+            multisig_signer: pda::multisig_signer().0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CreateMultisig { owners, threshold },
+        vec![],
+    )
+}
+
+/// Wrap `proposed` (a rafa instruction whose authority is the multisig signer PDA) into a
+/// proposal. `proposal_id` must be the multisig's current `proposal_count`.
+pub fn propose_multisig_tx(proposer: &Pubkey, proposal_id: u64, proposed: &Instruction) -> Instruction {
+    let signer = pda::multisig_signer().0;
+    let proposal_accounts = proposed
+        .accounts
+        .iter()
+        .map(|m| ProposalAccount {
+            pubkey: m.pubkey,
+            is_signer: m.is_signer && m.pubkey != signer,
+            is_writable: m.is_writable,
+        })
+        .collect();
+    build(
+        accounts::ProposeMultisigTx {
+            proposer: *proposer,
+            protocol_state: pda::protocol_state().0,
+            multisig: pda::multisig().0,
+            proposal: pda::multisig_proposal(proposal_id).0,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ProposeMultisigTx {
+            accounts: proposal_accounts,
+            data: proposed.data.clone(),
+        },
+        vec![],
+    )
+}
+
+pub fn approve_multisig_tx(owner: &Pubkey, proposal_id: u64) -> Instruction {
+    build(
+        accounts::ApproveMultisigTx {
+            owner: *owner,
+            protocol_state: pda::protocol_state().0,
+            multisig: pda::multisig().0,
+            proposal: pda::multisig_proposal(proposal_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ApproveMultisigTx { proposal_id },
+        vec![],
+    )
+}
+
+/// Execute an approved proposal; `proposed` is the same instruction given to
+/// [`propose_multisig_tx`], whose accounts are forwarded as remaining accounts.
+pub fn execute_multisig_tx(proposal_id: u64, proposed: &Instruction) -> Instruction {
+    let signer = pda::multisig_signer().0;
+    let mut remaining: Vec<AccountMeta> = proposed
+        .accounts
+        .iter()
+        .map(|m| AccountMeta {
+            pubkey: m.pubkey,
+            // The PDA signs inside the program, never at the transaction level
+            is_signer: m.is_signer && m.pubkey != signer,
+            is_writable: m.is_writable,
+        })
+        .collect();
+    remaining.push(AccountMeta::new_readonly(rafa::ID, false));
+    build(
+        accounts::ExecuteMultisigTx {
+            protocol_state: pda::protocol_state().0,
+            multisig: pda::multisig().0,
+            multisig_signer: signer,
+            proposal: pda::multisig_proposal(proposal_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ExecuteMultisigTx { proposal_id },
+        remaining,
+    )
+}
+
+/// Meant to be wrapped in a multisig proposal: the multisig signer PDA must sign
+pub fn set_multisig_owners(owners: Vec<Pubkey>, threshold: u8) -> Instruction {
+    build(
+        accounts::SetMultisigOwners {
+            multisig_signer: pda::multisig_signer().0,
+            protocol_state: pda::protocol_state().0,
+            multisig: pda::multisig().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::SetMultisigOwners { owners, threshold },
+        vec![],
+    )
+}
+
+pub fn begin_wind_down(admin: &Pubkey) -> Instruction {
+    build(
+        accounts::BeginWindDown {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::BeginWindDown {},
+        vec![],
+    )
+}
+
+pub fn close_protocol_state(admin: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseProtocolState {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CloseProtocolState {},
+        vec![],
+    )
+}
+
+/// `rent_payer` is `rafa::rent_receiver(round.rent_payer, protocol_state)`
+pub fn close_round(round_id: u64, rent_payer: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseRound {
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            rent_payer: *rent_payer,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CloseRound { round_id },
+        vec![],
+    )
+}
+
+/// `rent_payer` is `rafa::rent_receiver(ticket.rent_payer, protocol_state)`
+pub fn close_claim_ticket(round_id: u64, winner: &Pubkey, rent_payer: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseClaimTicket {
+            protocol_state: pda::protocol_state().0,
+            claim_ticket: pda::claim_ticket(round_id, winner).0,
+            rent_payer: *rent_payer,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CloseClaimTicket { round_id, winner: *winner },
+        vec![],
+    )
+}
+
+pub fn close_withdrawal_queue(round_id: u64) -> Instruction {
+    build(
+        accounts::CloseWithdrawalQueue {
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            withdrawal_queue: pda::withdrawal_queue(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CloseWithdrawalQueue { round_id },
+        vec![],
+    )
+}
+
+pub fn close_user_account(owner: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseUserAccount {
+            owner: *owner,
+            protocol_state: pda::protocol_state().0,
+            user_account: pda::user_account(owner).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::CloseUserAccount {},
+        vec![],
+    )
+}
+
+/// `positions` are the UserAccounts still staked in the round
+pub fn reconcile_round(authority: &Pubkey, round_id: u64, positions: &[Pubkey]) -> Instruction {
+    build(
+        accounts::ReconcileRound {
+            authority: *authority,
+            protocol_state: pda::protocol_state().0,
+            round_state: pda::round_state(round_id).0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ReconcileRound { round_id },
+        readonly(positions),
+    )
+}
+
+/// `positions` are every UserAccount holding a balance or a pending withdrawal
+pub fn reconcile_liabilities(admin: &Pubkey, positions: &[Pubkey]) -> Instruction {
+    build(
+        accounts::ReconcileLiabilities {
+            admin: *admin,
+            protocol_state: pda::protocol_state().0,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        instruction::ReconcileLiabilities {},
+        readonly(positions),
+    )
+}
+
+fn migrate(payer: &Pubkey, account: Pubkey, data: impl InstructionData) -> Instruction {
+    build(
+        accounts::Migrate {
+            payer: *payer,
+            protocol_state: pda::protocol_state().0,
+            account,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: rafa::ID,
+        },
+        data,
+        vec![],
+    )
+}
+
+pub fn migrate_protocol_state(payer: &Pubkey) -> Instruction {
+    migrate(payer, pda::protocol_state().0, instruction::MigrateProtocolState {})
+}
+
+pub fn migrate_round_state(payer: &Pubkey, round_id: u64) -> Instruction {
+    migrate(payer, pda::round_state(round_id).0, instruction::MigrateRoundState {})
+}
+
+pub fn migrate_user_account(payer: &Pubkey, owner: &Pubkey) -> Instruction {
+    migrate(payer, pda::user_account(owner).0, instruction::MigrateUserAccount {})
+}
+
+pub fn migrate_claim_ticket(payer: &Pubkey, round_id: u64, winner: &Pubkey) -> Instruction {
+    migrate(payer, pda::claim_ticket(round_id, winner).0, instruction::MigrateClaimTicket {})
+}
+
+pub fn migrate_change_queue(payer: &Pubkey) -> Instruction {
+    migrate(payer, pda::change_queue().0, instruction::MigrateChangeQueue {})
+}
+
+pub fn migrate_multisig(payer: &Pubkey) -> Instruction {
+    migrate(payer, pda::multisig().0, instruction::MigrateMultisig {})
+}
+
+pub fn migrate_multisig_proposal(payer: &Pubkey, proposal_id: u64) -> Instruction {
+    migrate(payer, pda::multisig_proposal(proposal_id).0, instruction::MigrateMultisigProposal {})
+}
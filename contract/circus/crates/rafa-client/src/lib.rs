@@ -0,0 +1,12 @@
+//! Typed client for the rafa program, shared by the off-chain services and tests:
+//! PDA derivation, instruction builders, account decoding, ticket odds and a small RPC client.
+
+pub mod accounts;
+pub mod instructions;
+pub mod odds;
+pub mod pda;
+pub mod rpc;
+
+pub use odds::Odds;
+pub use rafa::{ConfigChange, ProtocolRole, ProtocolStatus, ID as PROGRAM_ID};
+pub use rpc::RpcClient;
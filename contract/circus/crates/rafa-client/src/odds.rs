@@ -0,0 +1,118 @@
+//! Ticket odds. A round draws one ticket uniformly out of those still held (forfeited tickets
+//! are re-drawn), so a user's chance is simply the share of the round's live tickets they hold.
+
+use rafa::{RoundState, UserAccount, TICKET_PRICE_LAMPORTS};
+
+/// Tickets bought by depositing `lamports`; the program rejects amounts that are not an exact
+/// multiple of the ticket price
+pub fn tickets_for_amount(lamports: u64) -> Option<u64> {
+    let tickets = lamports / TICKET_PRICE_LAMPORTS;
+    (tickets * TICKET_PRICE_LAMPORTS == lamports).then_some(tickets)
+}
+
+/// Tickets `user` holds in `round`
+pub fn tickets_held(user: &UserAccount, round: &RoundState) -> u64 {
+    if user.round_joined != round.round_id {
+        return 0;
+    }
+    user.tickets_held()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Odds {
+    pub tickets: u64,
+    pub total_tickets: u64,
+}
+
+impl Odds {
+    pub fn new(tickets: u64, total_tickets: u64) -> Self {
+        Self { tickets, total_tickets }
+    }
+
+    /// Odds of `user` winning `round` as it stands
+    pub fn current(user: &UserAccount, round: &RoundState) -> Self {
+        Self::new(tickets_held(user, round), round.live_tickets())
+    }
+
+    /// Odds after buying `extra` more tickets in a round of `total_tickets`
+    pub fn after_deposit(self, extra: u64) -> Self {
+        Self::new(self.tickets + extra, self.total_tickets + extra)
+    }
+
+    /// Chance of winning in [0, 1]
+    pub fn probability(&self) -> f64 {
+        if self.total_tickets == 0 {
+            return 0.0;
+        }
+        self.tickets as f64 / self.total_tickets as f64
+    }
+
+    /// The "1 in N" form, `None` when holding no tickets
+    pub fn one_in(&self) -> Option<f64> {
+        (self.tickets > 0).then(|| self.total_tickets as f64 / self.tickets as f64)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AnchorDeserialize;
+
+    use super::*;
+
+    fn round(round_id: u64, total_tickets_sold: u64, voided_tickets: u64) -> RoundState {
+        let mut round = RoundState::deserialize(&mut &[0u8; RoundState::SIZE][..]).unwrap();
+        round.round_id = round_id;
+        round.total_tickets_sold = total_tickets_sold;
+        round.voided_tickets = voided_tickets;
+        round
+    }
+
+    fn user(round_joined: u64, ticket_start: u64, ticket_end: u64) -> UserAccount {
+        let mut user = UserAccount::deserialize(&mut &[0u8; UserAccount::SIZE][..]).unwrap();
+        user.round_joined = round_joined;
+        user.ticket_start = ticket_start;
+        user.ticket_end = ticket_end;
+        user.balance = (ticket_end + 1 - ticket_start) * TICKET_PRICE_LAMPORTS;
+        user
+    }
+
+    #[test]
+    fn only_whole_tickets_can_be_bought() {
+        assert_eq!(tickets_for_amount(0), Some(0));
+        assert_eq!(tickets_for_amount(3 * TICKET_PRICE_LAMPORTS), Some(3));
+        assert_eq!(tickets_for_amount(3 * TICKET_PRICE_LAMPORTS + 1), None);
+        assert_eq!(tickets_for_amount(TICKET_PRICE_LAMPORTS - 1), None);
+    }
+
+    #[test]
+    fn tickets_count_only_in_the_round_joined() {
+        let alice = user(2, 4, 6);
+        assert_eq!(tickets_held(&alice, &round(2, 10, 0)), 3);
+        assert_eq!(tickets_held(&alice, &round(3, 10, 0)), 0);
+
+        // A fully forfeited range is empty
+        let mut gone = user(2, 7, 6);
+        gone.balance = 0;
+        assert_eq!(tickets_held(&gone, &round(2, 10, 3)), 0);
+    }
+
+    #[test]
+    fn odds_are_the_share_of_live_tickets() {
+        let odds = Odds::current(&user(0, 0, 1), &round(0, 10, 2));
+        assert_eq!(odds, Odds::new(2, 8));
+        assert_eq!(odds.probability(), 0.25);
+        assert_eq!(odds.one_in(), Some(4.0));
+
+        let more = odds.after_deposit(2);
+        assert_eq!(more, Odds::new(4, 10));
+        assert_eq!(more.probability(), 0.4);
+    }
+
+    #[test]
+    fn no_tickets_means_no_chance() {
+        assert_eq!(Odds::new(0, 0).probability(), 0.0);
+        assert_eq!(Odds::new(0, 5).probability(), 0.0);
+        assert_eq!(Odds::new(0, 5).one_in(), None);
+    }
+}
@@ -0,0 +1,65 @@
+//! PDA derivation, mirroring the `seeds = [...]` constraints in the program.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_2022;
+
+pub fn protocol_state() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"state"], &rafa::ID)
+}
+
+pub fn round_state(round_id: u64) -> (Pubkey, u8) {
+    rafa::find_round_pda(&protocol_state().0, round_id, &rafa::ID)
+}
+
+pub fn user_account(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"user", owner.as_ref()], &rafa::ID)
+}
+
+/// Mint of the ticket receipt `payer` buys in `round_id` under `nonce`, any number unused so far
+pub fn receipt_mint(round_id: u64, payer: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"receipt_mint", &round_id.to_le_bytes(), payer.as_ref(), &nonce.to_le_bytes()],
+        &rafa::ID,
+    )
+}
+
+/// The associated Token-2022 account holding `holder`'s token of the ticket receipt `mint`
+pub fn receipt_token_account(holder: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(holder, mint, &token_2022::ID)
+}
+
+pub fn claim_ticket(round_id: u64, winner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"claim", &round_id.to_le_bytes(), winner.as_ref()], &rafa::ID)
+}
+
+pub fn withdrawal_queue(round_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"withdrawals", protocol_state().0.as_ref(), &round_id.to_le_bytes()],
+        &rafa::ID,
+    )
+}
+
+pub fn change_queue() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"changes", protocol_state().0.as_ref()], &rafa::ID)
+}
+
+pub fn multisig() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"multisig", protocol_state().0.as_ref()], &rafa::ID)
+}
+
+pub fn multisig_signer() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"multisig-signer", multisig().0.as_ref()], &rafa::ID)
+}
+
+pub fn multisig_proposal(proposal_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"proposal", multisig().0.as_ref(), &proposal_id.to_le_bytes()],
+        &rafa::ID,
+    )
+}
+
+/// Signer of the `emit_cpi!` self-invocations, required by every `#[event_cpi]` instruction
+pub fn event_authority() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"__event_authority"], &rafa::ID)
+}
@@ -0,0 +1,224 @@
+//! Minimal blocking JSON-RPC client: account fetches, participant discovery and transaction
+//! submission.
+
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::Transaction;
+
+use crate::accounts::{decode, ProtocolState, RoundState, UserAccount, WithdrawalQueue, USER_ROUND_JOINED_OFFSET};
+use crate::pda;
+
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl RpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into(), agent: ureq::Agent::new() }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(body)
+            .with_context(|| format!("{method} request to {}", self.url))?
+            .into_json()?;
+        if let Some(err) = response.get("error") {
+            bail!("{method} failed: {err}");
+        }
+        Ok(response["result"].clone())
+    }
+
+    /// Raw account data, `None` if the account does not exist
+    pub fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), { "encoding": "base64", "commitment": "confirmed" }]),
+        )?;
+        let value = &result["value"];
+        if value.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(decode_base64_data(&value["data"])?))
+    }
+
+    /// Fetch and decode a program account, `None` if it does not exist
+    pub fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<Option<T>> {
+        self.account_data(address)?.map(|data| decode(&data)).transpose()
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> Result<u64> {
+        let result = self.call("getBalance", json!([address.to_string(), { "commitment": "confirmed" }]))?;
+        result["value"].as_u64().context("unexpected getBalance response")
+    }
+
+    /// Unix time of the latest confirmed block in milliseconds, the clock the program sees
+    pub fn cluster_time_ms(&self) -> Result<u64> {
+        let slot = self.call("getSlot", json!([{ "commitment": "confirmed" }]))?;
+        let slot = slot.as_u64().context("unexpected getSlot response")?;
+        let time = self.call("getBlockTime", json!([slot]))?;
+        let seconds = time.as_u64().context("unexpected getBlockTime response")?;
+        Ok(seconds * 1000)
+    }
+
+    pub fn protocol_state(&self) -> Result<ProtocolState> {
+        self.account(&pda::protocol_state().0)?
+            .ok_or_else(|| anyhow!("protocol is not initialized"))
+    }
+
+    pub fn round(&self, round_id: u64) -> Result<Option<RoundState>> {
+        self.account(&pda::round_state(round_id).0)
+    }
+
+    pub fn user(&self, owner: &Pubkey) -> Result<Option<UserAccount>> {
+        self.account(&pda::user_account(owner).0)
+    }
+
+    pub fn withdrawal_queue(&self, round_id: u64) -> Result<Option<WithdrawalQueue>> {
+        self.account(&pda::withdrawal_queue(round_id).0)
+    }
+
+    /// UserAccounts that joined `round_id`, with their addresses, in ticket order
+    pub fn participants(&self, round_id: u64) -> Result<Vec<(Pubkey, UserAccount)>> {
+        let round_joined = json!({ "memcmp": {
+            "offset": USER_ROUND_JOINED_OFFSET,
+            "bytes": BASE64.encode(round_id.to_le_bytes()),
+            "encoding": "base64",
+        } });
+        let mut participants = self.user_accounts(Some(round_joined))?;
+        participants.sort_by_key(|(_, user)| user.ticket_start);
+        Ok(participants)
+    }
+
+    /// Every UserAccount still holding a balance or a pending withdrawal, in any round
+    pub fn stakers(&self) -> Result<Vec<(Pubkey, UserAccount)>> {
+        let mut users = self.user_accounts(None)?;
+        users.retain(|(_, user)| user.balance > 0 || user.pending_withdrawal_amount > 0);
+        Ok(users)
+    }
+
+    /// UserAccounts matching `filter`, with their addresses. Accounts grow with every extra
+    /// ticket range, so they are matched by discriminator rather than size.
+    fn user_accounts(&self, filter: Option<Value>) -> Result<Vec<(Pubkey, UserAccount)>> {
+        let discriminator = json!({ "memcmp": {
+            "offset": 0,
+            "bytes": BASE64.encode(UserAccount::DISCRIMINATOR),
+            "encoding": "base64",
+        } });
+        let filters: Vec<Value> = [Some(discriminator), filter].into_iter().flatten().collect();
+        let result = self.call(
+            "getProgramAccounts",
+            json!([rafa::ID.to_string(), {
+                "encoding": "base64",
+                "commitment": "confirmed",
+                "filters": filters,
+            }]),
+        )?;
+        let entries = result.as_array().ok_or_else(|| anyhow!("unexpected getProgramAccounts response"))?;
+        let mut users = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let key = entry["pubkey"].as_str().context("missing pubkey")?;
+            let key = Pubkey::from_str(key).map_err(|e| anyhow!("bad pubkey {key}: {e}"))?;
+            let data = decode_base64_data(&entry["account"]["data"])?;
+            users.push((key, decode::<UserAccount>(&data)?));
+        }
+        Ok(users)
+    }
+
+    pub fn latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        let hash = result["value"]["blockhash"].as_str().context("missing blockhash")?;
+        Hash::from_str(hash).map_err(|e| anyhow!("bad blockhash {hash}: {e}"))
+    }
+
+    /// Sign `instructions` with `signers` (the first one pays) and build a transaction
+    pub fn build_transaction(&self, instructions: &[Instruction], signers: &[&dyn Signer]) -> Result<Transaction> {
+        let payer = signers.first().ok_or_else(|| anyhow!("no signers"))?.pubkey();
+        let blockhash = self.latest_blockhash()?;
+        let mut tx = Transaction::new_with_payer(instructions, Some(&payer));
+        tx.try_sign(&signers.to_vec(), blockhash)?;
+        Ok(tx)
+    }
+
+    /// Send a signed transaction and return its signature once the node accepted it
+    pub fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        let result = self.call(
+            "sendTransaction",
+            json!([encode_transaction(tx)?, { "encoding": "base64", "preflightCommitment": "confirmed" }]),
+        )?;
+        let signature = result.as_str().context("unexpected sendTransaction response")?;
+        Signature::from_str(signature).map_err(|e| anyhow!("bad signature {signature}: {e}"))
+    }
+
+    /// Simulate a transaction, returning its logs or the simulation error
+    pub fn simulate_transaction(&self, tx: &Transaction) -> Result<Vec<String>> {
+        let result = self.call(
+            "simulateTransaction",
+            json!([encode_transaction(tx)?, { "encoding": "base64", "commitment": "confirmed" }]),
+        )?;
+        let value = &result["value"];
+        let logs: Vec<String> = value["logs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|l| l.as_str().map(str::to_string))
+            .collect();
+        if !value["err"].is_null() {
+            bail!("simulation failed: {}\n{}", value["err"], logs.join("\n"));
+        }
+        Ok(logs)
+    }
+
+    /// Poll until `signature` is confirmed; fails if the transaction errored
+    pub fn confirm(&self, signature: &Signature, attempts: u32) -> Result<()> {
+        for _ in 0..attempts {
+            let result = self.call("getSignatureStatuses", json!([[signature.to_string()]]))?;
+            let status = &result["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    bail!("transaction {signature} failed: {}", status["err"]);
+                }
+                if matches!(status["confirmationStatus"].as_str(), Some("confirmed" | "finalized")) {
+                    return Ok(());
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(500));
+        }
+        bail!("transaction {signature} not confirmed")
+    }
+
+    /// Build, send and confirm
+    pub fn send_and_confirm(&self, instructions: &[Instruction], signers: &[&dyn Signer]) -> Result<Signature> {
+        let tx = self.build_transaction(instructions, signers)?;
+        let signature = self.send_transaction(&tx)?;
+        self.confirm(&signature, 60)?;
+        Ok(signature)
+    }
+}
+
+fn encode_transaction(tx: &Transaction) -> Result<String> {
+    Ok(BASE64.encode(bincode::serialize(tx)?))
+}
+
+/// `"data": ["<base64>", "base64"]`
+fn decode_base64_data(data: &Value) -> Result<Vec<u8>> {
+    let encoded = data[0].as_str().ok_or_else(|| anyhow!("account data is not base64"))?;
+    Ok(BASE64.decode(encoded)?)
+}
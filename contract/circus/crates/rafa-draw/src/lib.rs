@@ -0,0 +1,67 @@
+//! The winner draw, exactly as the rafa program runs it.
+//!
+//! The program finalizes a round by deriving a seed from the clock at that slot, reducing it to
+//! a ticket number and handing the prize to whoever holds that ticket, re-drawing when the
+//! ticket was forfeited. Anyone with the round's `draw_slot`, end time and ticket ledger can run
+//! the same steps and must land on the recorded `winning_ticket` and `winner`.
+
+#![no_std]
+
+/// Seed for a clock-based draw, from the `Clock` sysvar fields at finalization
+pub fn clock_seed(slot: u64, unix_timestamp: i64, epoch: u64) -> u64 {
+    slot.wrapping_mul(unix_timestamp as u64).wrapping_add(epoch)
+}
+
+/// The ticket a seed draws out of `total_tickets`; `None` for a round without tickets
+pub fn winning_ticket(seed: u64, total_tickets: u64) -> Option<u64> {
+    seed.checked_rem(total_tickets)
+}
+
+/// Owner of `ticket` in a ledger of `(owner, first_ticket, last_ticket)` ranges, both ends
+/// inclusive. The first matching range wins, as in the program.
+pub fn ticket_owner<K>(ledger: impl IntoIterator<Item = (K, u64, u64)>, ticket: u64) -> Option<K> {
+    ledger
+        .into_iter()
+        .find(|(_, start, end)| (*start..=*end).contains(&ticket))
+        .map(|(owner, _, _)| owner)
+}
+
+/// Draws on a void ticket before falling back to the next live one
+pub const MAX_REDRAWS: u32 = 32;
+
+/// Seed of the `attempt`-th draw; attempt 0 is the round's own seed
+pub fn redraw_seed(seed: u64, attempt: u32) -> u64 {
+    if attempt == 0 {
+        return seed;
+    }
+    // splitmix64 of the seed offset by the attempt number
+    let mut z = seed.wrapping_add(u64::from(attempt).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Winning ticket and its owner. Tickets `0..total_tickets` were sold, `live_tickets` of them
+/// are still held by `ledger`; the rest are void (forfeited on withdrawal). A draw on a void
+/// ticket is re-drawn up to MAX_REDRAWS times, then the next live ticket wins.
+///
+/// A miss only counts as void when the ledger holds every live ticket; with holders missing
+/// from it the draw returns `None`, as the ticket may belong to one of them.
+pub fn draw<K: Copy>(seed: u64, total_tickets: u64, live_tickets: u64, ledger: &[(K, u64, u64)]) -> Option<(u64, K)> {
+    let held: u64 = ledger.iter().map(|(_, start, end)| end - start + 1).sum();
+    let complete = held == live_tickets;
+    let mut ticket = 0;
+    for attempt in 0..=MAX_REDRAWS {
+        ticket = winning_ticket(redraw_seed(seed, attempt), total_tickets)?;
+        if let Some(owner) = ticket_owner(ledger.iter().copied(), ticket) {
+            return Some((ticket, owner));
+        }
+        if !complete {
+            return None;
+        }
+    }
+    // First live ticket after the last (void) draw, wrapping around to the lowest overall
+    let after = ledger.iter().filter(|(_, start, _)| *start > ticket).min_by_key(|(_, start, _)| *start);
+    let (owner, start, _) = after.or_else(|| ledger.iter().min_by_key(|(_, start, _)| *start))?;
+    Some((*start, *owner))
+}
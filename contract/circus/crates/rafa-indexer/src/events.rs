@@ -0,0 +1,321 @@
+//! Decoding of rafa events out of confirmed transactions.
+//!
+//! The program emits every event through `emit_cpi!`, i.e. as a self-invocation whose
+//! instruction data is `EVENT_IX_TAG_LE || event discriminator || borsh(event)`. Those show up
+//! as inner instructions, so unlike `Program data:` log lines they survive log truncation.
+
+use std::str::FromStr;
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, Context, Result};
+use rafa::{
+    AccountMigrated, AdminChanged, AdminProposed, ChangeCancelled, ChangeExecuted, ChangeProposed,
+    ChangeQueueCreated, ClaimTicketClosed, ClaimTicketCreated, DepositFunded, DepositMade, EpochAdvanced,
+    InstantWithdrawal, LiabilitiesReconciled, MultisigOwnersChanged, MultisigTxApproved, MultisigTxExecuted,
+    MultisigTxProposed, PositionRolledOver, PositionTransferred, PrizeClaimed, PrizeSeeded, ProtocolClosed,
+    ProtocolInitialized, ProtocolStatusChanged, ReceiptMinted, ReceiptRedeemed, RefundProcessed, RoleChanged,
+    RoundCancelled, RoundClosed, RoundFinalized, RoundReconciled, RoundStarted, TicketsForfeited, UserAccountClosed,
+    WindDownBegun, WithdrawalProcessed, WithdrawalQueueClosed, WithdrawalRequested,
+};
+use serde_json::Value;
+
+pub enum RafaEvent {
+    PrizeSeeded(PrizeSeeded),
+    RoundStarted(RoundStarted),
+    DepositMade(DepositMade),
+    WithdrawalRequested(WithdrawalRequested),
+    EpochAdvanced(EpochAdvanced),
+    RoundFinalized(RoundFinalized),
+    ClaimTicketCreated(ClaimTicketCreated),
+    PrizeClaimed(PrizeClaimed),
+    WithdrawalProcessed(WithdrawalProcessed),
+    RoundCancelled(RoundCancelled),
+    RefundProcessed(RefundProcessed),
+    TicketsForfeited(TicketsForfeited),
+    InstantWithdrawal(InstantWithdrawal),
+    PositionRolledOver(PositionRolledOver),
+    DepositFunded(DepositFunded),
+    PositionTransferred(PositionTransferred),
+    ReceiptMinted(ReceiptMinted),
+    ReceiptRedeemed(ReceiptRedeemed),
+    ProtocolInitialized(ProtocolInitialized),
+    ProtocolStatusChanged(ProtocolStatusChanged),
+    RoleChanged(RoleChanged),
+    AdminProposed(AdminProposed),
+    AdminChanged(AdminChanged),
+    ChangeQueueCreated(ChangeQueueCreated),
+    ChangeProposed(ChangeProposed),
+    ChangeExecuted(ChangeExecuted),
+    ChangeCancelled(ChangeCancelled),
+    MultisigOwnersChanged(MultisigOwnersChanged),
+    MultisigTxProposed(MultisigTxProposed),
+    MultisigTxApproved(MultisigTxApproved),
+    MultisigTxExecuted(MultisigTxExecuted),
+    WindDownBegun(WindDownBegun),
+    ProtocolClosed(ProtocolClosed),
+    RoundClosed(RoundClosed),
+    ClaimTicketClosed(ClaimTicketClosed),
+    WithdrawalQueueClosed(WithdrawalQueueClosed),
+    UserAccountClosed(UserAccountClosed),
+    AccountMigrated(AccountMigrated),
+    RoundReconciled(RoundReconciled),
+    LiabilitiesReconciled(LiabilitiesReconciled),
+}
+
+impl RafaEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RafaEvent::PrizeSeeded(_) => "PrizeSeeded",
+            RafaEvent::RoundStarted(_) => "RoundStarted",
+            RafaEvent::DepositMade(_) => "DepositMade",
+            RafaEvent::WithdrawalRequested(_) => "WithdrawalRequested",
+            RafaEvent::EpochAdvanced(_) => "EpochAdvanced",
+            RafaEvent::RoundFinalized(_) => "RoundFinalized",
+            RafaEvent::ClaimTicketCreated(_) => "ClaimTicketCreated",
+            RafaEvent::PrizeClaimed(_) => "PrizeClaimed",
+            RafaEvent::WithdrawalProcessed(_) => "WithdrawalProcessed",
+            RafaEvent::RoundCancelled(_) => "RoundCancelled",
+            RafaEvent::RefundProcessed(_) => "RefundProcessed",
+            RafaEvent::TicketsForfeited(_) => "TicketsForfeited",
+            RafaEvent::InstantWithdrawal(_) => "InstantWithdrawal",
+            RafaEvent::PositionRolledOver(_) => "PositionRolledOver",
+            RafaEvent::DepositFunded(_) => "DepositFunded",
+            RafaEvent::PositionTransferred(_) => "PositionTransferred",
+            RafaEvent::ReceiptMinted(_) => "ReceiptMinted",
+            RafaEvent::ReceiptRedeemed(_) => "ReceiptRedeemed",
+            RafaEvent::ProtocolInitialized(_) => "ProtocolInitialized",
+            RafaEvent::ProtocolStatusChanged(_) => "ProtocolStatusChanged",
+            RafaEvent::RoleChanged(_) => "RoleChanged",
+            RafaEvent::AdminProposed(_) => "AdminProposed",
+            RafaEvent::AdminChanged(_) => "AdminChanged",
+            RafaEvent::ChangeQueueCreated(_) => "ChangeQueueCreated",
+            RafaEvent::ChangeProposed(_) => "ChangeProposed",
+            RafaEvent::ChangeExecuted(_) => "ChangeExecuted",
+            RafaEvent::ChangeCancelled(_) => "ChangeCancelled",
+            RafaEvent::MultisigOwnersChanged(_) => "MultisigOwnersChanged",
+            RafaEvent::MultisigTxProposed(_) => "MultisigTxProposed",
+            RafaEvent::MultisigTxApproved(_) => "MultisigTxApproved",
+            RafaEvent::MultisigTxExecuted(_) => "MultisigTxExecuted",
+            RafaEvent::WindDownBegun(_) => "WindDownBegun",
+            RafaEvent::ProtocolClosed(_) => "ProtocolClosed",
+            RafaEvent::RoundClosed(_) => "RoundClosed",
+            RafaEvent::ClaimTicketClosed(_) => "ClaimTicketClosed",
+            RafaEvent::WithdrawalQueueClosed(_) => "WithdrawalQueueClosed",
+            RafaEvent::UserAccountClosed(_) => "UserAccountClosed",
+            RafaEvent::AccountMigrated(_) => "AccountMigrated",
+            RafaEvent::RoundReconciled(_) => "RoundReconciled",
+            RafaEvent::LiabilitiesReconciled(_) => "LiabilitiesReconciled",
+        }
+    }
+}
+
+/// An event together with where it was found on chain
+pub struct IndexedEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Position of the event inside its transaction
+    pub index: u32,
+    pub event: RafaEvent,
+}
+
+/// Decode the data of a self-CPI event instruction. Returns `None` for anything that is not a
+/// known rafa event (regular instructions, events from a newer program version, ...).
+pub fn decode_event(data: &[u8]) -> Option<RafaEvent> {
+    let data = data.strip_prefix(EVENT_IX_TAG_LE)?;
+    if data.len() < 8 {
+        return None;
+    }
+    let (disc, mut body) = data.split_at(8);
+
+    macro_rules! try_event {
+        ($($ty:ident),*) => {
+            $(
+                if disc == $ty::DISCRIMINATOR {
+                    return $ty::deserialize(&mut body).ok().map(RafaEvent::$ty);
+                }
+            )*
+        };
+    }
+    try_event!(
+        PrizeSeeded,
+        RoundStarted,
+        DepositMade,
+        WithdrawalRequested,
+        EpochAdvanced,
+        RoundFinalized,
+        ClaimTicketCreated,
+        PrizeClaimed,
+        WithdrawalProcessed,
+        RoundCancelled,
+        RefundProcessed,
+        TicketsForfeited,
+        InstantWithdrawal,
+        PositionRolledOver,
+        DepositFunded,
+        PositionTransferred,
+        ReceiptMinted,
+        ReceiptRedeemed,
+        ProtocolInitialized,
+        ProtocolStatusChanged,
+        RoleChanged,
+        AdminProposed,
+        AdminChanged,
+        ChangeQueueCreated,
+        ChangeProposed,
+        ChangeExecuted,
+        ChangeCancelled,
+        MultisigOwnersChanged,
+        MultisigTxProposed,
+        MultisigTxApproved,
+        MultisigTxExecuted,
+        WindDownBegun,
+        ProtocolClosed,
+        RoundClosed,
+        ClaimTicketClosed,
+        WithdrawalQueueClosed,
+        UserAccountClosed,
+        AccountMigrated,
+        RoundReconciled,
+        LiabilitiesReconciled
+    );
+    None
+}
+
+/// Extract the rafa events from a `getTransaction` result (`"encoding": "json"`).
+/// Failed transactions yield no events.
+pub fn events_from_transaction(tx: &Value, program_id: &Pubkey) -> Result<Vec<IndexedEvent>> {
+    let meta = tx.get("meta").ok_or_else(|| anyhow!("transaction without meta"))?;
+    if !meta.get("err").is_none_or(Value::is_null) {
+        return Ok(Vec::new());
+    }
+
+    let signature = tx["transaction"]["signatures"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("transaction without signature"))?
+        .to_string();
+    let slot = tx["slot"].as_u64().context("transaction without slot")?;
+    let block_time = tx["blockTime"].as_i64();
+    let account_keys = account_keys(tx)?;
+
+    let mut events = Vec::new();
+    let inner = meta["innerInstructions"].as_array().cloned().unwrap_or_default();
+    for group in inner {
+        for ix in group["instructions"].as_array().into_iter().flatten() {
+            let program_index = ix["programIdIndex"].as_u64().context("missing programIdIndex")? as usize;
+            if account_keys.get(program_index) != Some(program_id) {
+                continue;
+            }
+            let data = bs58::decode(ix["data"].as_str().unwrap_or_default())
+                .into_vec()
+                .with_context(|| format!("bad instruction data in {signature}"))?;
+            if let Some(event) = decode_event(&data) {
+                events.push(IndexedEvent {
+                    signature: signature.clone(),
+                    slot,
+                    block_time,
+                    index: events.len() as u32,
+                    event,
+                });
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Static keys followed by address-table lookups, the order `programIdIndex` refers to
+fn account_keys(tx: &Value) -> Result<Vec<Pubkey>> {
+    let mut keys = Vec::new();
+    let static_keys = tx["transaction"]["message"]["accountKeys"]
+        .as_array()
+        .ok_or_else(|| anyhow!("transaction without accountKeys"))?;
+    let loaded = &tx["meta"]["loadedAddresses"];
+    let writable = loaded["writable"].as_array().into_iter().flatten();
+    let readonly = loaded["readonly"].as_array().into_iter().flatten();
+    for key in static_keys.iter().chain(writable).chain(readonly) {
+        let key = key.as_str().ok_or_else(|| anyhow!("non-string account key"))?;
+        keys.push(Pubkey::from_str(key).map_err(|e| anyhow!("bad account key {key}: {e}"))?);
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::Event;
+    use serde_json::json;
+
+    use super::*;
+
+    fn deposit() -> DepositMade {
+        DepositMade {
+            round_id: 3,
+            user: Pubkey::new_unique(),
+            amount: 20_000_000,
+            tickets: 2,
+            ticket_start: 5,
+            ticket_end: 6,
+            total_tickets_sold: 7,
+            total_staked_lamports: 70_000_000,
+        }
+    }
+
+    fn event_ix(event: &impl Event) -> Vec<u8> {
+        [EVENT_IX_TAG_LE, &event.data()].concat()
+    }
+
+    #[test]
+    fn decodes_a_self_cpi_event() {
+        let made = deposit();
+        let Some(RafaEvent::DepositMade(decoded)) = decode_event(&event_ix(&made)) else {
+            panic!("not decoded as DepositMade");
+        };
+        assert_eq!((decoded.user, decoded.ticket_start, decoded.ticket_end), (made.user, 5, 6));
+    }
+
+    #[test]
+    fn ignores_data_without_the_event_tag() {
+        let mut data = event_ix(&deposit());
+        data[0] ^= 0xff;
+        assert!(decode_event(&data).is_none());
+        // A plain instruction carrying the event bytes is not an event either
+        assert!(decode_event(&deposit().data()).is_none());
+    }
+
+    #[test]
+    fn ignores_truncated_events() {
+        let data = event_ix(&deposit());
+        assert!(decode_event(&data[..EVENT_IX_TAG_LE.len() + 4]).is_none());
+        assert!(decode_event(&data[..data.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn reads_events_of_the_program_out_of_inner_instructions() {
+        let program_id = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let data = bs58::encode(event_ix(&deposit())).into_string();
+        let mut tx = json!({
+            "slot": 42,
+            "blockTime": 1_700_000_000,
+            "transaction": {
+                "signatures": ["sig"],
+                "message": { "accountKeys": [program_id.to_string(), other.to_string()] },
+            },
+            "meta": {
+                "err": null,
+                "innerInstructions": [{ "index": 0, "instructions": [
+                    { "programIdIndex": 0, "data": data },
+                    { "programIdIndex": 1, "data": data },
+                ]}],
+            },
+        });
+
+        let events = events_from_transaction(&tx, &program_id).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].signature.as_str(), events[0].slot, events[0].index), ("sig", 42, 0));
+
+        tx["meta"]["err"] = json!({ "InstructionError": [0, { "Custom": 6000 }] });
+        assert!(events_from_transaction(&tx, &program_id).unwrap().is_empty());
+    }
+}
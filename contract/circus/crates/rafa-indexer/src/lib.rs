@@ -0,0 +1,44 @@
+//! Off-chain indexer for the rafa program.
+//!
+//! Account state only reflects the present (a `UserAccount` is reset as soon as its owner joins
+//! a new round), so the indexer replays the program's events into SQLite to keep the full
+//! history of rounds, ticket ranges, winners and payouts.
+
+pub mod events;
+pub mod source;
+pub mod store;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::Result;
+use serde_json::Value;
+
+pub use events::{decode_event, events_from_transaction, IndexedEvent, RafaEvent};
+pub use source::{read_ledger_dump, RpcSource};
+pub use store::{ConfigChangeRecord, DepositRecord, PayoutRecord, ProtocolRecord, RoundRecord, Store};
+
+/// Index already-fetched transactions (in chain order). Returns the number of new events.
+pub fn index_transactions<'a>(
+    store: &mut Store,
+    program_id: &Pubkey,
+    txs: impl IntoIterator<Item = &'a Value>,
+) -> Result<usize> {
+    let mut applied = 0;
+    for tx in txs {
+        let events = events_from_transaction(tx, program_id)?;
+        applied += store.apply(&events)?;
+    }
+    Ok(applied)
+}
+
+/// Pull every transaction newer than the stored cursor from RPC and index it
+pub fn sync(store: &mut Store, rpc: &RpcSource, program_id: &Pubkey) -> Result<usize> {
+    let cursor = store.cursor()?;
+    let signatures = rpc.signatures_since(program_id, cursor.as_deref())?;
+    let mut applied = 0;
+    for info in signatures {
+        let tx = rpc.transaction(&info.signature)?;
+        applied += index_transactions(store, program_id, [&tx])?;
+        store.set_cursor(&info.signature, info.slot)?;
+    }
+    Ok(applied)
+}
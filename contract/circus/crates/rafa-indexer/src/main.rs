@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use rafa_indexer::{index_transactions, read_ledger_dump, sync, RpcSource, Store};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(name = "rafa-indexer", about = "Index rafa program events into SQLite")]
+struct Cli {
+    /// SQLite database file
+    #[arg(long, default_value = "rafa-index.db")]
+    db: PathBuf,
+    /// Program id to index (defaults to the id declared by the program crate)
+    #[arg(long)]
+    program_id: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch new transactions from an RPC endpoint
+    Sync {
+        #[arg(long, default_value = "http://127.0.0.1:8899")]
+        rpc_url: String,
+        /// Keep polling instead of exiting after one pass
+        #[arg(long)]
+        follow: bool,
+        #[arg(long, default_value_t = 10)]
+        interval_secs: u64,
+    },
+    /// Index a ledger dump: one getTransaction JSON result per line
+    Import { file: PathBuf },
+    /// Current roles, status and the config change history
+    Protocol,
+    /// List all rounds
+    Rounds,
+    /// Show one round with its ticket ledger
+    Round { round_id: u64 },
+    /// Participation and payout history of a wallet
+    User { wallet: String },
+    /// Wallet owning a ticket in a round
+    Owner { round_id: u64, ticket: u64 },
+}
+
+fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let program_id = match &cli.program_id {
+        Some(id) => Pubkey::from_str(id).map_err(|e| anyhow!("invalid program id: {e}"))?,
+        None => rafa::ID,
+    };
+    let mut store = Store::open(&cli.db)?;
+
+    match cli.command {
+        Command::Sync { rpc_url, follow, interval_secs } => {
+            let rpc = RpcSource::new(rpc_url);
+            loop {
+                let applied = sync(&mut store, &rpc, &program_id)?;
+                eprintln!("indexed {applied} new events");
+                if !follow {
+                    break;
+                }
+                thread::sleep(Duration::from_secs(interval_secs));
+            }
+        }
+        Command::Import { file } => {
+            let txs = read_ledger_dump(&file)?;
+            let applied = index_transactions(&mut store, &program_id, &txs)?;
+            eprintln!("indexed {applied} new events from {} transactions", txs.len());
+        }
+        Command::Protocol => print_json(&serde_json::json!({
+            "protocol": store.protocol()?,
+            "config_changes": store.config_changes()?,
+        }))?,
+        Command::Rounds => print_json(&store.rounds()?)?,
+        Command::Round { round_id } => {
+            let round = store.round(round_id)?.ok_or_else(|| anyhow!("round {round_id} not indexed"))?;
+            print_json(&serde_json::json!({
+                "round": round,
+                "tickets": store.round_deposits(round_id)?,
+                "forfeits": store.round_forfeits(round_id)?,
+                "transfers": store.round_transfers(round_id)?,
+            }))?;
+        }
+        Command::User { wallet } => print_json(&serde_json::json!({
+            "deposits": store.user_deposits(&wallet)?,
+            "payouts": store.user_payouts(&wallet)?,
+        }))?,
+        Command::Owner { round_id, ticket } => print_json(&store.ticket_owner(round_id, ticket)?)?,
+    }
+    Ok(())
+}
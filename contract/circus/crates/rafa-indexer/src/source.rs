@@ -0,0 +1,105 @@
+//! Where transactions come from: a JSON-RPC endpoint (local validator, devnet) or a ledger
+//! dump file with one `getTransaction` result (`"encoding": "json"`) per line.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+
+/// Max signatures per `getSignaturesForAddress` page
+const SIGNATURE_PAGE: usize = 1000;
+
+pub struct RpcSource {
+    url: String,
+    agent: ureq::Agent,
+}
+
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+}
+
+impl RpcSource {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into(), agent: ureq::Agent::new() }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(body)
+            .with_context(|| format!("{method} request to {}", self.url))?
+            .into_json()?;
+        if let Some(err) = response.get("error") {
+            bail!("{method} failed: {err}");
+        }
+        Ok(response["result"].clone())
+    }
+
+    /// Signatures touching `program_id` that are newer than `until`, oldest first
+    pub fn signatures_since(&self, program_id: &Pubkey, until: Option<&str>) -> Result<Vec<SignatureInfo>> {
+        let mut all = Vec::new();
+        let mut before: Option<String> = None;
+        loop {
+            let mut config = json!({ "limit": SIGNATURE_PAGE, "commitment": "confirmed" });
+            if let Some(b) = &before {
+                config["before"] = json!(b);
+            }
+            if let Some(u) = until {
+                config["until"] = json!(u);
+            }
+            let page = self.call("getSignaturesForAddress", json!([program_id.to_string(), config]))?;
+            let page = page.as_array().ok_or_else(|| anyhow!("unexpected signatures response"))?;
+            for entry in page {
+                all.push(SignatureInfo {
+                    signature: entry["signature"].as_str().context("missing signature")?.to_string(),
+                    slot: entry["slot"].as_u64().context("missing slot")?,
+                });
+            }
+            if page.len() < SIGNATURE_PAGE {
+                break;
+            }
+            before = all.last().map(|s| s.signature.clone());
+        }
+        all.reverse();
+        Ok(all)
+    }
+
+    pub fn transaction(&self, signature: &str) -> Result<Value> {
+        let tx = self.call(
+            "getTransaction",
+            json!([signature, {
+                "encoding": "json",
+                "commitment": "confirmed",
+                "maxSupportedTransactionVersion": 0
+            }]),
+        )?;
+        if tx.is_null() {
+            bail!("transaction {signature} not found");
+        }
+        Ok(tx)
+    }
+}
+
+/// Read a ledger dump, sorted by slot so events replay in chain order
+pub fn read_ledger_dump(path: impl AsRef<Path>) -> Result<Vec<Value>> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path).with_context(|| format!("open {}", path.display()))?);
+    let mut txs = Vec::new();
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let tx: Value = serde_json::from_str(&line)
+            .with_context(|| format!("{}:{}: invalid JSON", path.display(), n + 1))?;
+        txs.push(tx);
+    }
+    txs.sort_by_key(|tx| tx["slot"].as_u64().unwrap_or_default());
+    Ok(txs)
+}
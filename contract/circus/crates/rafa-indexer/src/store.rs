@@ -0,0 +1,812 @@
+//! SQLite storage for the reconstructed round/ticket history and the query API on top of it.
+//!
+//! Every event is recorded once in `events` keyed by (signature, index); applying the same
+//! transaction twice is a no-op, so re-syncing overlapping ranges is always safe.
+
+use std::path::Path;
+
+use anyhow::Result;
+use rafa::ProtocolRole;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::events::{IndexedEvent, RafaEvent};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    name TEXT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    round_id INTEGER PRIMARY KEY,
+    status TEXT NOT NULL DEFAULT 'open',
+    start_time_ms INTEGER,
+    end_time_ms INTEGER,
+    stake_account TEXT,
+    epoch INTEGER NOT NULL DEFAULT 1,
+    total_tickets INTEGER NOT NULL DEFAULT 0,
+    total_staked INTEGER NOT NULL DEFAULT 0,
+    winner TEXT,
+    winning_ticket INTEGER,
+    prize_amount INTEGER,
+    prize_claimed INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS deposits (
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    round_id INTEGER NOT NULL,
+    user TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    ticket_start INTEGER NOT NULL,
+    ticket_end INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS deposits_round ON deposits (round_id, ticket_start);
+CREATE INDEX IF NOT EXISTS deposits_user ON deposits (user);
+CREATE TABLE IF NOT EXISTS forfeits (
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    round_id INTEGER NOT NULL,
+    user TEXT NOT NULL,
+    first_ticket INTEGER NOT NULL,
+    last_ticket INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS forfeits_round ON forfeits (round_id, user);
+CREATE TABLE IF NOT EXISTS transfers (
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    round_id INTEGER NOT NULL,
+    from_user TEXT NOT NULL,
+    to_user TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    ticket_start INTEGER NOT NULL,
+    ticket_end INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS transfers_round ON transfers (round_id, ticket_start);
+CREATE TABLE IF NOT EXISTS withdrawal_requests (
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    round_id INTEGER NOT NULL,
+    user TEXT NOT NULL,
+    amount INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS claim_tickets (
+    round_id INTEGER NOT NULL,
+    winner TEXT NOT NULL,
+    prize_amount INTEGER NOT NULL,
+    stake_amount INTEGER NOT NULL,
+    claimed INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (round_id, winner)
+);
+CREATE TABLE IF NOT EXISTS payouts (
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    round_id INTEGER NOT NULL,
+    user TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    amount INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS payouts_user ON payouts (user);
+CREATE TABLE IF NOT EXISTS prize_seeds (
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    treasurer TEXT NOT NULL,
+    amount INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS protocol (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    admin TEXT NOT NULL,
+    pending_admin TEXT,
+    keeper TEXT NOT NULL,
+    guardian TEXT NOT NULL,
+    treasurer TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'Active'
+);
+CREATE TABLE IF NOT EXISTS config_changes (
+    change_id INTEGER PRIMARY KEY,
+    change TEXT NOT NULL,
+    execute_after INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending'
+);
+CREATE TABLE IF NOT EXISTS cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
+";
+
+#[derive(Debug, Serialize)]
+pub struct RoundRecord {
+    pub round_id: u64,
+    pub status: String,
+    pub start_time_ms: Option<u64>,
+    pub end_time_ms: Option<u64>,
+    pub stake_account: Option<String>,
+    pub epoch: u8,
+    pub total_tickets: u64,
+    pub total_staked: u64,
+    pub winner: Option<String>,
+    pub winning_ticket: Option<u64>,
+    pub prize_amount: Option<u64>,
+    pub prize_claimed: bool,
+}
+
+/// Tickets bought in a round, by a deposit or by stake rolled over from an earlier round
+#[derive(Debug, Serialize)]
+pub struct DepositRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub round_id: u64,
+    pub user: String,
+    pub amount: u64,
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+}
+
+/// Tickets taken out of the draw by a withdrawal; their numbers are never sold again
+#[derive(Debug, Serialize)]
+pub struct ForfeitRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub round_id: u64,
+    pub user: String,
+    pub first_ticket: u64,
+    pub last_ticket: u64,
+}
+
+/// A position changing hands mid-round: tickets `ticket_start..=ticket_end` still held at that
+/// point belong to `to_user` from then on
+#[derive(Debug, Serialize)]
+pub struct TransferRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub round_id: u64,
+    pub from_user: String,
+    pub to_user: String,
+    pub amount: u64,
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayoutRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub round_id: u64,
+    pub user: String,
+    /// `prize`, `withdrawal`, `instant` (net of the exit fee), `refund` or `receipt` (stake and any prize)
+    pub kind: String,
+    pub amount: u64,
+}
+
+/// Who holds each role and the emergency status, as of the last indexed event
+#[derive(Debug, Serialize)]
+pub struct ProtocolRecord {
+    pub admin: String,
+    pub pending_admin: Option<String>,
+    pub keeper: String,
+    pub guardian: String,
+    pub treasurer: String,
+    /// A ProtocolStatus, or `Closed` once close_protocol_state ran
+    pub status: String,
+}
+
+/// A timelocked config change; `status` is `pending`, `executed` or `cancelled`
+#[derive(Debug, Serialize)]
+pub struct ConfigChangeRecord {
+    pub change_id: u64,
+    pub change: String,
+    pub execute_after: i64,
+    pub status: String,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Apply the events of one transaction atomically. Returns how many were new.
+    pub fn apply(&mut self, events: &[IndexedEvent]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut applied = 0;
+        for ev in events {
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO events (signature, event_index, slot, block_time, name)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![ev.signature, ev.index, ev.slot as i64, ev.block_time, ev.event.name()],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+            apply_event(&tx, ev)?;
+            applied += 1;
+        }
+        tx.commit()?;
+        Ok(applied)
+    }
+
+    /// Newest signature already indexed, used as the `until` bound of the next sync
+    pub fn cursor(&self) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT signature FROM cursor WHERE id = 0", [], |r| r.get(0))
+            .optional()?)
+    }
+
+    pub fn set_cursor(&self, signature: &str, slot: u64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO cursor (id, signature, slot) VALUES (0, ?1, ?2)
+             ON CONFLICT (id) DO UPDATE SET signature = excluded.signature, slot = excluded.slot",
+            params![signature, slot as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn rounds(&self) -> Result<Vec<RoundRecord>> {
+        let mut stmt = self.conn.prepare(&format!("{ROUND_SELECT} ORDER BY round_id"))?;
+        let rows = stmt.query_map([], round_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn round(&self, round_id: u64) -> Result<Option<RoundRecord>> {
+        Ok(self
+            .conn
+            .query_row(&format!("{ROUND_SELECT} WHERE round_id = ?1"), [round_id as i64], round_from_row)
+            .optional()?)
+    }
+
+    /// Every ticket range bought in a round, in ticket order (the round's ticket ledger)
+    pub fn round_deposits(&self, round_id: u64) -> Result<Vec<DepositRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{DEPOSIT_SELECT} WHERE round_id = ?1 ORDER BY ticket_start"
+        ))?;
+        let rows = stmt.query_map([round_id as i64], deposit_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Tickets forfeited in a round, in ticket order. The round's live ledger is its deposits
+    /// minus these ranges.
+    pub fn round_forfeits(&self, round_id: u64) -> Result<Vec<ForfeitRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, round_id, user, first_ticket, last_ticket FROM forfeits
+             WHERE round_id = ?1 ORDER BY first_ticket",
+        )?;
+        let rows = stmt.query_map([round_id as i64], |r| {
+            Ok(ForfeitRecord {
+                signature: r.get(0)?,
+                slot: r.get::<_, i64>(1)? as u64,
+                round_id: r.get::<_, i64>(2)? as u64,
+                user: r.get(3)?,
+                first_ticket: r.get::<_, i64>(4)? as u64,
+                last_ticket: r.get::<_, i64>(5)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Positions that changed hands in a round, oldest first. Deposits keep naming the buyer;
+    /// these say who holds the tickets now.
+    pub fn round_transfers(&self, round_id: u64) -> Result<Vec<TransferRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, round_id, from_user, to_user, amount, ticket_start, ticket_end
+             FROM transfers WHERE round_id = ?1 ORDER BY slot, rowid",
+        )?;
+        let rows = stmt.query_map([round_id as i64], |r| {
+            Ok(TransferRecord {
+                signature: r.get(0)?,
+                slot: r.get::<_, i64>(1)? as u64,
+                round_id: r.get::<_, i64>(2)? as u64,
+                from_user: r.get(3)?,
+                to_user: r.get(4)?,
+                amount: r.get::<_, i64>(5)? as u64,
+                ticket_start: r.get::<_, i64>(6)? as u64,
+                ticket_end: r.get::<_, i64>(7)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Full participation history of a wallet across rounds
+    pub fn user_deposits(&self, user: &str) -> Result<Vec<DepositRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{DEPOSIT_SELECT} WHERE user = ?1 ORDER BY round_id, ticket_start"
+        ))?;
+        let rows = stmt.query_map([user], deposit_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn user_payouts(&self, user: &str) -> Result<Vec<PayoutRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, round_id, user, kind, amount FROM payouts
+             WHERE user = ?1 ORDER BY slot",
+        )?;
+        let rows = stmt.query_map([user], |r| {
+            Ok(PayoutRecord {
+                signature: r.get(0)?,
+                slot: r.get::<_, i64>(1)? as u64,
+                round_id: r.get::<_, i64>(2)? as u64,
+                user: r.get(3)?,
+                kind: r.get(4)?,
+                amount: r.get::<_, i64>(5)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Roles and status rebuilt from the governance events; `None` until initialize is indexed
+    pub fn protocol(&self) -> Result<Option<ProtocolRecord>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT admin, pending_admin, keeper, guardian, treasurer, status FROM protocol WHERE id = 0",
+                [],
+                |r| {
+                    Ok(ProtocolRecord {
+                        admin: r.get(0)?,
+                        pending_admin: r.get(1)?,
+                        keeper: r.get(2)?,
+                        guardian: r.get(3)?,
+                        treasurer: r.get(4)?,
+                        status: r.get(5)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    pub fn config_changes(&self) -> Result<Vec<ConfigChangeRecord>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT change_id, change, execute_after, status FROM config_changes ORDER BY change_id")?;
+        let rows = stmt.query_map([], |r| {
+            Ok(ConfigChangeRecord {
+                change_id: r.get::<_, i64>(0)? as u64,
+                change: r.get(1)?,
+                execute_after: r.get(2)?,
+                status: r.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Wallet that holds `ticket` in `round_id`; `None` if it was never sold or was forfeited
+    pub fn ticket_owner(&self, round_id: u64, ticket: u64) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT COALESCE((SELECT to_user FROM transfers
+                                  WHERE round_id = ?1 AND ticket_start <= ?2 AND ticket_end >= ?2
+                                  ORDER BY slot DESC, rowid DESC LIMIT 1), user)
+                 FROM deposits
+                 WHERE round_id = ?1 AND ticket_start <= ?2 AND ticket_end >= ?2
+                   AND NOT EXISTS (SELECT 1 FROM forfeits
+                                   WHERE round_id = ?1 AND first_ticket <= ?2 AND last_ticket >= ?2)",
+                params![round_id as i64, ticket as i64],
+                |r| r.get(0),
+            )
+            .optional()?)
+    }
+}
+
+const ROUND_SELECT: &str = "SELECT round_id, status, start_time_ms, end_time_ms, stake_account, epoch,
+    total_tickets, total_staked, winner, winning_ticket, prize_amount, prize_claimed FROM rounds";
+
+const DEPOSIT_SELECT: &str =
+    "SELECT signature, slot, round_id, user, amount, ticket_start, ticket_end FROM deposits";
+
+fn round_from_row(r: &rusqlite::Row) -> rusqlite::Result<RoundRecord> {
+    Ok(RoundRecord {
+        round_id: r.get::<_, i64>(0)? as u64,
+        status: r.get(1)?,
+        start_time_ms: r.get::<_, Option<i64>>(2)?.map(|v| v as u64),
+        end_time_ms: r.get::<_, Option<i64>>(3)?.map(|v| v as u64),
+        stake_account: r.get(4)?,
+        epoch: r.get(5)?,
+        total_tickets: r.get::<_, i64>(6)? as u64,
+        total_staked: r.get::<_, i64>(7)? as u64,
+        winner: r.get(8)?,
+        winning_ticket: r.get::<_, Option<i64>>(9)?.map(|v| v as u64),
+        prize_amount: r.get::<_, Option<i64>>(10)?.map(|v| v as u64),
+        prize_claimed: r.get(11)?,
+    })
+}
+
+fn deposit_from_row(r: &rusqlite::Row) -> rusqlite::Result<DepositRecord> {
+    Ok(DepositRecord {
+        signature: r.get(0)?,
+        slot: r.get::<_, i64>(1)? as u64,
+        round_id: r.get::<_, i64>(2)? as u64,
+        user: r.get(3)?,
+        amount: r.get::<_, i64>(4)? as u64,
+        ticket_start: r.get::<_, i64>(5)? as u64,
+        ticket_end: r.get::<_, i64>(6)? as u64,
+    })
+}
+
+fn ensure_round(conn: &Connection, round_id: u64) -> rusqlite::Result<()> {
+    conn.execute("INSERT OR IGNORE INTO rounds (round_id) VALUES (?1)", [round_id as i64])?;
+    Ok(())
+}
+
+fn apply_event(conn: &Connection, ev: &IndexedEvent) -> rusqlite::Result<()> {
+    let sig = &ev.signature;
+    let slot = ev.slot as i64;
+    match &ev.event {
+        RafaEvent::PrizeSeeded(e) => {
+            conn.execute(
+                "INSERT INTO prize_seeds (signature, slot, treasurer, amount) VALUES (?1, ?2, ?3, ?4)",
+                params![sig, slot, e.treasurer.to_string(), e.amount as i64],
+            )?;
+        }
+        RafaEvent::RoundStarted(e) => {
+            ensure_round(conn, e.round_id)?;
+            conn.execute(
+                "UPDATE rounds SET start_time_ms = ?2, stake_account = ?3 WHERE round_id = ?1",
+                params![e.round_id as i64, e.start_time_ms as i64, e.stake_account.to_string()],
+            )?;
+        }
+        RafaEvent::DepositMade(e) => {
+            ensure_round(conn, e.round_id)?;
+            conn.execute(
+                "INSERT INTO deposits (signature, slot, round_id, user, amount, ticket_start, ticket_end)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    sig,
+                    slot,
+                    e.round_id as i64,
+                    e.user.to_string(),
+                    e.amount as i64,
+                    e.ticket_start as i64,
+                    e.ticket_end as i64
+                ],
+            )?;
+            conn.execute(
+                "UPDATE rounds SET total_tickets = ?2, total_staked = ?3 WHERE round_id = ?1",
+                params![e.round_id as i64, e.total_tickets_sold as i64, e.total_staked_lamports as i64],
+            )?;
+        }
+        // Stake carried over from an earlier round buys its tickets like a deposit
+        RafaEvent::PositionRolledOver(e) => {
+            ensure_round(conn, e.round_id)?;
+            conn.execute(
+                "INSERT INTO deposits (signature, slot, round_id, user, amount, ticket_start, ticket_end)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    sig,
+                    slot,
+                    e.round_id as i64,
+                    e.user.to_string(),
+                    e.amount as i64,
+                    e.ticket_start as i64,
+                    e.ticket_end as i64
+                ],
+            )?;
+            conn.execute(
+                "UPDATE rounds SET total_tickets = ?2, total_staked = ?3 WHERE round_id = ?1",
+                params![e.round_id as i64, e.total_tickets_sold as i64, e.total_staked_lamports as i64],
+            )?;
+        }
+        // The DepositMade next to it already credits the beneficiary; who paid stays in `events`
+        RafaEvent::DepositFunded(_) => {}
+        RafaEvent::PositionTransferred(e) => {
+            conn.execute(
+                "INSERT INTO transfers (signature, slot, round_id, from_user, to_user, amount, ticket_start, ticket_end)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    sig,
+                    slot,
+                    e.round_id as i64,
+                    e.from.to_string(),
+                    e.to.to_string(),
+                    e.amount as i64,
+                    e.ticket_start as i64,
+                    e.ticket_end as i64
+                ],
+            )?;
+        }
+        // The DepositMade next to it credits the receipt's mint; who holds it stays in `events`
+        RafaEvent::ReceiptMinted(_) => {}
+        RafaEvent::ReceiptRedeemed(e) => {
+            let total = e.stake_amount.saturating_add(e.prize_amount);
+            insert_payout(conn, sig, slot, e.round_id, &e.holder.to_string(), "receipt", total)?;
+            if e.prize_amount > 0 {
+                conn.execute("UPDATE rounds SET prize_claimed = 1 WHERE round_id = ?1", [e.round_id as i64])?;
+            }
+        }
+        RafaEvent::WithdrawalRequested(e) => {
+            conn.execute(
+                "INSERT INTO withdrawal_requests (signature, slot, round_id, user, amount)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![sig, slot, e.round_id as i64, e.user.to_string(), e.amount as i64],
+            )?;
+        }
+        RafaEvent::EpochAdvanced(e) => {
+            ensure_round(conn, e.round_id)?;
+            conn.execute(
+                "UPDATE rounds SET epoch = ?2 WHERE round_id = ?1",
+                params![e.round_id as i64, e.to_epoch],
+            )?;
+        }
+        RafaEvent::RoundFinalized(e) => {
+            ensure_round(conn, e.round_id)?;
+            conn.execute(
+                "UPDATE rounds SET status = 'finalized', winner = ?2, winning_ticket = ?3,
+                     total_tickets = ?4, total_staked = ?5, prize_amount = ?6, end_time_ms = ?7
+                 WHERE round_id = ?1",
+                params![
+                    e.round_id as i64,
+                    e.winner.to_string(),
+                    e.winning_ticket as i64,
+                    e.total_tickets_sold as i64,
+                    e.total_staked_lamports as i64,
+                    e.prize_amount as i64,
+                    e.end_time_ms as i64
+                ],
+            )?;
+        }
+        RafaEvent::ClaimTicketCreated(e) => {
+            conn.execute(
+                "INSERT OR REPLACE INTO claim_tickets (round_id, winner, prize_amount, stake_amount)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![e.round_id as i64, e.winner.to_string(), e.prize_amount as i64, e.stake_amount as i64],
+            )?;
+        }
+        RafaEvent::PrizeClaimed(e) => {
+            let total = e.stake_amount.saturating_add(e.prize_amount);
+            insert_payout(conn, sig, slot, e.round_id, &e.winner.to_string(), "prize", total)?;
+            conn.execute(
+                "UPDATE claim_tickets SET claimed = 1 WHERE round_id = ?1 AND winner = ?2",
+                params![e.round_id as i64, e.winner.to_string()],
+            )?;
+            conn.execute("UPDATE rounds SET prize_claimed = 1 WHERE round_id = ?1", [e.round_id as i64])?;
+        }
+        RafaEvent::WithdrawalProcessed(e) => {
+            insert_payout(conn, sig, slot, e.round_id, &e.user.to_string(), "withdrawal", e.amount)?;
+        }
+        RafaEvent::RoundCancelled(e) => {
+            ensure_round(conn, e.round_id)?;
+            conn.execute("UPDATE rounds SET status = 'cancelled' WHERE round_id = ?1", [e.round_id as i64])?;
+        }
+        RafaEvent::RefundProcessed(e) => {
+            insert_payout(conn, sig, slot, e.round_id, &e.user.to_string(), "refund", e.amount)?;
+        }
+        RafaEvent::TicketsForfeited(e) => {
+            conn.execute(
+                "INSERT INTO forfeits (signature, slot, round_id, user, first_ticket, last_ticket)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    sig,
+                    slot,
+                    e.round_id as i64,
+                    e.user.to_string(),
+                    e.first_ticket as i64,
+                    e.last_ticket as i64
+                ],
+            )?;
+        }
+        RafaEvent::InstantWithdrawal(e) => {
+            let net = e.amount.saturating_sub(e.exit_fee);
+            insert_payout(conn, sig, slot, e.round_id, &e.user.to_string(), "instant", net)?;
+        }
+        RafaEvent::ProtocolInitialized(e) => {
+            let admin = e.admin.to_string();
+            conn.execute(
+                "INSERT OR REPLACE INTO protocol (id, admin, keeper, guardian, treasurer) VALUES (0, ?1, ?1, ?1, ?1)",
+                [admin],
+            )?;
+        }
+        RafaEvent::ProtocolStatusChanged(e) => {
+            conn.execute("UPDATE protocol SET status = ?1 WHERE id = 0", [format!("{:?}", e.status)])?;
+        }
+        RafaEvent::RoleChanged(e) => {
+            let column = match e.role {
+                ProtocolRole::Keeper => "keeper",
+                ProtocolRole::Guardian => "guardian",
+                ProtocolRole::Treasurer => "treasurer",
+            };
+            conn.execute(&format!("UPDATE protocol SET {column} = ?1 WHERE id = 0"), [e.key.to_string()])?;
+        }
+        RafaEvent::AdminProposed(e) => {
+            conn.execute("UPDATE protocol SET pending_admin = ?1 WHERE id = 0", [e.pending_admin.to_string()])?;
+        }
+        RafaEvent::AdminChanged(e) => {
+            conn.execute("UPDATE protocol SET admin = ?1, pending_admin = NULL WHERE id = 0", [e.admin.to_string()])?;
+        }
+        RafaEvent::ChangeProposed(e) => {
+            conn.execute(
+                "INSERT OR REPLACE INTO config_changes (change_id, change, execute_after) VALUES (?1, ?2, ?3)",
+                params![e.id as i64, format!("{:?}", e.change), e.execute_after],
+            )?;
+        }
+        RafaEvent::ChangeExecuted(e) => {
+            conn.execute("UPDATE config_changes SET status = 'executed' WHERE change_id = ?1", [e.id as i64])?;
+        }
+        RafaEvent::ChangeCancelled(e) => {
+            conn.execute("UPDATE config_changes SET status = 'cancelled' WHERE change_id = ?1", [e.id as i64])?;
+        }
+        RafaEvent::WindDownBegun(e) => {
+            conn.execute("UPDATE protocol SET status = ?1 WHERE id = 0", [format!("{:?}", e.status)])?;
+        }
+        RafaEvent::ProtocolClosed(_) => {
+            conn.execute("UPDATE protocol SET status = 'Closed', pending_admin = NULL WHERE id = 0", [])?;
+        }
+        // The multisig's history stays in `events`; what it executes shows up as its own events
+        RafaEvent::ChangeQueueCreated(_)
+        | RafaEvent::MultisigOwnersChanged(_)
+        | RafaEvent::MultisigTxProposed(_)
+        | RafaEvent::MultisigTxApproved(_)
+        | RafaEvent::MultisigTxExecuted(_) => {}
+        // Closing, migrating or reconciling an account changes nothing the history tables hold;
+        // rounds and claim tickets stay queryable after their accounts are gone
+        RafaEvent::RoundClosed(_)
+        | RafaEvent::ClaimTicketClosed(_)
+        | RafaEvent::WithdrawalQueueClosed(_)
+        | RafaEvent::UserAccountClosed(_)
+        | RafaEvent::AccountMigrated(_)
+        | RafaEvent::RoundReconciled(_)
+        | RafaEvent::LiabilitiesReconciled(_) => {}
+    }
+    Ok(())
+}
+
+fn insert_payout(
+    conn: &Connection,
+    sig: &str,
+    slot: i64,
+    round_id: u64,
+    user: &str,
+    kind: &str,
+    amount: u64,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO payouts (signature, slot, round_id, user, kind, amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![sig, slot, round_id as i64, user, kind, amount as i64],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::prelude::Pubkey;
+    use rafa::{
+        AdminChanged, AdminProposed, DepositMade, PositionTransferred, ProtocolClosed, ProtocolInitialized,
+        ProtocolStatus, ProtocolStatusChanged, RoleChanged, RoundClosed, TicketsForfeited, WindDownBegun,
+    };
+
+    use super::*;
+
+    fn indexed(signature: &str, slot: u64, events: Vec<RafaEvent>) -> Vec<IndexedEvent> {
+        events
+            .into_iter()
+            .enumerate()
+            .map(|(index, event)| IndexedEvent {
+                signature: signature.to_string(),
+                slot,
+                block_time: None,
+                index: index as u32,
+                event,
+            })
+            .collect()
+    }
+
+    fn deposit(user: Pubkey, ticket_start: u64, ticket_end: u64) -> RafaEvent {
+        let tickets = ticket_end - ticket_start + 1;
+        RafaEvent::DepositMade(DepositMade {
+            round_id: 0,
+            user,
+            amount: tickets * rafa::TICKET_PRICE_LAMPORTS,
+            tickets,
+            ticket_start,
+            ticket_end,
+            total_tickets_sold: ticket_end + 1,
+            total_staked_lamports: (ticket_end + 1) * rafa::TICKET_PRICE_LAMPORTS,
+        })
+    }
+
+    #[test]
+    fn replaying_a_transaction_changes_nothing() {
+        let mut store = Store::open_in_memory().unwrap();
+        let alice = Pubkey::new_unique();
+        let tx = indexed("a", 1, vec![deposit(alice, 0, 1)]);
+
+        assert_eq!(store.apply(&tx).unwrap(), 1);
+        assert_eq!(store.apply(&tx).unwrap(), 0);
+        assert_eq!(store.round_deposits(0).unwrap().len(), 1);
+        assert_eq!(store.round(0).unwrap().unwrap().total_tickets, 2);
+        assert_eq!(store.user_deposits(&alice.to_string()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn ticket_owner_follows_transfers_and_forfeits() {
+        let mut store = Store::open_in_memory().unwrap();
+        let (alice, bob, carol) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        store.apply(&indexed("a", 1, vec![deposit(alice, 0, 2), deposit(bob, 3, 3)])).unwrap();
+        let forfeit = RafaEvent::TicketsForfeited(TicketsForfeited {
+            round_id: 0,
+            user: alice,
+            first_ticket: 2,
+            last_ticket: 2,
+            total_tickets_sold: 4,
+            voided_tickets: 1,
+        });
+        store.apply(&indexed("b", 2, vec![forfeit])).unwrap();
+        let transfer = RafaEvent::PositionTransferred(PositionTransferred {
+            round_id: 0,
+            from: alice,
+            to: carol,
+            amount: 2 * rafa::TICKET_PRICE_LAMPORTS,
+            ticket_start: 0,
+            ticket_end: 1,
+        });
+        store.apply(&indexed("c", 3, vec![transfer])).unwrap();
+
+        assert_eq!(store.ticket_owner(0, 0).unwrap(), Some(carol.to_string()));
+        assert_eq!(store.ticket_owner(0, 1).unwrap(), Some(carol.to_string()));
+        assert_eq!(store.ticket_owner(0, 2).unwrap(), None);
+        assert_eq!(store.ticket_owner(0, 3).unwrap(), Some(bob.to_string()));
+        assert_eq!(store.ticket_owner(0, 4).unwrap(), None);
+    }
+
+    #[test]
+    fn roles_and_status_follow_governance_events() {
+        let mut store = Store::open_in_memory().unwrap();
+        let (admin, guardian, next_admin) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let events = vec![
+            RafaEvent::ProtocolInitialized(ProtocolInitialized { admin, validator: Pubkey::new_unique() }),
+            RafaEvent::RoleChanged(RoleChanged { role: ProtocolRole::Guardian, previous: admin, key: guardian }),
+            RafaEvent::ProtocolStatusChanged(ProtocolStatusChanged {
+                authority: guardian,
+                previous: ProtocolStatus::Active,
+                status: ProtocolStatus::Frozen,
+            }),
+            RafaEvent::AdminProposed(AdminProposed { admin, pending_admin: next_admin }),
+        ];
+        store.apply(&indexed("a", 1, events)).unwrap();
+        let protocol = store.protocol().unwrap().unwrap();
+        assert_eq!(protocol.pending_admin, Some(next_admin.to_string()));
+        assert_eq!((protocol.keeper, protocol.guardian), (admin.to_string(), guardian.to_string()));
+        assert_eq!(protocol.status, "Frozen");
+
+        let accepted = RafaEvent::AdminChanged(AdminChanged { previous: admin, admin: next_admin });
+        store.apply(&indexed("b", 2, vec![accepted])).unwrap();
+        let protocol = store.protocol().unwrap().unwrap();
+        assert_eq!((protocol.admin, protocol.pending_admin), (next_admin.to_string(), None));
+    }
+
+    #[test]
+    fn teardown_closes_the_protocol_and_keeps_round_history() {
+        let mut store = Store::open_in_memory().unwrap();
+        let admin = Pubkey::new_unique();
+        let events = vec![
+            RafaEvent::ProtocolInitialized(ProtocolInitialized { admin, validator: Pubkey::new_unique() }),
+            deposit(Pubkey::new_unique(), 0, 1),
+            RafaEvent::WindDownBegun(WindDownBegun { admin, status: ProtocolStatus::WithdrawOnly, closable_at: 360 }),
+        ];
+        store.apply(&indexed("a", 1, events)).unwrap();
+        assert_eq!(store.protocol().unwrap().unwrap().status, "WithdrawOnly");
+
+        let events = vec![
+            RafaEvent::RoundClosed(RoundClosed { round_id: 0, rent_receiver: admin, rent: 1 }),
+            RafaEvent::ProtocolClosed(ProtocolClosed { admin, returned: 1 }),
+        ];
+        store.apply(&indexed("b", 2, events)).unwrap();
+        assert_eq!(store.protocol().unwrap().unwrap().status, "Closed");
+        assert_eq!(store.round_deposits(0).unwrap().len(), 1);
+    }
+}
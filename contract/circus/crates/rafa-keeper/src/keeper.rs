@@ -0,0 +1,237 @@
+//! One keeper pass: read the current round, derive its phase and submit whatever transaction
+//! moves it forward. Every step is safe to repeat, so a failed or interrupted pass is simply
+//! retried on the next tick.
+
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{ensure, Context, Result};
+use rafa::{UserAccount, MAX_ROUND_PARTICIPANTS, MAX_WITHDRAWALS_PER_BATCH};
+use rafa_client::accounts::ClaimTicket;
+use rafa_client::{instructions, pda, RpcClient};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use tracing::{debug, info, warn};
+
+use crate::phase::{snapshot_index, Phase};
+
+pub struct Config {
+    /// UserAccounts per `take_snapshot_batch` transaction
+    pub snapshot_batch: usize,
+    /// Attempts per transaction before the pass gives up
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every further attempt
+    pub retry_base: Duration,
+}
+
+pub struct Keeper {
+    rpc: RpcClient,
+    keypair: Keypair,
+    config: Config,
+}
+
+impl Keeper {
+    pub fn new(rpc: RpcClient, keypair: Keypair, config: Config) -> Self {
+        Self { rpc, keypair, config }
+    }
+
+    /// Run one pass over the current round and the one before it
+    pub fn tick(&self) -> Result<()> {
+        let protocol = self.rpc.protocol_state()?;
+        let round_id = protocol.current_round;
+        if round_id == 0 {
+            warn!("no round initialized yet; run init_round first");
+            return Ok(());
+        }
+
+        // The crank that finalizes a round also opens the next one, so the previous round's
+        // claim ticket is created on the following pass
+        if round_id > 1 {
+            self.ensure_claim_ticket(round_id - 1, &protocol.treasurer)?;
+        }
+        if round_id > 0 {
+            self.pay_withdrawal_queue(round_id - 1)?;
+        }
+
+        let round = self
+            .rpc
+            .round(round_id)?
+            .with_context(|| format!("round {round_id} is current but does not exist"))?;
+        let now_ms = self.rpc.cluster_time_ms()?;
+        let phase = Phase::of(&round, now_ms);
+        info!(
+            round_id,
+            phase = phase.name(),
+            epoch = round.epoch_in_round,
+            tickets = round.total_tickets_sold,
+            staked = round.total_staked_lamports,
+            "round status"
+        );
+
+        match phase {
+            Phase::Running { epoch, remaining_ms } => {
+                debug!(round_id, epoch, remaining_secs = remaining_ms / 1000, "epoch running");
+            }
+            Phase::EpochEnded { epoch } => {
+                self.snapshot(round_id, epoch)?;
+                self.send("advance_epoch", round_id, instructions::advance_epoch(&self.keypair.pubkey(), round_id))?;
+            }
+            Phase::ReadyToFinalize => {
+                self.snapshot(round_id, round.epoch_in_round)?;
+                self.finalize(round_id)?;
+            }
+            Phase::Empty => {
+                debug!(round_id, "last epoch over without tickets; waiting for a deposit");
+            }
+            Phase::Finalized { winner } => {
+                info!(round_id, %winner, winning_ticket = round.winning_ticket, "round finalized");
+                self.ensure_claim_ticket(round_id, &protocol.treasurer)?;
+                self.pay_withdrawal_queue(round_id)?;
+                self.open_next_round(round_id)?;
+            }
+            Phase::Cancelled => {
+                self.pay_withdrawal_queue(round_id)?;
+                self.open_next_round(round_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record `epoch`'s balance for every participant still missing it. Only the accounts that
+    /// lack the snapshot bit are sent, so an interrupted pass resumes with the remaining pages.
+    fn snapshot(&self, round_id: u64, epoch: u8) -> Result<()> {
+        let mask_bit = 1u8 << snapshot_index(epoch);
+        let pending: Vec<Pubkey> = self
+            .rpc
+            .participants(round_id)?
+            .into_iter()
+            .filter(|(_, user)| user.snapshots_recorded_mask & mask_bit == 0)
+            .map(|(key, _)| key)
+            .collect();
+        if pending.is_empty() {
+            debug!(round_id, epoch, "snapshots already recorded");
+            return Ok(());
+        }
+
+        let pages = pending.len().div_ceil(self.config.snapshot_batch);
+        for (page, batch) in pending.chunks(self.config.snapshot_batch).enumerate() {
+            info!(round_id, epoch, page = page + 1, pages, accounts = batch.len(), "taking snapshot batch");
+            self.send("take_snapshot_batch", round_id, instructions::take_snapshot_batch(round_id, batch))?;
+        }
+        Ok(())
+    }
+
+    /// Crank with every participant so the program can find the winning ticket's owner. The
+    /// program caps a round at MAX_ROUND_PARTICIPANTS, which one transaction carries; only a round
+    /// opened before the cap could hold more, and then the pass stops with an error.
+    fn finalize(&self, round_id: u64) -> Result<()> {
+        let participants: Vec<Pubkey> = self.participant_keys(round_id)?;
+        ensure!(
+            participants.len() <= MAX_ROUND_PARTICIPANTS as usize,
+            "round {round_id} has {} participants, more than the {MAX_ROUND_PARTICIPANTS} one crank transaction \
+             carries",
+            participants.len()
+        );
+        info!(round_id, participants = participants.len(), "cranking to finalize");
+        self.send("crank", round_id, instructions::crank(round_id, &participants))
+    }
+
+    /// A crank on a complete round opens the next one funded by the treasury
+    fn open_next_round(&self, round_id: u64) -> Result<()> {
+        if self.rpc.round(round_id + 1)?.is_some() {
+            return Ok(());
+        }
+        info!(round_id, next_round_id = round_id + 1, "opening next round");
+        self.send("crank", round_id, instructions::crank(round_id, &[]))
+    }
+
+    /// Create the winner's claim ticket when this keeper holds the treasurer role. A winning
+    /// receipt needs none: redeem_receipt pays its holder the prize.
+    fn ensure_claim_ticket(&self, round_id: u64, treasurer: &Pubkey) -> Result<()> {
+        let Some(round) = self.rpc.round(round_id)? else {
+            return Ok(());
+        };
+        let Some(winner) = round.winner else {
+            return Ok(());
+        };
+        let position = self.rpc.user(&winner)?;
+        if position.as_ref().is_some_and(|user: &UserAccount| user.is_receipt) {
+            debug!(round_id, %winner, "receipt won; its holder collects the prize by redeeming it");
+            return Ok(());
+        }
+        let ticket = pda::claim_ticket(round_id, &winner).0;
+        if self.rpc.account::<ClaimTicket>(&ticket)?.is_some() {
+            return Ok(());
+        }
+        if *treasurer != self.keypair.pubkey() {
+            debug!(round_id, %winner, "claim ticket missing; left to the treasurer or the winner");
+            return Ok(());
+        }
+
+        let stake = position
+            .filter(|user| user.round_joined == round_id)
+            .map_or(0, |user| user.balance + user.pending_withdrawal_amount);
+        info!(round_id, %winner, prize = round.total_prize_lamports, stake, "creating claim ticket");
+        self.send(
+            "create_claim_ticket",
+            round_id,
+            instructions::create_claim_ticket(&self.keypair.pubkey(), round_id, &winner, round.total_prize_lamports, stake),
+        )
+    }
+
+    /// Pay a complete round's queued withdrawals, one batch per transaction from the queue's
+    /// head. Stops early when a batch makes no progress: the protocol lacks the liquidity for the
+    /// next request and a later pass retries.
+    fn pay_withdrawal_queue(&self, round_id: u64) -> Result<()> {
+        let mut head = None;
+        loop {
+            let Some(queue) = self.rpc.withdrawal_queue(round_id)? else {
+                return Ok(());
+            };
+            let waiting = &queue.entries[queue.head as usize..];
+            if waiting.is_empty() {
+                return Ok(());
+            }
+            if head == Some(queue.head) {
+                warn!(round_id, head = queue.head, waiting = waiting.len(), "withdrawal queue waiting for liquidity");
+                return Ok(());
+            }
+            head = Some(queue.head);
+
+            let batch = &waiting[..waiting.len().min(MAX_WITHDRAWALS_PER_BATCH)];
+            info!(round_id, head = queue.head, batch = batch.len(), waiting = waiting.len(), "paying queued withdrawals");
+            self.send("process_withdrawals_batch", round_id, instructions::process_withdrawals_batch(round_id, batch))?;
+        }
+    }
+
+    fn participant_keys(&self, round_id: u64) -> Result<Vec<Pubkey>> {
+        Ok(self.rpc.participants(round_id)?.into_iter().map(|(key, _)| key).collect())
+    }
+
+    /// Send with exponential backoff. Each attempt is re-signed with a fresh blockhash.
+    fn send(&self, action: &str, round_id: u64, ix: Instruction) -> Result<()> {
+        let mut delay = self.config.retry_base;
+        let mut attempt = 1;
+        loop {
+            match self.try_send(&ix) {
+                Ok(signature) => {
+                    info!(round_id, action, %signature, "transaction confirmed");
+                    return Ok(());
+                }
+                Err(err) if attempt < self.config.max_attempts => {
+                    warn!(round_id, action, attempt, error = %err, retry_in_ms = delay.as_millis() as u64, "transaction failed");
+                    thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err.context(format!("{action} for round {round_id}"))),
+            }
+        }
+    }
+
+    fn try_send(&self, ix: &Instruction) -> Result<Signature> {
+        self.rpc.send_and_confirm(std::slice::from_ref(ix), &[&self.keypair])
+    }
+}
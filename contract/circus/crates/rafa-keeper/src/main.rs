@@ -0,0 +1,100 @@
+//! Keeper daemon for the rafa program, replacing `bot/raffle-bot.ts`.
+//!
+//! Every tick it reads the current round, derives its phase (see `phase.rs`) and submits the
+//! transaction that moves it forward: per-epoch snapshots, `advance_epoch`, the finalizing
+//! crank, the winner's claim ticket and the next round.
+
+mod keeper;
+mod phase;
+
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+use rafa_client::RpcClient;
+use solana_sdk::signature::read_keypair_file;
+use solana_sdk::signer::Signer;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+
+use crate::keeper::{Config, Keeper};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Parser)]
+#[command(name = "rafa-keeper", about = "Drive rafa rounds through their epochs")]
+struct Cli {
+    #[arg(long, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+    /// Keeper keypair; it also creates claim tickets when it holds the treasurer role
+    #[arg(long, default_value = "~/.config/solana/id.json")]
+    keypair: String,
+    #[arg(long, default_value_t = 30)]
+    interval_secs: u64,
+    /// UserAccounts per take_snapshot_batch transaction
+    #[arg(long, default_value_t = 20)]
+    snapshot_batch: usize,
+    #[arg(long, default_value_t = 5)]
+    max_attempts: u32,
+    #[arg(long, default_value_t = 500)]
+    retry_base_ms: u64,
+    /// Run a single pass and exit
+    #[arg(long)]
+    once: bool,
+    #[arg(long, value_enum, default_value = "text")]
+    log_format: LogFormat,
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    match cli.log_format {
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        LogFormat::Json => tracing_subscriber::fmt().json().with_env_filter(filter).init(),
+    }
+
+    let keypair_path = expand_home(&cli.keypair);
+    let keypair = read_keypair_file(&keypair_path)
+        .map_err(|e| anyhow!("read keypair {}: {e}", keypair_path.display()))?;
+    if cli.snapshot_batch == 0 {
+        return Err(anyhow!("--snapshot-batch must be at least 1"));
+    }
+    info!(keeper = %keypair.pubkey(), rpc_url = %cli.rpc_url, interval_secs = cli.interval_secs, "keeper started");
+
+    let keeper = Keeper::new(
+        RpcClient::new(cli.rpc_url),
+        keypair,
+        Config {
+            snapshot_batch: cli.snapshot_batch,
+            max_attempts: cli.max_attempts.max(1),
+            retry_base: Duration::from_millis(cli.retry_base_ms),
+        },
+    );
+
+    loop {
+        // A failed pass is logged and retried on the next tick; only `--once` surfaces it
+        match keeper.tick() {
+            Ok(()) => {}
+            Err(err) if cli.once => return Err(err),
+            Err(err) => error!(error = format!("{err:#}"), "keeper pass failed"),
+        }
+        if cli.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(cli.interval_secs));
+    }
+}
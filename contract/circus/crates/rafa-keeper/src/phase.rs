@@ -0,0 +1,138 @@
+//! Per-round state machine. The phase is derived purely from the on-chain `RoundState` and the
+//! cluster clock, so a restarted keeper picks up exactly where the previous one stopped.
+
+use anchor_lang::prelude::Pubkey;
+use rafa::{RoundState, EPOCH_DURATION_SECONDS};
+
+pub const EPOCH_MS: u64 = EPOCH_DURATION_SECONDS as u64 * 1000;
+pub const EPOCHS_PER_ROUND: u8 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// `epoch` is still running
+    Running { epoch: u8, remaining_ms: u64 },
+    /// `epoch` (1 or 2) is over: snapshot its balances, then advance
+    EpochEnded { epoch: u8 },
+    /// The last epoch is over and tickets are held: snapshot, then crank to draw the winner
+    ReadyToFinalize,
+    /// The last epoch is over but nobody holds a ticket; the round waits for a deposit
+    Empty,
+    /// Winner drawn; the claim ticket and the next round may still be missing
+    Finalized { winner: Pubkey },
+    /// Cancelled by the admin; only the next round is left to open
+    Cancelled,
+}
+
+impl Phase {
+    pub fn of(round: &RoundState, now_ms: u64) -> Self {
+        if round.is_cancelled {
+            return Phase::Cancelled;
+        }
+        if let Some(winner) = round.winner {
+            return Phase::Finalized { winner };
+        }
+        let epoch = round.epoch_in_round.max(1);
+        let epoch_end = round.start_epoch + u64::from(epoch) * EPOCH_MS;
+        if now_ms < epoch_end {
+            return Phase::Running { epoch, remaining_ms: epoch_end - now_ms };
+        }
+        if epoch < EPOCHS_PER_ROUND {
+            Phase::EpochEnded { epoch }
+        } else if round.live_tickets() > 0 {
+            Phase::ReadyToFinalize
+        } else {
+            Phase::Empty
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Running { .. } => "running",
+            Phase::EpochEnded { .. } => "epoch_ended",
+            Phase::ReadyToFinalize => "ready_to_finalize",
+            Phase::Empty => "empty",
+            Phase::Finalized { .. } => "finalized",
+            Phase::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Index into `UserAccount::snapshot_balances` for a 1-based epoch
+pub fn snapshot_index(epoch: u8) -> usize {
+    usize::from(epoch.clamp(1, EPOCHS_PER_ROUND) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AnchorDeserialize;
+
+    use super::*;
+
+    const START_MS: u64 = 1_000_000;
+
+    fn round(epoch_in_round: u8, total_tickets_sold: u64) -> RoundState {
+        let mut round = RoundState::deserialize(&mut &[0u8; RoundState::SIZE][..]).unwrap();
+        round.epoch_in_round = epoch_in_round;
+        round.start_epoch = START_MS;
+        round.total_tickets_sold = total_tickets_sold;
+        round
+    }
+
+    #[test]
+    fn running_until_the_epoch_ends() {
+        assert_eq!(Phase::of(&round(1, 1), START_MS), Phase::Running { epoch: 1, remaining_ms: EPOCH_MS });
+        let second = Phase::of(&round(2, 1), START_MS + EPOCH_MS + 1);
+        assert_eq!(second, Phase::Running { epoch: 2, remaining_ms: EPOCH_MS - 1 });
+        // A round that never advanced counts as epoch 1
+        assert_eq!(Phase::of(&round(0, 0), START_MS + EPOCH_MS - 1), Phase::Running { epoch: 1, remaining_ms: 1 });
+    }
+
+    #[test]
+    fn epochs_end_exactly_on_the_boundary() {
+        assert_eq!(Phase::of(&round(1, 1), START_MS + EPOCH_MS), Phase::EpochEnded { epoch: 1 });
+        assert_eq!(Phase::of(&round(2, 1), START_MS + 2 * EPOCH_MS), Phase::EpochEnded { epoch: 2 });
+        // Missed epochs are caught up one advance at a time
+        assert_eq!(Phase::of(&round(1, 1), START_MS + 5 * EPOCH_MS), Phase::EpochEnded { epoch: 1 });
+    }
+
+    #[test]
+    fn last_epoch_over_finalizes_only_with_live_tickets() {
+        let end = START_MS + 3 * EPOCH_MS;
+        assert_eq!(Phase::of(&round(3, 2), end - 1), Phase::Running { epoch: 3, remaining_ms: 1 });
+        assert_eq!(Phase::of(&round(3, 2), end), Phase::ReadyToFinalize);
+        assert_eq!(Phase::of(&round(3, 0), end), Phase::Empty);
+
+        let mut forfeited = round(3, 2);
+        forfeited.voided_tickets = 2;
+        assert_eq!(Phase::of(&forfeited, end), Phase::Empty);
+    }
+
+    #[test]
+    fn a_winner_finalizes_the_round() {
+        let winner = Pubkey::new_unique();
+        let mut done = round(3, 2);
+        done.winner = Some(winner);
+        done.is_complete = true;
+        assert_eq!(Phase::of(&done, START_MS), Phase::Finalized { winner });
+    }
+
+    #[test]
+    fn cancellation_wins_over_everything_else() {
+        let mut cancelled = round(3, 2);
+        cancelled.is_cancelled = true;
+        cancelled.is_complete = true;
+        assert_eq!(Phase::of(&cancelled, START_MS), Phase::Cancelled);
+        assert_eq!(Phase::of(&cancelled, START_MS + 3 * EPOCH_MS), Phase::Cancelled);
+
+        cancelled.winner = Some(Pubkey::new_unique());
+        assert_eq!(Phase::of(&cancelled, START_MS), Phase::Cancelled);
+    }
+
+    #[test]
+    fn snapshot_slots_are_zero_based() {
+        assert_eq!(snapshot_index(1), 0);
+        assert_eq!(snapshot_index(3), 2);
+        assert_eq!(snapshot_index(0), 0);
+        assert_eq!(snapshot_index(9), 2);
+    }
+}
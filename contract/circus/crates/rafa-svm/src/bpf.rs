@@ -0,0 +1,159 @@
+//! The `bpf` mode: the compiled program run by LiteSVM. Signatures aren't verified, so signers
+//! are taken from the account metas as in the native mode, and a payer of its own covers every
+//! fee so wallet balances move exactly as the program moves them.
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::instruction::{Instruction, InstructionError};
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::{system_program, AccountDeserialize, AccountSerialize};
+use litesvm::types::TransactionMetadata;
+use litesvm::LiteSVM;
+use solana_sdk::account::{Account, ReadableAccount};
+use solana_sdk::message::Message;
+use solana_sdk::transaction::{Transaction, TransactionError as RuntimeError};
+
+use crate::{check_size, genesis_clock, Outcome, TransactionError, TransactionResult, MS_PER_SLOT};
+
+/// Where `anchor build` leaves the program
+const DEFAULT_PROGRAM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/deploy/rafa.so");
+
+pub struct Svm {
+    svm: LiteSVM,
+    clock: Clock,
+    payer: Pubkey,
+}
+
+impl Default for Svm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        let mut svm = LiteSVM::new().with_sigverify(false).with_blockhash_check(false).with_transaction_history(0);
+        let program = std::env::var("RAFA_PROGRAM_SO").unwrap_or_else(|_| DEFAULT_PROGRAM.to_string());
+        svm.add_program_from_file(rafa::ID, &program)
+            .unwrap_or_else(|err| panic!("load {program} (run `anchor build` first): {err}"));
+        let clock = genesis_clock();
+        svm.set_sysvar(&clock);
+        let payer = Pubkey::new_unique();
+        let mut svm = Self { svm, clock, payer };
+        svm.airdrop(&payer, u64::MAX / 4);
+        svm
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Current cluster time in milliseconds, the unit rafa stores round times in
+    pub fn now_ms(&self) -> u64 {
+        self.clock.unix_timestamp as u64 * 1000
+    }
+
+    /// Move the clock forward, advancing slots at the target slot time
+    pub fn warp(&mut self, seconds: i64) {
+        self.clock.unix_timestamp += seconds;
+        self.clock.slot += (seconds * 1000 / MS_PER_SLOT) as u64;
+        self.svm.set_sysvar(&self.clock);
+    }
+
+    pub fn account(&self, address: &Pubkey) -> Option<Account> {
+        self.svm.get_account(address).filter(|account| account.lamports > 0)
+    }
+
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.svm.set_account(address, account).expect("set account");
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.account(address).map_or(0, |a| a.lamports)
+    }
+
+    /// Credit a system account, creating it if needed
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        let mut account = self.account(address).unwrap_or_else(|| Account::new(0, 0, &system_program::ID));
+        account.lamports += lamports;
+        self.set_account(*address, account);
+    }
+
+    /// A fresh funded wallet
+    pub fn new_wallet(&mut self, lamports: u64) -> Pubkey {
+        let wallet = Pubkey::new_unique();
+        self.airdrop(&wallet, lamports);
+        wallet
+    }
+
+    /// Decode a program account, `None` if missing or of another type
+    pub fn get<T: AccountDeserialize>(&self, address: &Pubkey) -> Option<T> {
+        let account = self.account(address)?;
+        T::try_deserialize(&mut account.data.as_slice()).ok()
+    }
+
+    /// Overwrite a program account's state, for setting up situations no instruction sequence
+    /// can reach
+    pub fn write<T: AccountSerialize>(&mut self, address: &Pubkey, state: &T) {
+        let mut account = self.account(address).expect("account to overwrite does not exist");
+        let mut data = Vec::with_capacity(account.data.len());
+        state.try_serialize(&mut data).expect("serialize account");
+        account.data[..data.len()].copy_from_slice(&data);
+        self.set_account(*address, account);
+    }
+
+    /// Every rafa account of type `T`
+    pub fn program_accounts<T: AccountDeserialize>(&self) -> Vec<(Pubkey, T)> {
+        self.svm
+            .accounts_db()
+            .inner
+            .iter()
+            .filter(|(_, account)| account.owner() == &rafa::ID && account.lamports() > 0)
+            .filter_map(|(key, account)| Some((*key, T::try_deserialize(&mut account.data()).ok()?)))
+            .collect()
+    }
+
+    /// Execute one instruction. Signatures are taken from the account metas: whoever builds the
+    /// instruction is assumed to hold the keys it marks as signers.
+    pub fn process(&mut self, instruction: Instruction) -> TransactionResult {
+        self.process_transaction(&[instruction])
+    }
+
+    /// Execute instructions atomically: if one fails, none of them takes effect
+    pub fn process_transaction(&mut self, instructions: &[Instruction]) -> TransactionResult {
+        check_size(instructions)?;
+        let message = Message::new(instructions, Some(&self.payer));
+        match self.svm.send_transaction(Transaction::new_unsigned(message.clone())) {
+            Ok(meta) => Ok(Outcome { events: events(&message, &meta) }),
+            Err(failed) => Err(error(&message, failed.err)),
+        }
+    }
+}
+
+/// Raw `emit_cpi!` payloads: rafa's self-invocations carrying the event tag
+fn events(message: &Message, meta: &TransactionMetadata) -> Vec<Vec<u8>> {
+    meta.inner_instructions
+        .iter()
+        .flatten()
+        .map(|inner| &inner.instruction)
+        .filter(|ix| message.account_keys[usize::from(ix.program_id_index)] == rafa::ID)
+        .filter_map(|ix| ix.data.strip_prefix(EVENT_IX_TAG_LE).map(<[u8]>::to_vec))
+        .collect()
+}
+
+fn error(message: &Message, err: RuntimeError) -> TransactionError {
+    match err {
+        RuntimeError::InstructionError(_, InstructionError::UnbalancedInstruction) => {
+            TransactionError::UnbalancedInstruction
+        }
+        RuntimeError::InstructionError(_, err) => match ProgramError::try_from(err) {
+            Ok(err) => TransactionError::Program(err),
+            Err(err) => TransactionError::Runtime(format!("{err:?}")),
+        },
+        RuntimeError::InsufficientFundsForRent { account_index } => {
+            TransactionError::InsufficientFundsForRent(message.account_keys[usize::from(account_index)])
+        }
+        err => TransactionError::Runtime(format!("{err:?}")),
+    }
+}
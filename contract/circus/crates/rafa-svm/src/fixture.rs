@@ -0,0 +1,132 @@
+//! A deployed and initialized protocol plus shortcuts for the common steps of a round.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use rafa::{ProtocolState, RoundState, UserAccount, EPOCH_DURATION_SECONDS, TICKET_PRICE_LAMPORTS};
+use rafa_client::{instructions, pda};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_token_2022::extension::StateWithExtensions;
+use spl_token_2022::state::Account as TokenAccount;
+
+use crate::{Svm, TransactionError, TransactionResult};
+
+pub const EPOCH_SECONDS: i64 = EPOCH_DURATION_SECONDS;
+
+/// Lamports for `n` tickets
+pub fn tickets(n: u64) -> u64 {
+    n * TICKET_PRICE_LAMPORTS
+}
+
+pub struct Protocol {
+    pub svm: Svm,
+    /// Admin, and initially keeper, guardian and treasurer as well
+    pub admin: Pubkey,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Protocol {
+    pub fn new() -> Self {
+        let mut svm = Svm::new();
+        let admin = svm.new_wallet(1_000 * LAMPORTS_PER_SOL);
+        svm.process(instructions::initialize(&admin, Pubkey::new_unique()))
+            .expect("initialize");
+        Self { svm, admin }
+    }
+
+    /// A fresh wallet holding `sol` SOL
+    pub fn wallet(&mut self, sol: u64) -> Pubkey {
+        self.svm.new_wallet(sol * LAMPORTS_PER_SOL)
+    }
+
+    pub fn state(&self) -> ProtocolState {
+        self.svm.get(&pda::protocol_state().0).expect("protocol state")
+    }
+
+    pub fn round(&self, round_id: u64) -> RoundState {
+        self.svm.get(&pda::round_state(round_id).0).expect("round state")
+    }
+
+    pub fn current_round(&self) -> RoundState {
+        self.round(self.state().current_round)
+    }
+
+    pub fn user(&self, owner: &Pubkey) -> UserAccount {
+        self.svm.get(&pda::user_account(owner).0).expect("user account")
+    }
+
+    /// UserAccount addresses of `round_id`'s participants, in ticket order
+    pub fn participants(&self, round_id: u64) -> Vec<Pubkey> {
+        let mut users = self.svm.program_accounts::<UserAccount>();
+        users.retain(|(_, user)| user.round_joined == round_id);
+        users.sort_by_key(|(_, user)| user.ticket_start);
+        users.into_iter().map(|(key, _)| key).collect()
+    }
+
+    pub fn seed(&mut self, lamports: u64) -> TransactionResult {
+        let ix = instructions::seed_prize(&self.admin, lamports);
+        self.svm.process(ix)
+    }
+
+    /// Deposit into the current round, passing its participants so an expired round can finalize
+    pub fn deposit(&mut self, user: &Pubkey, lamports: u64) -> TransactionResult {
+        let round_id = self.state().current_round;
+        let participants = self.participants(round_id);
+        self.svm.process(instructions::deposit(user, round_id, lamports, &participants))
+    }
+
+    /// Deposit into the current round for a ticket receipt; returns the receipt's mint
+    pub fn deposit_with_receipt(&mut self, payer: &Pubkey, lamports: u64) -> Result<Pubkey, TransactionError> {
+        let round_id = self.state().current_round;
+        let participants = self.participants(round_id);
+        let nonce = (0..).find(|&nonce| self.svm.account(&pda::receipt_mint(round_id, payer, nonce).0).is_none());
+        let nonce = nonce.unwrap();
+        self.svm.process(instructions::deposit_with_receipt(payer, round_id, nonce, lamports, &participants))?;
+        Ok(pda::receipt_mint(round_id, payer, nonce).0)
+    }
+
+    /// Receipt tokens of `mint` in `holder`'s associated token account, 0 if it has none
+    pub fn receipt_tokens(&self, holder: &Pubkey, mint: &Pubkey) -> u64 {
+        let Some(account) = self.svm.account(&pda::receipt_token_account(holder, mint)) else {
+            return 0;
+        };
+        StateWithExtensions::<TokenAccount>::unpack(&account.data).expect("token account").base.amount
+    }
+
+    /// Hand `from`'s receipt token to `to`, opening `to`'s token account if needed
+    pub fn transfer_receipt(&mut self, from: &Pubkey, to: &Pubkey, mint: &Pubkey) -> TransactionResult {
+        let open = create_associated_token_account_idempotent(from, to, mint, &spl_token_2022::ID);
+        let transfer = spl_token_2022::instruction::transfer_checked(
+            &spl_token_2022::ID,
+            &pda::receipt_token_account(from, mint),
+            mint,
+            &pda::receipt_token_account(to, mint),
+            from,
+            &[],
+            1,
+            0,
+        )
+        .expect("transfer instruction");
+        self.svm.process_transaction(&[open, transfer])
+    }
+
+    pub fn crank(&mut self, round_id: u64) -> TransactionResult {
+        let participants = self.participants(round_id);
+        self.svm.process(instructions::crank(round_id, &participants))
+    }
+
+    /// Warp past the end of the current round's last epoch and crank it to a winner
+    pub fn finish_round(&mut self) -> TransactionResult {
+        let round = self.current_round();
+        let end_ms = round.start_epoch + 3 * EPOCH_SECONDS as u64 * 1000;
+        let now_ms = self.svm.now_ms();
+        if now_ms < end_ms {
+            self.svm.warp(((end_ms - now_ms) / 1000) as i64);
+        }
+        self.crank(round.round_id)
+    }
+}
@@ -0,0 +1,119 @@
+//! In-process execution of the rafa program for tests.
+//!
+//! By default the program's own `entry` runs natively against accounts serialized the way the
+//! BPF loader serializes them. Syscalls are served by stubs: the clock and rent sysvars, return
+//! data, system program CPIs, rafa invoking itself (`emit_cpi!` events and multisig execution),
+//! and the real Token-2022 and associated token account processors for ticket receipts. After
+//! every instruction the runtime's account rules are enforced: read-only accounts stay
+//! untouched, lamports balance out and no account is left below its rent-exempt minimum.
+//!
+//! With the `bpf` feature the same `Svm` instead loads the compiled program into LiteSVM, which
+//! also meters compute units and catches anything that only goes wrong on-chain. Build the
+//! program first (`anchor build`); `RAFA_PROGRAM_SO` points at another `.so`. The native mode
+//! stays the fast default.
+//!
+//! Either way transactions must fit in one packet, and time only moves when a test warps the
+//! clock, which makes epoch boundaries exact. Not emulated natively: compute units and fees.
+
+#[cfg(feature = "bpf")]
+mod bpf;
+#[cfg(not(feature = "bpf"))]
+mod native;
+#[cfg(not(feature = "bpf"))]
+mod runtime;
+
+pub mod fixture;
+
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::{Discriminator, Event};
+use solana_sdk::message::Message;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::transaction::Transaction;
+
+#[cfg(feature = "bpf")]
+pub use crate::bpf::Svm;
+#[cfg(not(feature = "bpf"))]
+pub use crate::native::Svm;
+
+/// Solana's target slot time
+const MS_PER_SLOT: i64 = 400;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionError {
+    /// The instruction failed; Anchor and program errors surface as `Custom`
+    Program(ProgramError),
+    /// The program changed an account passed as read-only
+    ReadonlyAccountModified(Pubkey),
+    /// Lamports were created or destroyed
+    UnbalancedInstruction,
+    /// An account was left holding lamports below its rent-exempt minimum
+    InsufficientFundsForRent(Pubkey),
+    /// The serialized transaction, of this many bytes, does not fit in one packet
+    TooLarge(usize),
+    /// Any other failure LiteSVM reports
+    Runtime(String),
+}
+
+impl TransactionError {
+    /// The `Custom` code of a program error
+    pub fn custom_code(&self) -> Option<u32> {
+        match self {
+            TransactionError::Program(ProgramError::Custom(code)) => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+pub type TransactionResult = Result<Outcome, TransactionError>;
+
+/// What a successful instruction left behind
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// Raw `emit_cpi!` payloads (discriminator + borsh), in emission order
+    pub events: Vec<Vec<u8>>,
+}
+
+impl Outcome {
+    /// Every emitted event of type `E`
+    pub fn events<E: Event + Discriminator>(&self) -> Vec<E> {
+        self.events
+            .iter()
+            .filter_map(|data| data.strip_prefix(E::DISCRIMINATOR))
+            .map(|mut body| E::deserialize(&mut body).expect("malformed event"))
+            .collect()
+    }
+
+    /// The single event of type `E`; panics unless exactly one was emitted
+    pub fn event<E: Event + Discriminator>(&self) -> E {
+        let mut events = self.events::<E>();
+        assert_eq!(events.len(), 1, "expected exactly one event");
+        events.pop().unwrap()
+    }
+}
+
+/// The clock every `Svm` starts at
+fn genesis_clock() -> Clock {
+    Clock {
+        slot: 1,
+        epoch_start_timestamp: 1_700_000_000,
+        epoch: 0,
+        leader_schedule_epoch: 1,
+        unix_timestamp: 1_700_000_000,
+    }
+}
+
+/// Refuse a transaction that would not fit in one packet, as the cluster does. The fee payer is
+/// the first signer, or a keeper outside the instructions when nothing signs.
+fn check_size(instructions: &[Instruction]) -> Result<(), TransactionError> {
+    let signer = instructions.iter().flat_map(|ix| &ix.accounts).find(|meta| meta.is_signer);
+    let payer = signer.map_or_else(Pubkey::new_unique, |meta| meta.pubkey);
+    let transaction = Transaction::new_unsigned(Message::new(instructions, Some(&payer)));
+    let size = bincode::serialized_size(&transaction).expect("serialize transaction") as usize;
+    if size > PACKET_DATA_SIZE {
+        return Err(TransactionError::TooLarge(size));
+    }
+    Ok(())
+}
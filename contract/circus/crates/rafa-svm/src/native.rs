@@ -0,0 +1,179 @@
+//! The default mode: the program's `entry` called natively under the stubs in `runtime`.
+
+use std::collections::HashMap;
+
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::solana_program::rent::Rent;
+use anchor_lang::{system_program, AccountDeserialize, AccountSerialize};
+use solana_sdk::account::Account;
+use solana_sdk_ids::{bpf_loader_upgradeable, native_loader};
+
+use crate::runtime::{install_stubs, SerializedInput};
+use crate::{check_size, genesis_clock, Outcome, TransactionError, TransactionResult, MS_PER_SLOT};
+
+pub struct Svm {
+    accounts: HashMap<Pubkey, Account>,
+    clock: Clock,
+}
+
+impl Default for Svm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        install_stubs();
+        let mut svm = Self {
+            accounts: HashMap::new(),
+            clock: genesis_clock(),
+        };
+        svm.set_account(
+            rafa::ID,
+            Account { lamports: 1, data: vec![], owner: bpf_loader_upgradeable::ID, executable: true, rent_epoch: 0 },
+        );
+        for program in [system_program::ID, spl_token_2022::ID, spl_associated_token_account::ID] {
+            svm.set_account(
+                program,
+                Account { lamports: 1, data: vec![], owner: native_loader::ID, executable: true, rent_epoch: 0 },
+            );
+        }
+        svm
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Current cluster time in milliseconds, the unit rafa stores round times in
+    pub fn now_ms(&self) -> u64 {
+        self.clock.unix_timestamp as u64 * 1000
+    }
+
+    /// Move the clock forward, advancing slots at the target slot time
+    pub fn warp(&mut self, seconds: i64) {
+        self.clock.unix_timestamp += seconds;
+        self.clock.slot += (seconds * 1000 / MS_PER_SLOT) as u64;
+    }
+
+    pub fn account(&self, address: &Pubkey) -> Option<Account> {
+        self.accounts.get(address).cloned()
+    }
+
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.accounts.get(address).map_or(0, |a| a.lamports)
+    }
+
+    /// Credit a system account, creating it if needed
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        let account = self.accounts.entry(*address).or_insert_with(|| Account::new(0, 0, &system_program::ID));
+        account.lamports += lamports;
+    }
+
+    /// A fresh funded wallet
+    pub fn new_wallet(&mut self, lamports: u64) -> Pubkey {
+        let wallet = Pubkey::new_unique();
+        self.airdrop(&wallet, lamports);
+        wallet
+    }
+
+    /// Decode a program account, `None` if missing or of another type
+    pub fn get<T: AccountDeserialize>(&self, address: &Pubkey) -> Option<T> {
+        let account = self.accounts.get(address)?;
+        T::try_deserialize(&mut account.data.as_slice()).ok()
+    }
+
+    /// Overwrite a program account's state, for setting up situations no instruction sequence
+    /// can reach
+    pub fn write<T: AccountSerialize>(&mut self, address: &Pubkey, state: &T) {
+        let account = self.accounts.get_mut(address).expect("account to overwrite does not exist");
+        let mut data = Vec::with_capacity(account.data.len());
+        state.try_serialize(&mut data).expect("serialize account");
+        account.data[..data.len()].copy_from_slice(&data);
+    }
+
+    /// Every rafa account of type `T`
+    pub fn program_accounts<T: AccountDeserialize>(&self) -> Vec<(Pubkey, T)> {
+        self.accounts
+            .iter()
+            .filter(|(_, account)| account.owner == rafa::ID)
+            .filter_map(|(key, account)| Some((*key, T::try_deserialize(&mut account.data.as_slice()).ok()?)))
+            .collect()
+    }
+
+    /// Execute one instruction. Signatures are taken from the account metas: whoever builds the
+    /// instruction is assumed to hold the keys it marks as signers.
+    pub fn process(&mut self, instruction: Instruction) -> TransactionResult {
+        self.process_transaction(&[instruction])
+    }
+
+    /// Execute instructions atomically: if one fails, none of them takes effect
+    pub fn process_transaction(&mut self, instructions: &[Instruction]) -> TransactionResult {
+        check_size(instructions)?;
+        let checkpoint = self.accounts.clone();
+        let mut outcome = Outcome::default();
+        for instruction in instructions {
+            match self.execute(instruction) {
+                Ok(events) => outcome.events.extend(events),
+                Err(err) => {
+                    self.accounts = checkpoint;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    fn execute(&mut self, instruction: &Instruction) -> Result<Vec<Vec<u8>>, TransactionError> {
+        let load = |key: &Pubkey| {
+            self.accounts.get(key).cloned().unwrap_or_else(|| Account::new(0, 0, &system_program::ID))
+        };
+        let mut input = SerializedInput::new(instruction, load);
+        let (result, post, events) = input.execute(&self.clock);
+        result.map_err(TransactionError::Program)?;
+
+        let rent = Rent::default();
+        let mut pre_total: u128 = 0;
+        let mut post_total: u128 = 0;
+        let mut updates = Vec::new();
+        for (index, (key, after)) in post.into_iter().enumerate() {
+            // Duplicates share the first occurrence's state
+            if instruction.accounts[..index].iter().any(|m| m.pubkey == key) {
+                continue;
+            }
+            let before = load(&key);
+            let writable = instruction.accounts.iter().any(|m| m.pubkey == key && m.is_writable);
+            let changed = before.lamports != after.lamports || before.data != after.data || before.owner != after.owner;
+            if changed && !writable {
+                return Err(TransactionError::ReadonlyAccountModified(key));
+            }
+            let rent_paying = |a: &Account| !a.executable && a.lamports > 0 && a.lamports < rent.minimum_balance(a.data.len());
+            let was_rent_paying = rent_paying(&before) && before.data.len() == after.data.len();
+            if rent_paying(&after) && !(was_rent_paying && after.lamports <= before.lamports) {
+                return Err(TransactionError::InsufficientFundsForRent(key));
+            }
+            pre_total += u128::from(before.lamports);
+            post_total += u128::from(after.lamports);
+            updates.push((key, after));
+        }
+        if pre_total != post_total {
+            return Err(TransactionError::UnbalancedInstruction);
+        }
+
+        for (key, account) in updates {
+            if account.lamports == 0 {
+                self.accounts.remove(&key);
+            } else {
+                self.accounts.insert(key, account);
+            }
+        }
+        Ok(events)
+    }
+}
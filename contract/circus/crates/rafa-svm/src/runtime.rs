@@ -0,0 +1,306 @@
+//! The parts of the Solana runtime the program touches: the loader's input serialization,
+//! sysvars, return data and cross-program invocations (system program, Token-2022 and the
+//! associated token account program for ticket receipts, and rafa invoking itself for
+//! `emit_cpi!` events and multisig execution).
+
+use std::cell::RefCell;
+use std::sync::Once;
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::solana_program::account_info::AccountInfo;
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::entrypoint::{
+    self, ProgramResult, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER,
+};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::solana_program::rent::Rent;
+use anchor_lang::system_program;
+use solana_sdk::account::Account;
+
+const SUCCESS: u64 = 0;
+
+// SystemError codes, as the system program reports them
+const ACCOUNT_ALREADY_IN_USE: u32 = 0;
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+
+/// Per-thread execution state, so tests can run in parallel against the process-wide stubs
+#[derive(Default)]
+struct ExecutionContext {
+    clock: Clock,
+    events: Vec<Vec<u8>>,
+    /// Programs on the invocation stack, the running one last
+    callers: Vec<Pubkey>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+}
+
+/// The program currently running
+fn current_program() -> Pubkey {
+    CONTEXT.with(|c| c.borrow().callers.last().copied().unwrap_or_default())
+}
+
+thread_local! {
+    static CONTEXT: RefCell<ExecutionContext> = RefCell::new(ExecutionContext::default());
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = CONTEXT.with(|c| c.borrow().clock.clone());
+        unsafe { std::ptr::write(var_addr as *mut Clock, clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { std::ptr::write(var_addr as *mut Rent, Rent::default()) };
+        SUCCESS
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        CONTEXT.with(|c| c.borrow().return_data.clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        let program = current_program();
+        CONTEXT.with(|c| c.borrow_mut().return_data = Some((program, data.to_vec())));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        invoke(instruction, account_infos, signers_seeds)
+    }
+}
+
+pub(crate) fn install_stubs() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(Stubs));
+    });
+}
+
+/// A CPI: check the privileges the caller asks for, then run the callee on the caller's account
+/// infos so every change is visible to the caller afterwards
+fn invoke(instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+    let caller = current_program();
+    let pda_signers = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let mut callee_accounts = Vec::with_capacity(instruction.accounts.len());
+    for meta in &instruction.accounts {
+        let info = account_infos
+            .iter()
+            .find(|info| *info.key == meta.pubkey)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if meta.is_signer && !info.is_signer && !pda_signers.contains(&meta.pubkey) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if meta.is_writable && !info.is_writable {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let mut info = info.clone();
+        info.is_signer = meta.is_signer;
+        info.is_writable = meta.is_writable;
+        callee_accounts.push(info);
+    }
+
+    if instruction.program_id == rafa::ID {
+        if let Some(event) = instruction.data.strip_prefix(EVENT_IX_TAG_LE) {
+            CONTEXT.with(|c| c.borrow_mut().events.push(event.to_vec()));
+        }
+    }
+    CONTEXT.with(|c| c.borrow_mut().return_data = None);
+    process_instruction(&instruction.program_id, &callee_accounts, &instruction.data)
+}
+
+/// Run the program `program_id` with it on top of the invocation stack
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    CONTEXT.with(|c| c.borrow_mut().callers.push(*program_id));
+    let result = if *program_id == system_program::ID {
+        process_system_instruction(accounts, data)
+    } else if *program_id == rafa::ID {
+        call_rafa(program_id, accounts, data)
+    } else if *program_id == spl_token_2022::ID {
+        spl_token_2022::processor::Processor::process(program_id, accounts, data)
+    } else if *program_id == spl_associated_token_account::ID {
+        spl_associated_token_account::processor::process_instruction(program_id, accounts, data)
+    } else {
+        Err(ProgramError::IncorrectProgramId)
+    };
+    CONTEXT.with(|c| c.borrow_mut().callers.pop());
+    result
+}
+
+fn call_rafa(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    // SAFETY: `entry` wants the slice borrowed for as long as the infos' own lifetime; it only
+    // uses it during the call, while `accounts` is alive
+    let accounts: &[AccountInfo] = unsafe { &*(accounts as *const [AccountInfo]) };
+    rafa::entry(program_id, accounts, data)
+}
+
+/// The subset of the system program rafa uses: transfer, create_account, allocate and assign
+fn process_system_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (tag, args) = data.split_at_checked(4).ok_or(ProgramError::InvalidInstructionData)?;
+    let tag = u32::from_le_bytes(tag.try_into().unwrap());
+    let read_u64 = |at: usize| -> Result<u64, ProgramError> {
+        let bytes = args.get(at..at + 8).ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let read_key = |at: usize| -> Result<Pubkey, ProgramError> {
+        let bytes = args.get(at..at + 32).ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(Pubkey::new_from_array(bytes.try_into().unwrap()))
+    };
+    let account = |index: usize| accounts.get(index).ok_or(ProgramError::NotEnoughAccountKeys);
+
+    match tag {
+        // CreateAccount { lamports, space, owner }
+        0 => {
+            let (from, to) = (account(0)?, account(1)?);
+            if to.lamports() > 0 {
+                return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+            }
+            transfer(from, to, read_u64(0)?)?;
+            allocate(to, read_u64(8)?)?;
+            assign(to, &read_key(16)?)
+        }
+        // Assign { owner }
+        1 => assign(account(0)?, &read_key(0)?),
+        // Transfer { lamports }
+        2 => transfer(account(0)?, account(1)?, read_u64(0)?),
+        // Allocate { space }
+        8 => allocate(account(0)?, read_u64(0)?),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *from.owner != system_program::ID || !from.data_is_empty() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let balance = from.lamports();
+    if balance < lamports {
+        return Err(ProgramError::Custom(RESULT_WITH_NEGATIVE_LAMPORTS));
+    }
+    **from.try_borrow_mut_lamports()? = balance - lamports;
+    let to_balance = to.lamports();
+    **to.try_borrow_mut_lamports()? = to_balance.checked_add(lamports).ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *account.owner != system_program::ID || !account.data_is_empty() {
+        return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+    }
+    account.resize(space as usize)
+}
+
+fn assign(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner == owner {
+        return Ok(());
+    }
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *account.owner != system_program::ID {
+        return Err(ProgramError::InvalidArgument);
+    }
+    account.assign(owner);
+    Ok(())
+}
+
+/// Program input laid out exactly like the BPF loader's aligned serialization, so `realloc`,
+/// `assign` and duplicate accounts behave as they do on chain
+pub(crate) struct SerializedInput {
+    buffer: Vec<u64>,
+}
+
+impl SerializedInput {
+    pub(crate) fn new(instruction: &Instruction, load: impl Fn(&Pubkey) -> Account) -> Self {
+        let metas = &instruction.accounts;
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&(metas.len() as u64).to_le_bytes());
+        for (index, meta) in metas.iter().enumerate() {
+            let first = metas.iter().position(|m| m.pubkey == meta.pubkey).unwrap();
+            if first < index {
+                bytes.push(first as u8);
+                bytes.extend_from_slice(&[0; 7]);
+                continue;
+            }
+            // A key listed several times gets the union of its privileges
+            let same_key = metas.iter().filter(|m| m.pubkey == meta.pubkey);
+            let is_signer = same_key.clone().any(|m| m.is_signer);
+            let is_writable = same_key.clone().any(|m| m.is_writable);
+            let account = load(&meta.pubkey);
+
+            bytes.push(NON_DUP_MARKER);
+            bytes.push(is_signer as u8);
+            bytes.push(is_writable as u8);
+            bytes.push(account.executable as u8);
+            bytes.extend_from_slice(&[0; 4]);
+            bytes.extend_from_slice(meta.pubkey.as_ref());
+            bytes.extend_from_slice(account.owner.as_ref());
+            bytes.extend_from_slice(&account.lamports.to_le_bytes());
+            bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&account.data);
+            bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            bytes.resize(bytes.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+            bytes.extend_from_slice(&account.rent_epoch.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&instruction.data);
+        bytes.extend_from_slice(instruction.program_id.as_ref());
+
+        // u64 backing keeps every field at the alignment the loader guarantees
+        let mut buffer = vec![0u64; bytes.len().div_ceil(8)];
+        let dst = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, bytes.len()) };
+        dst.copy_from_slice(&bytes);
+        Self { buffer }
+    }
+
+    /// Run `instruction` against the serialized accounts and return their final state, one
+    /// entry per account meta
+    pub(crate) fn execute(&mut self, clock: &Clock) -> (ProgramResult, Vec<(Pubkey, Account)>, Vec<Vec<u8>>) {
+        CONTEXT.with(|c| {
+            let mut c = c.borrow_mut();
+            c.clock = clock.clone();
+            c.events.clear();
+            c.callers.clear();
+            c.return_data = None;
+        });
+
+        let input = self.buffer.as_mut_ptr() as *mut u8;
+        let (program_id, accounts, data) = unsafe { entrypoint::deserialize(input) };
+        let result = process_instruction(program_id, &accounts, data);
+
+        let post = accounts
+            .iter()
+            .map(|info| {
+                let account = Account {
+                    lamports: info.lamports(),
+                    data: info.data.borrow().to_vec(),
+                    owner: *info.owner,
+                    executable: info.executable,
+                    rent_epoch: info.rent_epoch,
+                };
+                (*info.key, account)
+            })
+            .collect();
+        let events = CONTEXT.with(|c| std::mem::take(&mut c.borrow_mut().events));
+        (result, post, events)
+    }
+}
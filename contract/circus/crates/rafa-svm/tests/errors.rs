@@ -0,0 +1,932 @@
+//! One test per reachable `ErrorCode`.
+//!
+//! Not covered: `MissingBump` and `TicketRangeNotContiguous` are never raised, and
+//! `MissingSystemProgram` is shadowed by Anchor's own `Program<System>` check on deposit while the
+//! crank looks the system program up by key.
+//! `ArithmeticOverflow`, `InvalidRound` and `OverlappingTicketRanges` need state no instruction
+//! sequence produces, so those tests write it directly.
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_lang::solana_program::program_pack::Pack;
+use rafa::{
+    ClaimTicket, ConfigChange, ErrorCode, ProtocolRole, ProtocolStatus, MAX_EXIT_FEE_BPS, MAX_PENDING_CHANGES,
+    MAX_ROUND_PARTICIPANTS,
+};
+use rafa_client::{instructions, pda};
+use rafa_svm::fixture::{tickets, Protocol, EPOCH_SECONDS};
+use rafa_svm::TransactionResult;
+use solana_sdk::account::Account;
+use spl_associated_token_account::instruction::create_associated_token_account;
+use spl_token_2022::instruction as token_instruction;
+use spl_token_2022::state::Mint;
+
+#[track_caller]
+fn assert_error(result: TransactionResult, code: ErrorCode) {
+    let err = result.expect_err("instruction should have failed");
+    assert_eq!(err.custom_code(), Some(u32::from(code)), "got {err:?}");
+}
+
+/// Puts `change` through the timelock before anyone has deposited
+fn configure(p: &mut Protocol, change: ConfigChange) {
+    p.svm.process(instructions::init_change_queue(&p.admin)).unwrap();
+    p.svm.process(instructions::propose_change(&p.admin, change)).unwrap();
+    p.svm.warp(rafa::DEFAULT_TIMELOCK_SECONDS);
+    p.svm.process(instructions::execute_change(&p.admin, 0)).unwrap();
+}
+
+/// Alice and Bob hold one ticket each in round 0, which `select_winner_local` hands to Alice.
+/// No next round is opened.
+fn decided_round() -> (Protocol, [Pubkey; 2]) {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    let participants = p.participants(0);
+    p.svm.process(instructions::select_winner_local(&p.admin, 0, 0, &participants)).unwrap();
+    (p, [alice, bob])
+}
+
+#[test]
+fn invalid_amount() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    assert_error(p.deposit(&alice, 0), ErrorCode::InvalidAmount);
+
+    p.deposit(&alice, tickets(1)).unwrap();
+    let ix = instructions::request_withdrawal(&alice, 0, tickets(2));
+    assert_error(p.svm.process(ix), ErrorCode::InvalidAmount);
+}
+
+#[test]
+fn arithmetic_overflow() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+
+    let mut round = p.round(0);
+    round.total_staked_lamports = u64::MAX;
+    p.svm.write(&pda::round_state(0).0, &round);
+    assert_error(p.deposit(&alice, tickets(1)), ErrorCode::ArithmeticOverflow);
+
+    // A winner's stake and pending withdrawal are added up when the claim settles the position
+    let (mut p, [alice, _]) = decided_round();
+    p.svm.process(instructions::create_claim_ticket_winner(&alice, 0)).unwrap();
+    let mut user = p.user(&alice);
+    user.pending_withdrawal_amount = u64::MAX;
+    p.svm.write(&pda::user_account(&alice).0, &user);
+    assert_error(p.svm.process(instructions::claim_prize(&alice, 0)), ErrorCode::ArithmeticOverflow);
+}
+
+#[test]
+fn invalid_epoch() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.svm.warp(2 * EPOCH_SECONDS);
+    p.crank(0).unwrap();
+
+    let ix = instructions::advance_epoch(&p.admin, 0);
+    assert_error(p.svm.process(ix), ErrorCode::InvalidEpoch);
+}
+
+#[test]
+fn invalid_ticket_amount() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    assert_error(p.deposit(&alice, tickets(1) + tickets(1) / 2), ErrorCode::InvalidTicketAmount);
+}
+
+#[test]
+fn missing_round_account() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let mut ix = instructions::deposit(&alice, 0, tickets(1), &[]);
+    ix.accounts.truncate(ix.accounts.len() - 2);
+    assert_error(p.svm.process(ix), ErrorCode::MissingRoundAccount);
+}
+
+#[test]
+fn invalid_round_account() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    // Round 1 is not the current round
+    let mut ix = instructions::deposit(&alice, 0, tickets(1), &[]);
+    let current = ix.accounts.len() - 2;
+    ix.accounts[current].pubkey = pda::round_state(1).0;
+    assert_error(p.svm.process(ix), ErrorCode::InvalidRoundAccount);
+}
+
+#[test]
+fn insufficient_funds() {
+    let (mut p, [alice, _]) = decided_round();
+    let ix = instructions::create_claim_ticket(&p.admin, 0, &alice, 100 * LAMPORTS_PER_SOL, tickets(1));
+    p.svm.process(ix).unwrap();
+    assert_error(p.svm.process(instructions::claim_prize(&alice, 0)), ErrorCode::InsufficientFunds);
+}
+
+#[test]
+fn deposits_closed_epoch3() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.svm.warp(2 * EPOCH_SECONDS);
+    assert_error(p.deposit(&alice, tickets(1)), ErrorCode::DepositsClosedEpoch3);
+}
+
+#[test]
+fn no_tickets_sold() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+
+    // A cancelled round is complete but has no winner. The treasurer's create_claim_ticket
+    // derives its PDA from the winner before this check runs, so only the winner path reports it.
+    let ix = instructions::create_claim_ticket_winner(&alice, 0);
+    assert_error(p.svm.process(ix), ErrorCode::NoTicketsSold);
+}
+
+#[test]
+fn already_claimed() {
+    let (mut p, [alice, _]) = decided_round();
+    p.svm.process(instructions::create_claim_ticket(&p.admin, 0, &alice, 0, tickets(1))).unwrap();
+    p.svm.process(instructions::claim_prize(&alice, 0)).unwrap();
+    assert_error(p.svm.process(instructions::claim_prize(&alice, 0)), ErrorCode::AlreadyClaimed);
+
+    // Closing the claimed ticket does not open the way to a second one
+    p.svm.process(instructions::close_claim_ticket(0, &alice, &p.admin)).unwrap();
+    let ix = instructions::create_claim_ticket_winner(&alice, 0);
+    assert_error(p.svm.process(ix), ErrorCode::AlreadyClaimed);
+}
+
+#[test]
+fn not_winner() {
+    let (mut p, [_, bob]) = decided_round();
+    let ix = instructions::create_claim_ticket_winner(&bob, 0);
+    assert_error(p.svm.process(ix), ErrorCode::NotWinner);
+}
+
+#[test]
+fn invalid_round() {
+    let (mut p, [alice, _]) = decided_round();
+    p.svm.process(instructions::create_claim_ticket(&p.admin, 0, &alice, 0, tickets(1))).unwrap();
+
+    let address = pda::claim_ticket(0, &alice).0;
+    let mut ticket: ClaimTicket = p.svm.get(&address).unwrap();
+    ticket.round_id = 7;
+    p.svm.write(&address, &ticket);
+    assert_error(p.svm.process(instructions::claim_prize(&alice, 0)), ErrorCode::InvalidRound);
+}
+
+#[test]
+fn round_not_complete() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    let ix = instructions::process_withdrawal(&alice, 0);
+    assert_error(p.svm.process(ix), ErrorCode::RoundNotComplete);
+}
+
+#[test]
+fn wrong_round() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.finish_round().unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+
+    // Bob joined round 1, so round 0 owes him nothing
+    let ix = instructions::process_withdrawal(&bob, 0);
+    assert_error(p.svm.process(ix), ErrorCode::WrongRound);
+}
+
+#[test]
+fn winner_must_claim() {
+    let (mut p, [alice, _]) = decided_round();
+    let ix = instructions::process_withdrawal(&alice, 0);
+    assert_error(p.svm.process(ix), ErrorCode::WinnerMustClaim);
+}
+
+#[test]
+fn nothing_to_withdraw() {
+    let (mut p, [_, bob]) = decided_round();
+    p.svm.process(instructions::process_withdrawal(&bob, 0)).unwrap();
+    let ix = instructions::process_withdrawal(&bob, 0);
+    assert_error(p.svm.process(ix), ErrorCode::NothingToWithdraw);
+}
+
+#[test]
+fn round_complete() {
+    let (mut p, _) = decided_round();
+    let carol = p.wallet(10);
+    // Without the next round's PDA the deposit cannot roll over
+    let mut ix = instructions::deposit(&carol, 0, tickets(1), &[]);
+    ix.accounts.pop();
+    assert_error(p.svm.process(ix), ErrorCode::RoundComplete);
+}
+
+#[test]
+fn unclaimed_prizes_exist() {
+    let (mut p, [alice, _]) = decided_round();
+    p.svm.process(instructions::create_claim_ticket(&p.admin, 0, &alice, 1, tickets(1))).unwrap();
+    p.svm.process(instructions::begin_wind_down(&p.admin)).unwrap();
+    p.svm.warp(rafa::WIND_DOWN_GRACE_SECONDS);
+    let ix = instructions::close_protocol_state(&p.admin);
+    assert_error(p.svm.process(ix), ErrorCode::UnclaimedPrizesExist);
+}
+
+#[test]
+fn unsettled_position() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    let winner = p.finish_round().unwrap().event::<rafa::RoundFinalized>().winner;
+    let loser = if winner == alice { bob } else { alice };
+
+    // The loser's round-0 stake is still in the account
+    assert_error(p.deposit(&loser, tickets(1)), ErrorCode::UnsettledPosition);
+    p.svm.process(instructions::process_withdrawal(&loser, 0)).unwrap();
+    p.deposit(&loser, tickets(1)).unwrap();
+}
+
+#[test]
+fn round_already_exists() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let now_ms = p.svm.now_ms();
+    // The keeper opens round 2 early, then goes back to round 1
+    for round_id in [2, 1] {
+        let ix = instructions::init_round(&p.admin, round_id, now_ms, &Pubkey::new_unique());
+        p.svm.process(ix).unwrap();
+    }
+    p.deposit(&alice, tickets(1)).unwrap();
+    let participants = p.participants(1);
+    p.svm.process(instructions::select_winner_local(&p.admin, 1, 0, &participants)).unwrap();
+
+    // Rolling round 1 over would open round 2 a second time
+    let bob = p.wallet(10);
+    assert_error(p.deposit(&bob, tickets(1)), ErrorCode::RoundAlreadyExists);
+}
+
+#[test]
+fn round_cancelled() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+    let ix = instructions::cancel_round(&p.admin, 0);
+    assert_error(p.svm.process(ix), ErrorCode::RoundCancelled);
+}
+
+#[test]
+fn round_not_cancelled() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    let ix = instructions::refund_cancelled(&alice, 0);
+    assert_error(p.svm.process(ix), ErrorCode::RoundNotCancelled);
+}
+
+#[test]
+fn round_already_finalized() {
+    let (mut p, _) = decided_round();
+    let ix = instructions::cancel_round(&p.admin, 0);
+    assert_error(p.svm.process(ix), ErrorCode::RoundAlreadyFinalized);
+}
+
+#[test]
+fn deposits_paused() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.svm.process(instructions::set_protocol_status(&p.admin, ProtocolStatus::DepositsPaused)).unwrap();
+    assert_error(p.deposit(&alice, tickets(1)), ErrorCode::DepositsPaused);
+    // Seeding the prize is a deposit too
+    assert_error(p.seed(LAMPORTS_PER_SOL), ErrorCode::DepositsPaused);
+}
+
+#[test]
+fn round_progression_paused() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.svm.process(instructions::set_protocol_status(&p.admin, ProtocolStatus::WithdrawOnly)).unwrap();
+    assert_error(p.crank(0), ErrorCode::RoundProgressionPaused);
+}
+
+#[test]
+fn protocol_frozen() {
+    let (mut p, [alice, bob]) = decided_round();
+    p.svm.process(instructions::init_change_queue(&p.admin)).unwrap();
+    p.svm.process(instructions::propose_change(&p.admin, ConfigChange::ExitFeeBps(10))).unwrap();
+    p.svm.warp(rafa::DEFAULT_TIMELOCK_SECONDS);
+    p.svm.process(instructions::propose_admin(&p.admin, bob)).unwrap();
+    p.svm.process(instructions::set_protocol_status(&p.admin, ProtocolStatus::Frozen)).unwrap();
+
+    let frozen = [
+        instructions::process_withdrawal(&bob, 0),
+        instructions::cancel_round(&p.admin, 0),
+        instructions::execute_change(&p.admin, 0),
+        instructions::migrate_round_state(&p.admin, 0),
+        instructions::migrate_user_account(&p.admin, &alice),
+        instructions::close_user_account(&alice),
+        instructions::close_protocol_state(&p.admin),
+        instructions::set_role(&p.admin, ProtocolRole::Keeper, alice),
+        instructions::propose_admin(&p.admin, alice),
+        instructions::accept_admin(&bob),
+        instructions::propose_change(&p.admin, ConfigChange::ExitFeeBps(20)),
+        instructions::cancel_change(&p.admin, 0),
+        instructions::begin_wind_down(&p.admin),
+    ];
+    for ix in frozen {
+        assert_error(p.svm.process(ix), ErrorCode::ProtocolFrozen);
+    }
+
+    // Nor can a change queue be opened
+    let mut p = Protocol::new();
+    p.svm.process(instructions::set_protocol_status(&p.admin, ProtocolStatus::Frozen)).unwrap();
+    assert_error(p.svm.process(instructions::init_change_queue(&p.admin)), ErrorCode::ProtocolFrozen);
+}
+
+#[test]
+fn protocol_frozen_multisig() {
+    let mut p = Protocol::new();
+    let (a, b) = (p.wallet(1), p.wallet(1));
+    p.svm.process(instructions::set_protocol_status(&p.admin, ProtocolStatus::Frozen)).unwrap();
+    let ix = instructions::create_multisig(&p.admin, vec![a, b], 2);
+    assert_error(p.svm.process(ix), ErrorCode::ProtocolFrozen);
+    p.svm.process(instructions::set_protocol_status(&p.admin, ProtocolStatus::Active)).unwrap();
+
+    // Hand the admin key to a 2-of-2 multisig and leave an owner rotation pending
+    p.svm.process(instructions::create_multisig(&p.admin, vec![a, b], 2)).unwrap();
+    let signer = pda::multisig_signer().0;
+    p.svm.process(instructions::propose_admin(&p.admin, signer)).unwrap();
+    let accept = instructions::accept_admin(&signer);
+    p.svm.process(instructions::propose_multisig_tx(&a, 0, &accept)).unwrap();
+    p.svm.process(instructions::approve_multisig_tx(&b, 0)).unwrap();
+    p.svm.process(instructions::execute_multisig_tx(0, &accept)).unwrap();
+    let rotate = instructions::set_multisig_owners(vec![a], 1);
+    p.svm.process(instructions::propose_multisig_tx(&a, 1, &rotate)).unwrap();
+
+    // The old admin key is still the guardian
+    p.svm.process(instructions::set_protocol_status(&p.admin, ProtocolStatus::Frozen)).unwrap();
+    let frozen = [
+        instructions::propose_multisig_tx(&a, 2, &rotate),
+        instructions::approve_multisig_tx(&b, 1),
+        instructions::execute_multisig_tx(1, &rotate),
+        rotate.clone(),
+    ];
+    for ix in frozen {
+        assert_error(p.svm.process(ix), ErrorCode::ProtocolFrozen);
+    }
+
+    // Only lifting the freeze still goes through the multisig
+    let unfreeze = instructions::set_protocol_status(&signer, ProtocolStatus::Active);
+    p.svm.process(instructions::propose_multisig_tx(&a, 2, &unfreeze)).unwrap();
+    p.svm.process(instructions::approve_multisig_tx(&b, 2)).unwrap();
+    p.svm.process(instructions::execute_multisig_tx(2, &unfreeze)).unwrap();
+    assert_eq!(p.state().status, ProtocolStatus::Active);
+}
+
+#[test]
+fn unauthorized() {
+    let mut p = Protocol::new();
+    let mallory = p.wallet(10);
+    let ix = instructions::seed_prize(&mallory, LAMPORTS_PER_SOL);
+    assert_error(p.svm.process(ix), ErrorCode::Unauthorized);
+
+    // Only the admin or the guardian cancels a round
+    p.deposit(&mallory, tickets(1)).unwrap();
+    assert_error(p.svm.process(instructions::cancel_round(&mallory, 0)), ErrorCode::Unauthorized);
+}
+
+#[test]
+fn guardian_cannot_unpause() {
+    let mut p = Protocol::new();
+    let guardian = p.wallet(1);
+    p.svm.process(instructions::set_role(&p.admin, ProtocolRole::Guardian, guardian)).unwrap();
+    p.svm.process(instructions::set_protocol_status(&guardian, ProtocolStatus::DepositsPaused)).unwrap();
+    let ix = instructions::set_protocol_status(&guardian, ProtocolStatus::Active);
+    assert_error(p.svm.process(ix), ErrorCode::GuardianCannotUnpause);
+}
+
+#[test]
+fn not_pending_admin() {
+    let mut p = Protocol::new();
+    let nominee = p.wallet(1);
+    let mallory = p.wallet(1);
+    p.svm.process(instructions::propose_admin(&p.admin, nominee)).unwrap();
+    let ix = instructions::accept_admin(&mallory);
+    assert_error(p.svm.process(ix), ErrorCode::NotPendingAdmin);
+}
+
+#[test]
+fn change_queue_full() {
+    let mut p = Protocol::new();
+    p.svm.process(instructions::init_change_queue(&p.admin)).unwrap();
+    for delay in 0..MAX_PENDING_CHANGES as i64 {
+        p.svm.process(instructions::propose_change(&p.admin, ConfigChange::TimelockDelay(delay))).unwrap();
+    }
+    let ix = instructions::propose_change(&p.admin, ConfigChange::TimelockDelay(0));
+    assert_error(p.svm.process(ix), ErrorCode::ChangeQueueFull);
+}
+
+#[test]
+fn change_not_found() {
+    let mut p = Protocol::new();
+    p.svm.process(instructions::init_change_queue(&p.admin)).unwrap();
+    let ix = instructions::execute_change(&p.admin, 42);
+    assert_error(p.svm.process(ix), ErrorCode::ChangeNotFound);
+}
+
+#[test]
+fn timelock_not_expired() {
+    let mut p = Protocol::new();
+    p.svm.process(instructions::init_change_queue(&p.admin)).unwrap();
+    p.svm.process(instructions::propose_change(&p.admin, ConfigChange::TimelockDelay(0))).unwrap();
+    p.svm.warp(rafa::DEFAULT_TIMELOCK_SECONDS - 1);
+    let ix = instructions::execute_change(&p.admin, 0);
+    assert_error(p.svm.process(ix), ErrorCode::TimelockNotExpired);
+
+    p.svm.warp(1);
+    p.svm.process(instructions::execute_change(&p.admin, 0)).unwrap();
+    assert_eq!(p.state().timelock_delay_seconds, 0);
+}
+
+#[test]
+fn invalid_config_value() {
+    let mut p = Protocol::new();
+    p.svm.process(instructions::init_change_queue(&p.admin)).unwrap();
+    let ix = instructions::propose_change(&p.admin, ConfigChange::TimelockDelay(-1));
+    assert_error(p.svm.process(ix), ErrorCode::InvalidConfigValue);
+    let ix = instructions::propose_change(&p.admin, ConfigChange::ExitFeeBps(MAX_EXIT_FEE_BPS + 1));
+    assert_error(p.svm.process(ix), ErrorCode::InvalidConfigValue);
+    let ix = instructions::propose_change(&p.admin, ConfigChange::MinTicketsPerDeposit(0));
+    assert_error(p.svm.process(ix), ErrorCode::InvalidConfigValue);
+}
+
+#[test]
+fn invalid_multisig_owners() {
+    let mut p = Protocol::new();
+    let owner = p.wallet(1);
+    let ix = instructions::create_multisig(&p.admin, vec![owner, owner], 1);
+    assert_error(p.svm.process(ix), ErrorCode::InvalidMultisigOwners);
+}
+
+#[test]
+fn invalid_multisig_threshold() {
+    let mut p = Protocol::new();
+    let owner = p.wallet(1);
+    let ix = instructions::create_multisig(&p.admin, vec![owner], 2);
+    assert_error(p.svm.process(ix), ErrorCode::InvalidMultisigThreshold);
+}
+
+#[test]
+fn not_multisig_owner() {
+    let mut p = Protocol::new();
+    let owner = p.wallet(1);
+    let mallory = p.wallet(1);
+    p.svm.process(instructions::create_multisig(&p.admin, vec![owner], 1)).unwrap();
+    let proposed = instructions::set_multisig_owners(vec![mallory], 1);
+    let ix = instructions::propose_multisig_tx(&mallory, 0, &proposed);
+    assert_error(p.svm.process(ix), ErrorCode::NotMultisigOwner);
+}
+
+#[test]
+fn not_enough_approvals() {
+    let mut p = Protocol::new();
+    let (a, b) = (p.wallet(1), p.wallet(1));
+    p.svm.process(instructions::create_multisig(&p.admin, vec![a, b], 2)).unwrap();
+    let proposed = instructions::set_multisig_owners(vec![a], 1);
+    p.svm.process(instructions::propose_multisig_tx(&a, 0, &proposed)).unwrap();
+    let ix = instructions::execute_multisig_tx(0, &proposed);
+    assert_error(p.svm.process(ix), ErrorCode::NotEnoughApprovals);
+}
+
+#[test]
+fn proposal_already_executed() {
+    let mut p = Protocol::new();
+    let (a, b) = (p.wallet(1), p.wallet(1));
+    p.svm.process(instructions::create_multisig(&p.admin, vec![a, b], 2)).unwrap();
+    let proposed = instructions::set_protocol_status(&pda::multisig_signer().0, ProtocolStatus::Active);
+    p.svm.process(instructions::propose_multisig_tx(&a, 0, &proposed)).unwrap();
+    p.svm.process(instructions::approve_multisig_tx(&b, 0)).unwrap();
+    // The multisig only acts for the protocol once it holds the admin key
+    p.svm.process(instructions::propose_admin(&p.admin, pda::multisig_signer().0)).unwrap();
+    let accept = instructions::accept_admin(&pda::multisig_signer().0);
+    p.svm.process(instructions::propose_multisig_tx(&a, 1, &accept)).unwrap();
+    p.svm.process(instructions::approve_multisig_tx(&b, 1)).unwrap();
+    p.svm.process(instructions::execute_multisig_tx(1, &accept)).unwrap();
+    assert_eq!(p.state().admin, pda::multisig_signer().0);
+
+    p.svm.process(instructions::execute_multisig_tx(0, &proposed)).unwrap();
+    let ix = instructions::execute_multisig_tx(0, &proposed);
+    assert_error(p.svm.process(ix), ErrorCode::ProposalAlreadyExecuted);
+}
+
+#[test]
+fn stale_proposal() {
+    let mut p = Protocol::new();
+    let (a, b) = (p.wallet(1), p.wallet(1));
+    p.svm.process(instructions::create_multisig(&p.admin, vec![a, b], 1)).unwrap();
+    let pending = instructions::set_multisig_owners(vec![b], 1);
+    p.svm.process(instructions::propose_multisig_tx(&a, 0, &pending)).unwrap();
+
+    // Changing the owner set invalidates proposal #0
+    let rotate = instructions::set_multisig_owners(vec![a, b], 2);
+    p.svm.process(instructions::propose_multisig_tx(&a, 1, &rotate)).unwrap();
+    p.svm.process(instructions::execute_multisig_tx(1, &rotate)).unwrap();
+
+    let ix = instructions::approve_multisig_tx(&b, 0);
+    assert_error(p.svm.process(ix), ErrorCode::StaleProposal);
+}
+
+#[test]
+fn account_already_migrated() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    // Accounts created by this program are born on the current layout
+    let ix = instructions::migrate_user_account(&alice, &alice);
+    assert_error(p.svm.process(ix), ErrorCode::AccountAlreadyMigrated);
+}
+
+#[test]
+fn round_is_current() {
+    let (mut p, _) = decided_round();
+    let ix = instructions::close_round(0, &pda::protocol_state().0);
+    assert_error(p.svm.process(ix), ErrorCode::RoundIsCurrent);
+}
+
+#[test]
+fn round_not_settled() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    let winner = p.finish_round().unwrap().event::<rafa::RoundFinalized>().winner;
+    let loser = if winner == alice { bob } else { alice };
+    let close = instructions::close_round(0, &pda::protocol_state().0);
+
+    p.svm.process(instructions::process_withdrawal(&loser, 0)).unwrap();
+    // The winner's stake and prize are still owed
+    assert_error(p.svm.process(close.clone()), ErrorCode::RoundNotSettled);
+    p.svm.process(instructions::create_claim_ticket_winner(&winner, 0)).unwrap();
+    p.svm.process(instructions::claim_prize(&winner, 0)).unwrap();
+    p.svm.process(close).unwrap();
+}
+
+#[test]
+fn prize_not_claimed() {
+    let (mut p, [alice, _]) = decided_round();
+    p.svm.process(instructions::create_claim_ticket_winner(&alice, 0)).unwrap();
+    let ix = instructions::close_claim_ticket(0, &alice, &alice);
+    assert_error(p.svm.process(ix), ErrorCode::PrizeNotClaimed);
+}
+
+#[test]
+fn user_account_not_empty() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    let ix = instructions::close_user_account(&alice);
+    assert_error(p.svm.process(ix), ErrorCode::UserAccountNotEmpty);
+}
+
+#[test]
+fn winding_down() {
+    let mut p = Protocol::new();
+    p.svm.process(instructions::begin_wind_down(&p.admin)).unwrap();
+    assert_error(p.svm.process(instructions::begin_wind_down(&p.admin)), ErrorCode::WindingDown);
+    let ix = instructions::set_protocol_status(&p.admin, ProtocolStatus::Active);
+    assert_error(p.svm.process(ix), ErrorCode::WindingDown);
+}
+
+#[test]
+fn not_winding_down() {
+    let mut p = Protocol::new();
+    let ix = instructions::close_protocol_state(&p.admin);
+    assert_error(p.svm.process(ix), ErrorCode::NotWindingDown);
+}
+
+#[test]
+fn wind_down_grace_period() {
+    let mut p = Protocol::new();
+    p.svm.process(instructions::begin_wind_down(&p.admin)).unwrap();
+    p.svm.warp(rafa::WIND_DOWN_GRACE_SECONDS - 1);
+    let ix = instructions::close_protocol_state(&p.admin);
+    assert_error(p.svm.process(ix), ErrorCode::WindDownGracePeriod);
+}
+
+#[test]
+fn outstanding_stake() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.svm.process(instructions::begin_wind_down(&p.admin)).unwrap();
+    p.svm.warp(rafa::WIND_DOWN_GRACE_SECONDS);
+    // Alice's round was never cancelled, so her stake is still in
+    let ix = instructions::close_protocol_state(&p.admin);
+    assert_error(p.svm.process(ix), ErrorCode::OutstandingStake);
+}
+
+#[test]
+fn invalid_queue_entry() {
+    let (mut p, [alice, bob]) = decided_round();
+    p.svm.process(instructions::request_withdrawal(&bob, 0, tickets(1))).unwrap();
+    p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(1))).unwrap();
+
+    // Bob asked first, so Alice cannot be paid ahead of him
+    let ix = instructions::process_withdrawals_batch(0, &[alice]);
+    assert_error(p.svm.process(ix), ErrorCode::InvalidQueueEntry);
+}
+
+#[test]
+fn withdrawal_queue_not_empty() {
+    let (mut p, [_, bob]) = decided_round();
+    p.svm.process(instructions::request_withdrawal(&bob, 0, tickets(1))).unwrap();
+    assert_error(p.svm.process(instructions::close_withdrawal_queue(0)), ErrorCode::WithdrawalQueueNotEmpty);
+
+    p.svm.process(instructions::process_withdrawals_batch(0, &[bob])).unwrap();
+    p.svm.process(instructions::close_withdrawal_queue(0)).unwrap();
+}
+
+#[test]
+fn insufficient_liquidity() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    // Only a tenth of the escrow is kept liquid
+    let ix = instructions::instant_withdraw(&alice, 0, tickets(1));
+    assert_error(p.svm.process(ix), ErrorCode::InsufficientLiquidity);
+}
+
+#[test]
+fn instant_withdraw_closed() {
+    let (mut p, [_, bob]) = decided_round();
+    let ix = instructions::instant_withdraw(&bob, 0, tickets(1));
+    assert_error(p.svm.process(ix), ErrorCode::InstantWithdrawClosed);
+}
+
+#[test]
+fn pending_withdrawal() {
+    let (mut p, [_, bob]) = decided_round();
+    p.svm.process(instructions::request_withdrawal(&bob, 0, tickets(1))).unwrap();
+    let carol = p.wallet(10);
+    p.deposit(&carol, tickets(1)).unwrap();
+    assert_error(p.svm.process(instructions::roll_over(&bob, 0, 1)), ErrorCode::PendingWithdrawal);
+}
+
+#[test]
+fn recipient_has_position() {
+    // Bob's stake in round 0 is not settled yet when Carol's round 1 position would join it
+    let (mut p, [_, bob]) = decided_round();
+    let carol = p.wallet(10);
+    p.deposit(&carol, tickets(1)).unwrap();
+    let ix = instructions::transfer_position(&carol, &bob, 1);
+    assert_error(p.svm.process(ix), ErrorCode::RecipientHasPosition);
+
+    // Nor can a position be merged into itself
+    let ix = instructions::transfer_position(&carol, &carol, 1);
+    assert_error(p.svm.process(ix), ErrorCode::RecipientHasPosition);
+}
+
+#[test]
+fn transfer_closed() {
+    let (mut p, [_, bob]) = decided_round();
+    let carol = Pubkey::new_unique();
+    let ix = instructions::transfer_position(&bob, &carol, 0);
+    assert_error(p.svm.process(ix), ErrorCode::TransferClosed);
+}
+
+#[test]
+fn below_minimum_tickets() {
+    let mut p = Protocol::new();
+    configure(&mut p, ConfigChange::MinTicketsPerDeposit(2));
+    let alice = p.wallet(10);
+    assert_error(p.deposit(&alice, tickets(1)), ErrorCode::BelowMinimumTickets);
+    p.deposit(&alice, tickets(2)).unwrap();
+}
+
+#[test]
+fn wallet_ticket_cap_exceeded() {
+    let mut p = Protocol::new();
+    configure(&mut p, ConfigChange::MaxTicketsPerUser(3));
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    assert_error(p.deposit(&alice, tickets(2)), ErrorCode::WalletTicketCapExceeded);
+    p.deposit(&alice, tickets(1)).unwrap();
+}
+
+#[test]
+fn round_ticket_cap_exceeded() {
+    let mut p = Protocol::new();
+    configure(&mut p, ConfigChange::MaxTicketsPerRound(3));
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    assert_error(p.deposit(&bob, tickets(2)), ErrorCode::RoundTicketCapExceeded);
+    p.deposit(&bob, tickets(1)).unwrap();
+}
+
+/// Alice holds tickets 0-1 and Bob ticket 2 in round 0, whose last epoch is over
+fn expired_round() -> (Protocol, [Pubkey; 2]) {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    p.svm.warp(3 * EPOCH_SECONDS);
+    (p, [alice, bob])
+}
+
+#[test]
+fn duplicate_participant() {
+    let (mut p, [alice, _]) = expired_round();
+    // Alice's two tickets counted twice would pass for more than Bob's missing one
+    let alice_account = pda::user_account(&alice).0;
+    let ix = instructions::crank(0, &[alice_account, alice_account]);
+    assert_error(p.svm.process(ix), ErrorCode::DuplicateParticipant);
+}
+
+#[test]
+fn overlapping_ticket_ranges() {
+    let (mut p, [_, bob]) = expired_round();
+    let mut user = p.user(&bob);
+    user.ticket_start = 1;
+    p.svm.write(&pda::user_account(&bob).0, &user);
+    assert_error(p.crank(0), ErrorCode::OverlappingTicketRanges);
+}
+
+#[test]
+fn incomplete_ticket_ledger() {
+    let (mut p, [alice, _]) = expired_round();
+    let ix = instructions::crank(0, &[pda::user_account(&alice).0]);
+    assert_error(p.svm.process(ix), ErrorCode::IncompleteTicketLedger);
+    p.crank(0).unwrap();
+}
+
+#[test]
+fn receipt_position() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let mint = p.deposit_with_receipt(&alice, tickets(1)).unwrap();
+
+    // Neither a gift nor a transfer may grow the range the receipt's metadata records
+    let ix = instructions::deposit_for(&alice, &mint, 0, tickets(1), &[]);
+    assert_error(p.svm.process(ix), ErrorCode::ReceiptPosition);
+    p.deposit(&alice, tickets(1)).unwrap();
+    let ix = instructions::transfer_position(&alice, &mint, 0);
+    assert_error(p.svm.process(ix), ErrorCode::ReceiptPosition);
+
+    // Nor can a refund push its stake to the mint
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+    assert_error(p.svm.process(instructions::refund_cancelled(&mint, 0)), ErrorCode::ReceiptPosition);
+}
+
+#[test]
+fn receipt_mint_signing_as_owner() {
+    // The mint is a PDA, but nothing owner-signed may treat the receipt position as its own either way
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let mint = p.deposit_with_receipt(&alice, tickets(2)).unwrap();
+    let owner_signed = [
+        instructions::deposit(&mint, 0, tickets(1), &[]),
+        instructions::request_withdrawal(&mint, 0, tickets(1)),
+        instructions::instant_withdraw(&mint, 0, tickets(1)),
+        instructions::close_user_account(&mint),
+    ];
+    for ix in owner_signed {
+        assert_error(p.svm.process(ix), ErrorCode::ReceiptPosition);
+    }
+
+    // Nor once it has won
+    p.finish_round().unwrap();
+    assert_eq!(p.round(0).winner, Some(mint));
+    p.deposit(&alice, tickets(1)).unwrap();
+    let owner_signed = [
+        instructions::process_withdrawal(&mint, 0),
+        instructions::roll_over(&mint, 0, p.state().current_round),
+        instructions::transfer_position(&mint, &alice, 0),
+    ];
+    for ix in owner_signed {
+        assert_error(p.svm.process(ix), ErrorCode::ReceiptPosition);
+    }
+    p.svm.process(instructions::create_claim_ticket(&p.admin, 0, &mint, 0, tickets(2))).unwrap();
+    assert_error(p.svm.process(instructions::claim_prize(&mint, 0)), ErrorCode::ReceiptPosition);
+}
+
+#[test]
+fn not_a_receipt() {
+    // A mint of Mallory's own, with a gifted position keyed by it
+    let mut p = Protocol::new();
+    let mallory = p.wallet(10);
+    let mint = Pubkey::new_unique();
+    let receipt = pda::receipt_token_account(&mallory, &mint);
+    let lamports = Rent::default().minimum_balance(Mint::LEN);
+    p.svm.set_account(mint, Account::new(lamports, Mint::LEN, &spl_token_2022::ID));
+    let setup = [
+        token_instruction::initialize_mint2(&spl_token_2022::ID, &mint, &mallory, None, 0).unwrap(),
+        create_associated_token_account(&mallory, &mallory, &mint, &spl_token_2022::ID),
+        token_instruction::mint_to(&spl_token_2022::ID, &mint, &receipt, &mallory, &[], 1).unwrap(),
+    ];
+    p.svm.process_transaction(&setup).unwrap();
+    p.svm.process(instructions::deposit_for(&mallory, &mint, 0, tickets(1), &[])).unwrap();
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+
+    let ix = instructions::redeem_receipt(&mallory, &mint, 0);
+    assert_error(p.svm.process(ix), ErrorCode::NotAReceipt);
+}
+
+#[test]
+fn not_receipt_holder() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    let mint = p.deposit_with_receipt(&alice, tickets(1)).unwrap();
+    p.transfer_receipt(&alice, &bob, &mint).unwrap();
+    p.finish_round().unwrap();
+
+    let ix = instructions::redeem_receipt(&alice, &mint, 0);
+    assert_error(p.svm.process(ix), ErrorCode::NotReceiptHolder);
+}
+
+#[test]
+fn receipts_capped() {
+    let mut p = Protocol::new();
+    configure(&mut p, ConfigChange::MaxTicketsPerUser(3));
+    let alice = p.wallet(10);
+    let ix = instructions::deposit_with_receipt(&alice, 0, 0, tickets(1), &[]);
+    assert_error(p.svm.process(ix), ErrorCode::ReceiptsCapped);
+    p.svm.process(instructions::propose_change(&p.admin, ConfigChange::MaxTicketsPerUser(0))).unwrap();
+    p.svm.warp(rafa::DEFAULT_TIMELOCK_SECONDS);
+    p.svm.process(instructions::execute_change(&p.admin, 1)).unwrap();
+    p.deposit_with_receipt(&alice, tickets(1)).unwrap();
+}
+
+#[test]
+fn round_full() {
+    let mut p = Protocol::new();
+    let wallets: Vec<Pubkey> = (0..MAX_ROUND_PARTICIPANTS).map(|_| p.wallet(10)).collect();
+    for wallet in &wallets {
+        p.deposit(wallet, tickets(1)).unwrap();
+    }
+    let late = p.wallet(10);
+    assert_error(p.deposit(&late, tickets(1)), ErrorCode::RoundFull);
+    let ix = instructions::deposit_for(&late, &late, 0, tickets(1), &[]);
+    assert_error(p.svm.process(ix), ErrorCode::RoundFull);
+
+    // Those already in may still buy more, and one crank draws from all of them
+    p.deposit(&wallets[0], tickets(1)).unwrap();
+    p.finish_round().unwrap();
+    assert!(p.round(0).is_complete);
+}
+
+#[test]
+fn already_reconciled() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    let positions = [pda::user_account(&alice).0];
+    assert_error(p.svm.process(instructions::reconcile_round(&p.admin, 0, &positions)), ErrorCode::AlreadyReconciled);
+    let ix = instructions::reconcile_liabilities(&p.admin, &positions);
+    assert_error(p.svm.process(ix), ErrorCode::AlreadyReconciled);
+}
+
+#[test]
+fn not_an_open_position() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    let mut round = p.round(0);
+    round.positions_tracked = false;
+    p.svm.write(&pda::round_state(0).0, &round);
+    let mut state = p.state();
+    state.liabilities_tracked = false;
+    p.svm.write(&pda::protocol_state().0, &state);
+
+    // Not a UserAccount at all
+    let ix = instructions::reconcile_round(&p.admin, 0, &[pda::round_state(0).0]);
+    assert_error(p.svm.process(ix), ErrorCode::NotAnOpenPosition);
+    let ix = instructions::reconcile_liabilities(&p.admin, &[bob]);
+    assert_error(p.svm.process(ix), ErrorCode::NotAnOpenPosition);
+
+    // A position with nothing left staked
+    let (address, bump) = pda::user_account(&bob);
+    let account = p.svm.account(&pda::user_account(&alice).0).unwrap().clone();
+    p.svm.set_account(address, account);
+    let mut user = p.user(&alice);
+    (user.owner, user.bump, user.balance) = (bob, bump, 0);
+    p.svm.write(&address, &user);
+    let ix = instructions::reconcile_liabilities(&p.admin, &[address]);
+    assert_error(p.svm.process(ix), ErrorCode::NotAnOpenPosition);
+}
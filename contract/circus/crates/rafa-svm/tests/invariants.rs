@@ -0,0 +1,344 @@
+//! Random instruction sequences across several users, checking the payout and ticket accounting
+//! after every step:
+//! - each ticket of a round has at most one owner, and every ticket of an open round has one
+//! - no user gets back more stake than they deposited, overall and within each round
+//! - a round's prize is claimed at most once and never exceeds what finalization reserved
+//! - everything paid out stays within what was deposited and seeded
+//! - the protocol account always holds what it owes: stakes, reserved prizes and the seed pool
+//! - a round counts exactly the participants it still owes a stake, and closes only at zero
+//! - every pending withdrawal waits in its round's withdrawal queue, at most once
+//!
+//! Failed instructions are expected (most random steps are invalid); they must fail cleanly
+//! inside the program, never by breaking the runtime's account rules.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use proptest::prelude::*;
+use rafa::{
+    ClaimTicket, DepositMade, InstantWithdrawal, PositionRolledOver, PositionTransferred, PrizeClaimed, ProtocolState,
+    RefundProcessed, RoundState, UserAccount, WithdrawalProcessed, WithdrawalQueue, MAX_WITHDRAWALS_PER_BATCH,
+};
+use rafa_client::{instructions, pda};
+use rafa_svm::fixture::{tickets, Protocol, EPOCH_SECONDS};
+use rafa_svm::{Outcome, TransactionError};
+
+const USERS: usize = 4;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit { user: usize, tickets: u64 },
+    DepositFor { payer: usize, user: usize, tickets: u64 },
+    RequestWithdrawal { user: usize, tickets: u64 },
+    InstantWithdraw { user: usize, tickets: u64 },
+    RollOver { user: usize },
+    TransferPosition { user: usize, recipient: usize },
+    Warp { seconds: i64 },
+    Crank,
+    Snapshot,
+    Seed { lamports: u64 },
+    Cancel,
+    /// `back` rounds before the current one
+    CreateClaimTicket { user: usize, back: u64 },
+    ClaimPrize { user: usize, back: u64 },
+    ProcessWithdrawal { user: usize, back: u64 },
+    Refund { user: usize, back: u64 },
+    CloseRound { back: u64 },
+    CloseClaimTicket { user: usize, back: u64 },
+    CloseUserAccount { user: usize },
+    PayWithdrawals { back: u64 },
+    CloseWithdrawalQueue { back: u64 },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let user = 0..USERS;
+    let back = 0..3u64;
+    prop_oneof![
+        6 => (user.clone(), 1..6u64).prop_map(|(user, tickets)| Op::Deposit { user, tickets }),
+        1 => (user.clone(), user.clone(), 1..4u64).prop_map(|(payer, user, tickets)| Op::DepositFor { payer, user, tickets }),
+        1 => (user.clone(), 1..4u64).prop_map(|(user, tickets)| Op::RequestWithdrawal { user, tickets }),
+        1 => (user.clone(), 1..3u64).prop_map(|(user, tickets)| Op::InstantWithdraw { user, tickets }),
+        1 => user.clone().prop_map(|user| Op::RollOver { user }),
+        1 => (user.clone(), user.clone()).prop_map(|(user, recipient)| Op::TransferPosition { user, recipient }),
+        3 => (1..=3 * EPOCH_SECONDS).prop_map(|seconds| Op::Warp { seconds }),
+        2 => Just(Op::Crank),
+        1 => Just(Op::Snapshot),
+        1 => (0..2 * LAMPORTS_PER_SOL).prop_map(|lamports| Op::Seed { lamports }),
+        1 => Just(Op::Cancel),
+        2 => (user.clone(), back.clone()).prop_map(|(user, back)| Op::CreateClaimTicket { user, back }),
+        2 => (user.clone(), back.clone()).prop_map(|(user, back)| Op::ClaimPrize { user, back }),
+        2 => (user.clone(), back.clone()).prop_map(|(user, back)| Op::ProcessWithdrawal { user, back }),
+        1 => (user.clone(), back.clone()).prop_map(|(user, back)| Op::Refund { user, back }),
+        1 => (1..3u64).prop_map(|back| Op::CloseRound { back }),
+        1 => (user.clone(), back.clone()).prop_map(|(user, back)| Op::CloseClaimTicket { user, back }),
+        1 => user.prop_map(|user| Op::CloseUserAccount { user }),
+        2 => back.clone().prop_map(|back| Op::PayWithdrawals { back }),
+        1 => back.prop_map(|back| Op::CloseWithdrawalQueue { back }),
+    ]
+}
+
+/// What the emitted events say moved in and out of the protocol
+#[derive(Default)]
+struct Ledger {
+    /// Deposited, plus positions transferred in, minus positions transferred out
+    deposited: HashMap<Pubkey, u64>,
+    /// Stake paid back through withdrawals, claims and refunds, instant ones net of the exit fee
+    returned: HashMap<Pubkey, u64>,
+    /// Staked (deposited or rolled over) and paid back per (user, round); queued and partial
+    /// withdrawals pay a round in parts
+    staked_in_round: HashMap<(Pubkey, u64), u64>,
+    returned_from_round: HashMap<(Pubkey, u64), u64>,
+    prizes: HashMap<u64, u64>,
+    seeded: u64,
+}
+
+impl Ledger {
+    fn record(&mut self, outcome: &Outcome) {
+        for e in outcome.events::<DepositMade>() {
+            *self.deposited.entry(e.user).or_default() += e.amount;
+            *self.staked_in_round.entry((e.user, e.round_id)).or_default() += e.amount;
+        }
+        for e in outcome.events::<PositionRolledOver>() {
+            *self.staked_in_round.entry((e.user, e.round_id)).or_default() += e.amount;
+        }
+        // A transferred position takes its stake, and the right to be paid it, along
+        for e in outcome.events::<PositionTransferred>() {
+            *self.deposited.get_mut(&e.from).expect("transfer from a wallet that never staked") -= e.amount;
+            *self.deposited.entry(e.to).or_default() += e.amount;
+            *self.staked_in_round.get_mut(&(e.from, e.round_id)).expect("transfer of a position never staked") -= e.amount;
+            *self.staked_in_round.entry((e.to, e.round_id)).or_default() += e.amount;
+        }
+        let stake_returns = outcome
+            .events::<WithdrawalProcessed>()
+            .into_iter()
+            .map(|e| (e.user, e.round_id, e.amount))
+            .chain(outcome.events::<RefundProcessed>().into_iter().map(|e| (e.user, e.round_id, e.amount)))
+            .chain(outcome.events::<InstantWithdrawal>().into_iter().map(|e| (e.user, e.round_id, e.amount - e.exit_fee)))
+            .chain(outcome.events::<PrizeClaimed>().into_iter().map(|e| (e.winner, e.round_id, e.stake_amount)));
+        for (user, round_id, amount) in stake_returns {
+            let from_round = self.returned_from_round.entry((user, round_id)).or_default();
+            *from_round += amount;
+            let staked = self.staked_in_round.get(&(user, round_id)).copied().unwrap_or(0);
+            assert!(*from_round <= staked, "{user} got back {from_round} of {staked} staked in round {round_id}");
+            *self.returned.entry(user).or_default() += amount;
+        }
+        for e in outcome.events::<PrizeClaimed>() {
+            assert!(self.prizes.insert(e.round_id, e.prize_amount).is_none(), "round {} prize claimed twice", e.round_id);
+        }
+    }
+}
+
+struct Harness {
+    p: Protocol,
+    users: Vec<Pubkey>,
+    ledger: Ledger,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let mut p = Protocol::new();
+        let users = (0..USERS).map(|_| p.wallet(100)).collect();
+        Self { p, users, ledger: Ledger::default() }
+    }
+
+    fn round_back(&self, back: u64) -> u64 {
+        self.p.state().current_round.saturating_sub(back)
+    }
+
+    fn apply(&mut self, op: &Op) {
+        let current = self.p.state().current_round;
+        let protocol = pda::protocol_state().0;
+        let result = match *op {
+            Op::Deposit { user, tickets: n } => self.p.deposit(&self.users[user], tickets(n)),
+            Op::DepositFor { payer, user, tickets: n } => {
+                let participants = self.p.participants(current);
+                let ix = instructions::deposit_for(&self.users[payer], &self.users[user], current, tickets(n), &participants);
+                self.p.svm.process(ix)
+            }
+            Op::RequestWithdrawal { user, tickets: n } => {
+                let owner = self.users[user];
+                let round_id = self.p.svm.get::<UserAccount>(&pda::user_account(&owner).0).map_or(0, |u| u.round_joined);
+                self.p.svm.process(instructions::request_withdrawal(&owner, round_id, tickets(n)))
+            }
+            Op::RollOver { user } => {
+                let owner = self.users[user];
+                let from_round = self.p.svm.get::<UserAccount>(&pda::user_account(&owner).0).map_or(0, |u| u.round_joined);
+                self.p.svm.process(instructions::roll_over(&owner, from_round, current))
+            }
+            Op::TransferPosition { user, recipient } => {
+                let owner = self.users[user];
+                let round_id = self.p.svm.get::<UserAccount>(&pda::user_account(&owner).0).map_or(0, |u| u.round_joined);
+                self.p.svm.process(instructions::transfer_position(&owner, &self.users[recipient], round_id))
+            }
+            Op::InstantWithdraw { user, tickets: n } => {
+                let owner = self.users[user];
+                let round_id = self.p.svm.get::<UserAccount>(&pda::user_account(&owner).0).map_or(0, |u| u.round_joined);
+                self.p.svm.process(instructions::instant_withdraw(&owner, round_id, tickets(n)))
+            }
+            Op::Warp { seconds } => {
+                self.p.svm.warp(seconds);
+                return;
+            }
+            Op::Crank => self.p.crank(current),
+            Op::Snapshot => {
+                let participants = self.p.participants(current);
+                self.p.svm.process(instructions::take_snapshot_batch(current, &participants))
+            }
+            Op::Seed { lamports } => self.p.seed(lamports),
+            Op::Cancel => self.p.svm.process(instructions::cancel_round(&self.p.admin, current)),
+            Op::CreateClaimTicket { user, back } => {
+                let ix = instructions::create_claim_ticket_winner(&self.users[user], self.round_back(back));
+                self.p.svm.process(ix)
+            }
+            Op::ClaimPrize { user, back } => {
+                let ix = instructions::claim_prize(&self.users[user], self.round_back(back));
+                self.p.svm.process(ix)
+            }
+            Op::ProcessWithdrawal { user, back } => {
+                let ix = instructions::process_withdrawal(&self.users[user], self.round_back(back));
+                self.p.svm.process(ix)
+            }
+            Op::Refund { user, back } => {
+                let ix = instructions::refund_cancelled(&self.users[user], self.round_back(back));
+                self.p.svm.process(ix)
+            }
+            Op::CloseRound { back } => {
+                let round_id = self.round_back(back);
+                let Some(round) = self.p.svm.get::<RoundState>(&pda::round_state(round_id).0) else {
+                    return;
+                };
+                let ix = instructions::close_round(round_id, &rafa::rent_receiver(round.rent_payer, protocol));
+                self.p.svm.process(ix)
+            }
+            Op::CloseClaimTicket { user, back } => {
+                let (round_id, winner) = (self.round_back(back), self.users[user]);
+                let Some(ticket) = self.p.svm.get::<ClaimTicket>(&pda::claim_ticket(round_id, &winner).0) else {
+                    return;
+                };
+                let rent_payer = rafa::rent_receiver(ticket.rent_payer, protocol);
+                self.p.svm.process(instructions::close_claim_ticket(round_id, &winner, &rent_payer))
+            }
+            Op::CloseUserAccount { user } => {
+                self.p.svm.process(instructions::close_user_account(&self.users[user]))
+            }
+            Op::PayWithdrawals { back } => {
+                let round_id = self.round_back(back);
+                let Some(queue) = self.p.svm.get::<WithdrawalQueue>(&pda::withdrawal_queue(round_id).0) else {
+                    return;
+                };
+                let waiting: Vec<Pubkey> =
+                    queue.entries[queue.head as usize..].iter().take(MAX_WITHDRAWALS_PER_BATCH).copied().collect();
+                self.p.svm.process(instructions::process_withdrawals_batch(round_id, &waiting))
+            }
+            Op::CloseWithdrawalQueue { back } => {
+                self.p.svm.process(instructions::close_withdrawal_queue(self.round_back(back)))
+            }
+        };
+        match result {
+            Ok(outcome) => {
+                if let Op::Seed { lamports } = *op {
+                    self.ledger.seeded += lamports;
+                }
+                self.ledger.record(&outcome);
+            }
+            Err(err) => assert!(matches!(err, TransactionError::Program(_)), "{op:?} broke the runtime: {err:?}"),
+        }
+    }
+
+    fn check(&self) {
+        let state = self.p.state();
+        let accounts = self.p.svm.program_accounts::<UserAccount>();
+        let rounds: Vec<RoundState> = (0..=state.current_round)
+            .filter_map(|id| self.p.svm.get(&pda::round_state(id).0))
+            .collect();
+
+        // Tickets: a holder is a user whose stake in the round is not paid out yet and who has
+        // not forfeited every ticket
+        for round in &rounds {
+            let positions: Vec<&UserAccount> = accounts
+                .iter()
+                .map(|(_, user)| user)
+                .filter(|user| user.round_joined == round.round_id)
+                .filter(|user| user.balance + user.pending_withdrawal_amount > 0)
+                .collect();
+            assert_eq!(round.open_positions as usize, positions.len(), "round {}: open positions", round.round_id);
+            let mut ranges: Vec<(u64, u64)> = positions.iter().flat_map(|user| user.ranges()).collect();
+            ranges.sort();
+            for window in ranges.windows(2) {
+                assert!(window[0].1 < window[1].0, "round {}: ranges {window:?} overlap", round.round_id);
+            }
+            if let Some(last) = ranges.last() {
+                assert!(last.1 < round.total_tickets_sold, "round {}: ticket past the end", round.round_id);
+            }
+            if !round.is_complete {
+                let held: u64 = ranges.iter().map(|(start, end)| end - start + 1).sum();
+                assert_eq!(held, round.live_tickets(), "round {}: live tickets without an owner", round.round_id);
+                // Requested withdrawals gave up their tickets; only the balance still plays
+                for user in &positions {
+                    assert_eq!(tickets(user.tickets_held()), user.balance, "{} holds tickets for stake it withdrew", user.owner);
+                }
+            }
+        }
+
+        // Withdrawal queues; a winner's pending stake leaves the queue to be paid with the prize
+        let winners: Vec<(u64, Pubkey)> =
+            rounds.iter().filter_map(|round| round.winner.map(|w| (round.round_id, w))).collect();
+        for (_, user) in accounts
+            .iter()
+            .filter(|(_, user)| user.pending_withdrawal_amount > 0)
+            .filter(|(_, user)| !winners.contains(&(user.round_joined, user.owner)))
+        {
+            let queue: WithdrawalQueue = self
+                .p
+                .svm
+                .get(&pda::withdrawal_queue(user.round_joined).0)
+                .unwrap_or_else(|| panic!("{} has a pending withdrawal but round {} has no queue", user.owner, user.round_joined));
+            let waiting = &queue.entries[queue.head as usize..];
+            let entries = waiting.iter().filter(|owner| **owner == user.owner).count();
+            assert_eq!(entries, 1, "{} waits {entries} times in round {}'s queue", user.owner, user.round_joined);
+        }
+
+        // Payouts
+        for (user, returned) in &self.ledger.returned {
+            let deposited = self.ledger.deposited.get(user).copied().unwrap_or(0);
+            assert!(*returned <= deposited, "{user} got back {returned} of {deposited} deposited");
+        }
+        for (round_id, prize) in &self.ledger.prizes {
+            // Closed rounds had their prize checked before closing
+            let Some(round) = self.p.svm.get::<RoundState>(&pda::round_state(*round_id).0) else {
+                continue;
+            };
+            assert!(*prize <= round.total_prize_lamports, "round {round_id}: prize {prize} over the reserved amount");
+        }
+        let paid: u64 = self.ledger.returned.values().sum::<u64>() + self.ledger.prizes.values().sum::<u64>();
+        let funded: u64 = self.ledger.deposited.values().sum::<u64>() + self.ledger.seeded;
+        assert!(paid <= funded, "paid {paid} out of {funded} deposited and seeded");
+
+        // Solvency
+        let stakes: u64 = accounts.iter().map(|(_, user)| user.balance + user.pending_withdrawal_amount).sum();
+        assert_eq!(state.outstanding_stake, stakes, "tracked stake drifted from the user accounts");
+        let reserved_prizes: u64 = rounds
+            .iter()
+            .filter(|round| round.winner.is_some() && !round.prize_claimed)
+            .map(|round| round.total_prize_lamports)
+            .sum();
+        let rent = Rent::default().minimum_balance(8 + ProtocolState::SIZE);
+        let owed = rent + stakes + reserved_prizes + state.prize_seed_amount;
+        let held = self.p.svm.lamports(&pda::protocol_state().0);
+        assert!(held >= owed, "protocol holds {held} but owes {owed}");
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn accounting_holds_for_any_instruction_sequence(ops in prop::collection::vec(op(), 1..80)) {
+        let mut harness = Harness::new();
+        for op in &ops {
+            harness.apply(op);
+            harness.check();
+        }
+    }
+}
@@ -0,0 +1,917 @@
+//! Happy paths through a round: deposits, epochs and snapshots, finalization, claims,
+//! withdrawals, refunds and ticket receipts.
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use rafa::{
+    AdminChanged, AdminProposed, ChangeCancelled, ChangeExecuted, ChangeProposed, ChangeQueueCreated, ClaimTicket,
+    ClaimTicketClosed, ConfigChange, DepositFunded, DepositMade, EpochAdvanced, InstantWithdrawal,
+    MultisigOwnersChanged, MultisigTxApproved, MultisigTxExecuted, MultisigTxProposed, PositionRolledOver,
+    PositionTransferred, PrizeClaimed, ProtocolClosed, ProtocolRole, ProtocolState, ProtocolStatus,
+    ProtocolStatusChanged, ReceiptMinted, ReceiptRedeemed, RefundProcessed, RoleChanged, RoundClosed,
+    RoundFinalized, RoundStarted, TicketsForfeited, UserAccount, UserAccountClosed, WindDownBegun,
+    WithdrawalProcessed, WithdrawalQueue, WithdrawalQueueClosed, WithdrawalRequested, DEFAULT_EXIT_FEE_BPS,
+    DEFAULT_LIQUID_BUFFER_BPS, DEFAULT_TIMELOCK_SECONDS, MAX_ROUND_PARTICIPANTS, MAX_WITHDRAWALS_PER_BATCH,
+    RECEIPT_SYMBOL,
+};
+use rafa_client::{instructions, pda};
+use rafa_svm::fixture::{tickets, Protocol, EPOCH_SECONDS};
+use rafa_svm::TransactionError;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use spl_token_2022::solana_program::program_option::COption;
+use spl_token_2022::state::Mint;
+use spl_token_metadata_interface::state::TokenMetadata;
+
+#[test]
+fn initialize_hands_every_role_to_the_admin() {
+    let p = Protocol::new();
+    let state = p.state();
+    assert_eq!(state.admin, p.admin);
+    assert_eq!(state.keeper, p.admin);
+    assert_eq!(state.guardian, p.admin);
+    assert_eq!(state.treasurer, p.admin);
+    assert_eq!(state.status, ProtocolStatus::Active);
+    assert_eq!(state.timelock_delay_seconds, DEFAULT_TIMELOCK_SECONDS);
+    assert_eq!(state.current_round, 0);
+}
+
+#[test]
+fn first_deposit_opens_the_round_and_assigns_tickets() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let before = p.svm.lamports(&alice);
+
+    let outcome = p.deposit(&alice, tickets(3)).unwrap();
+
+    let started = outcome.event::<RoundStarted>();
+    assert_eq!(started.round_id, 0);
+    assert_eq!(started.opened_by, alice);
+    let deposit = outcome.event::<DepositMade>();
+    assert_eq!((deposit.ticket_start, deposit.ticket_end, deposit.tickets), (0, 2, 3));
+
+    let round = p.round(0);
+    assert_eq!(round.epoch_in_round, 1);
+    assert_eq!(round.start_epoch, p.svm.now_ms());
+    assert_eq!(round.total_tickets_sold, 3);
+    assert_eq!(round.total_staked_lamports, tickets(3));
+
+    let user = p.user(&alice);
+    assert_eq!((user.ticket_start, user.ticket_end, user.balance), (0, 2, tickets(3)));
+    // Alice paid her stake plus rent for her UserAccount and the RoundState (no seed funds yet)
+    assert!(before - p.svm.lamports(&alice) > tickets(3));
+}
+
+#[test]
+fn gifted_tickets_belong_to_the_beneficiary() {
+    let mut p = Protocol::new();
+    let sponsor = p.wallet(10);
+    let bob = Pubkey::new_unique();
+
+    let before = p.svm.lamports(&sponsor);
+    let ix = instructions::deposit_for(&sponsor, &bob, 0, tickets(2), &[]);
+    let outcome = p.svm.process(ix).unwrap();
+    let funded = outcome.event::<DepositFunded>();
+    assert_eq!((funded.payer, funded.beneficiary, funded.amount), (sponsor, bob, tickets(2)));
+    assert_eq!(outcome.event::<DepositMade>().user, bob);
+    // The sponsor pays the stake and the rent; the beneficiary needs no lamports at all
+    assert!(before - p.svm.lamports(&sponsor) > tickets(2));
+    assert_eq!(p.svm.lamports(&bob), 0);
+
+    let user = p.user(&bob);
+    assert_eq!((user.owner, user.balance, user.ticket_start, user.ticket_end), (bob, tickets(2), 0, 1));
+    assert!(p.svm.account(&pda::user_account(&sponsor).0).is_none());
+    assert_eq!(p.participants(0), vec![pda::user_account(&bob).0]);
+}
+
+#[test]
+fn gifted_tickets_do_not_block_the_beneficiarys_own() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let sponsor = p.wallet(10);
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.svm.process(instructions::deposit_for(&sponsor, &bob, 0, tickets(2), &[])).unwrap();
+    p.deposit(&alice, tickets(1)).unwrap();
+
+    // Bob tops up his gifted position, then Alice hands him hers
+    p.deposit(&bob, tickets(1)).unwrap();
+    let outcome = p.svm.process(instructions::transfer_position(&alice, &bob, 0)).unwrap();
+    let moved = outcome.event::<PositionTransferred>();
+    assert_eq!((moved.amount, moved.ticket_start, moved.ticket_end), (tickets(1), 2, 2));
+
+    let user = p.user(&bob);
+    assert_eq!((user.balance, user.tickets_held()), (tickets(4), 4));
+    assert_eq!(user.ranges().collect::<Vec<_>>(), vec![(0, 1), (3, 3), (2, 2)]);
+    assert_eq!(p.user(&alice).balance, 0);
+    assert_eq!(p.round(0).open_positions, 1);
+
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    assert_eq!(finalized.winner, bob);
+}
+
+#[test]
+fn ticket_receipts_pay_whoever_holds_them() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+
+    let round_id = p.state().current_round;
+    let participants = p.participants(round_id);
+    let mint = pda::receipt_mint(round_id, &alice, 0).0;
+    let ix = instructions::deposit_with_receipt(&alice, round_id, 0, tickets(3), &participants);
+    let minted = p.svm.process(ix).unwrap().event::<ReceiptMinted>();
+    assert_eq!((minted.mint, minted.holder, minted.ticket_start, minted.ticket_end), (mint, alice, 0, 2));
+    assert_eq!(p.receipt_tokens(&alice, &mint), 1);
+
+    // The position is keyed by the mint, and the mint can never issue a second token
+    let position = p.user(&mint);
+    assert_eq!((position.owner, position.balance, position.is_receipt), (mint, tickets(3), true));
+    assert!(p.svm.account(&pda::user_account(&alice).0).is_none());
+    let data = &p.svm.account(&mint).unwrap().data;
+    let state = StateWithExtensions::<Mint>::unpack(data).unwrap();
+    assert_eq!((state.base.supply, state.base.mint_authority), (1, COption::None));
+    let metadata = state.get_variable_len_extension::<TokenMetadata>().unwrap();
+    assert_eq!(metadata.symbol, RECEIPT_SYMBOL);
+    let field = |key: &str| metadata.additional_metadata.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+    assert_eq!((field("round_id"), field("ticket_start"), field("ticket_end")), (Some("0"), Some("0"), Some("2")));
+
+    // Selling the receipt hands over the position, prize included
+    p.transfer_receipt(&alice, &bob, &mint).unwrap();
+    assert_eq!((p.receipt_tokens(&alice, &mint), p.receipt_tokens(&bob, &mint)), (0, 1));
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    assert_eq!(finalized.winner, mint);
+
+    let before = p.svm.lamports(&bob);
+    let outcome = p.svm.process(instructions::redeem_receipt(&bob, &mint, 0)).unwrap();
+    let redeemed = outcome.event::<ReceiptRedeemed>();
+    assert_eq!((redeemed.holder, redeemed.stake_amount), (bob, tickets(3)));
+    assert_eq!(redeemed.prize_amount, finalized.prize_amount);
+    // Plus the rent of the position, the mint and the token account
+    assert!(p.svm.lamports(&bob) - before > tickets(3) + finalized.prize_amount);
+    for closed in [mint, pda::user_account(&mint).0, pda::receipt_token_account(&bob, &mint)] {
+        assert!(p.svm.account(&closed).is_none());
+    }
+    let round = p.round(0);
+    assert!(round.prize_claimed);
+    assert_eq!(round.open_positions, 0);
+    assert_eq!(p.state().outstanding_stake, 0);
+}
+
+#[test]
+fn receipts_from_a_cancelled_round_redeem_the_principal() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let mint = p.deposit_with_receipt(&alice, tickets(2)).unwrap();
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+
+    let before = p.svm.lamports(&alice);
+    let outcome = p.svm.process(instructions::redeem_receipt(&alice, &mint, 0)).unwrap();
+    let redeemed = outcome.event::<ReceiptRedeemed>();
+    assert_eq!((redeemed.stake_amount, redeemed.prize_amount), (tickets(2), 0));
+    assert!(p.svm.lamports(&alice) - before > tickets(2));
+    assert_eq!(p.round(0).open_positions, 0);
+}
+
+#[test]
+fn later_deposits_extend_ticket_ranges() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.deposit(&bob, tickets(5)).unwrap();
+
+    let alice_account = p.user(&alice);
+    assert_eq!((alice_account.ticket_start, alice_account.ticket_end), (0, 2));
+    assert_eq!(alice_account.balance, tickets(3));
+    let bob_account = p.user(&bob);
+    assert_eq!((bob_account.ticket_start, bob_account.ticket_end), (3, 7));
+    assert_eq!(p.round(0).total_tickets_sold, 8);
+}
+
+#[test]
+fn interleaved_deposits_keep_a_range_each() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    p.deposit(&alice, tickets(3)).unwrap();
+    p.deposit(&bob, tickets(2)).unwrap();
+    p.deposit(&alice, tickets(1)).unwrap();
+    // Nobody bought in between, so this one grows Alice's last range
+    p.deposit(&alice, tickets(1)).unwrap();
+
+    let ranges = |p: &Protocol, owner| p.user(owner).ranges().collect::<Vec<_>>();
+    assert_eq!(ranges(&p, &alice), vec![(0, 1), (3, 5), (8, 9)]);
+    assert_eq!(ranges(&p, &bob), vec![(2, 2), (6, 7)]);
+    assert_eq!(p.user(&alice).balance, tickets(7));
+    let alice_size = p.svm.account(&pda::user_account(&alice).0).unwrap().data.len();
+    assert_eq!(alice_size, 8 + UserAccount::space(2));
+
+    // Withdrawing gives up the newest tickets first, across ranges, and topping up afterwards
+    // still works
+    let outcome = p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(3))).unwrap();
+    let forfeited: Vec<_> =
+        outcome.events::<TicketsForfeited>().iter().map(|e| (e.first_ticket, e.last_ticket)).collect();
+    assert_eq!(forfeited, vec![(8, 9), (5, 5)]);
+    p.deposit(&bob, tickets(1)).unwrap();
+    p.deposit(&alice, tickets(1)).unwrap();
+    assert_eq!(ranges(&p, &alice), vec![(0, 1), (3, 4), (11, 11)]);
+    assert_eq!(ranges(&p, &bob), vec![(2, 2), (6, 7), (10, 10)]);
+
+    // The winning ticket is in one of the winner's ranges
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    let winner_ranges = ranges(&p, &finalized.winner);
+    assert!(winner_ranges.iter().any(|(first, last)| (*first..=*last).contains(&finalized.winning_ticket)));
+    assert!([alice, bob].contains(&finalized.winner));
+}
+
+#[test]
+fn crank_follows_the_clock_through_epochs() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+
+    // Nothing to do before the first epoch ends
+    let outcome = p.crank(0).unwrap();
+    assert!(outcome.events::<EpochAdvanced>().is_empty());
+
+    p.svm.warp(EPOCH_SECONDS);
+    let advanced = p.crank(0).unwrap().event::<EpochAdvanced>();
+    assert_eq!((advanced.from_epoch, advanced.to_epoch), (1, 2));
+
+    // A late crank jumps straight to the epoch the clock is in
+    p.svm.warp(EPOCH_SECONDS);
+    let advanced = p.crank(0).unwrap().event::<EpochAdvanced>();
+    assert_eq!((advanced.from_epoch, advanced.to_epoch), (2, 3));
+    assert_eq!(p.round(0).epoch_in_round, 3);
+}
+
+#[test]
+fn keeper_advances_epochs_and_snapshots_each_once() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    let participants = p.participants(0);
+
+    p.svm.process(instructions::take_snapshot_batch(0, &participants)).unwrap();
+    let alice_account = p.user(&alice);
+    assert_eq!(alice_account.snapshots_recorded_mask, 0b001);
+    assert_eq!(alice_account.snapshot_balances[0], tickets(2));
+
+    // Depositing more after the snapshot does not rewrite it
+    p.deposit(&bob, tickets(2)).unwrap();
+    p.svm.process(instructions::take_snapshot_batch(0, &participants)).unwrap();
+    assert_eq!(p.user(&bob).snapshot_balances[0], tickets(1));
+
+    p.svm.process(instructions::advance_epoch(&p.admin, 0)).unwrap();
+    p.svm.process(instructions::take_snapshot_batch(0, &participants)).unwrap();
+    let bob_account = p.user(&bob);
+    assert_eq!(bob_account.snapshots_recorded_mask, 0b011);
+    assert_eq!(bob_account.snapshot_balances[1], tickets(3));
+    assert_eq!(p.user(&alice).snapshot_balances, [tickets(2), tickets(2), 0]);
+}
+
+#[test]
+fn crank_finalizes_and_opens_the_next_round() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(3)).unwrap();
+
+    let outcome = p.finish_round().unwrap();
+
+    let finalized = outcome.event::<RoundFinalized>();
+    assert_eq!(finalized.round_id, 0);
+    assert_eq!(finalized.total_tickets_sold, 5);
+    assert!([alice, bob].contains(&finalized.winner));
+    let winner = p.user(&finalized.winner);
+    assert!((winner.ticket_start..=winner.ticket_end).contains(&finalized.winning_ticket));
+
+    let round = p.round(0);
+    assert!(round.is_complete);
+    assert_eq!(round.winner, Some(finalized.winner));
+    assert_eq!(round.total_prize_lamports, finalized.prize_amount);
+
+    // The crank also opened round 1, paying its rent out of the seeded treasury
+    assert_eq!(outcome.event::<RoundStarted>().round_id, 1);
+    // Finalization kept exactly that rent back from the prize
+    assert_eq!(p.state().prize_seed_amount, 0);
+    assert_eq!(p.state().current_round, 1);
+    assert_eq!(p.round(1).start_epoch, p.svm.now_ms());
+}
+
+#[test]
+fn largest_crank_the_keeper_sends_fits_one_transaction() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let wallets: Vec<Pubkey> = (0..MAX_ROUND_PARTICIPANTS).map(|_| p.wallet(10)).collect();
+    for wallet in &wallets {
+        p.deposit(wallet, tickets(1)).unwrap();
+    }
+
+    // A few keys past the cap and the crank no longer fits in a packet
+    let mut participants = p.participants(0);
+    participants.extend((0..8).map(|_| Pubkey::new_unique()));
+    let err = p.svm.process(instructions::crank(0, &participants)).expect_err("oversized crank");
+    assert!(matches!(err, TransactionError::TooLarge(_)), "got {err:?}");
+
+    // The keeper's crank for a full round carries every participant and draws among them
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    assert_eq!(finalized.total_tickets_sold, u64::from(MAX_ROUND_PARTICIPANTS));
+    assert!(wallets.contains(&finalized.winner));
+    assert!(p.round(0).is_complete);
+}
+
+#[test]
+fn crank_leaves_the_next_round_when_the_treasury_cannot_pay_its_rent() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+
+    // Nothing seeded, so finalization still goes through but round 1 stays unopened
+    let outcome = p.finish_round().unwrap();
+    assert_eq!(outcome.event::<RoundFinalized>().winner, alice);
+    assert!(outcome.events::<RoundStarted>().is_empty());
+    assert!(p.round(0).is_complete);
+    assert_eq!(p.state().current_round, 0);
+    assert!(p.svm.account(&pda::round_state(1).0).is_none());
+
+    // The next depositor opens it and pays the rent instead
+    let bob = p.wallet(10);
+    let started = p.deposit(&bob, tickets(1)).unwrap().event::<RoundStarted>();
+    assert_eq!((started.round_id, started.opened_by), (1, bob));
+    assert_eq!(p.state().current_round, 1);
+}
+
+#[test]
+fn crank_draw_is_reproducible_from_public_inputs() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(3)).unwrap();
+    let ledger: Vec<_> = [alice, bob]
+        .iter()
+        .map(|owner| {
+            let user = p.user(owner);
+            (*owner, user.ticket_start, user.ticket_end)
+        })
+        .collect();
+
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    assert_eq!((finalized.draw_slot, finalized.draw_seed), (p.round(0).draw_slot, p.round(0).draw_seed));
+
+    // What rafa-verify does: the harness clock stays in epoch 0
+    let round = p.round(0);
+    let seed = rafa_draw::clock_seed(round.draw_slot, (round.end_epoch / 1000) as i64, 0);
+    assert_eq!(seed, round.draw_seed);
+    let ticket = rafa_draw::winning_ticket(seed, round.total_tickets_sold).unwrap();
+    assert_eq!(ticket, round.winning_ticket);
+    assert_eq!(rafa_draw::ticket_owner(ledger, ticket), round.winner);
+}
+
+#[test]
+fn deposit_rolls_over_into_the_next_round() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.svm.warp(3 * EPOCH_SECONDS);
+
+    // Bob's deposit finalizes round 0 (Alice is the only ticket holder) and lands in round 1
+    let outcome = p.deposit(&bob, tickets(2)).unwrap();
+    assert_eq!(outcome.event::<RoundFinalized>().winner, alice);
+    assert_eq!(outcome.event::<RoundStarted>().round_id, 1);
+    assert_eq!(outcome.event::<DepositMade>().round_id, 1);
+
+    let bob_account = p.user(&bob);
+    assert_eq!(bob_account.round_joined, 1);
+    assert_eq!((bob_account.ticket_start, bob_account.ticket_end), (0, 1));
+    assert_eq!(p.round(1).total_tickets_sold, 2);
+}
+
+#[test]
+fn select_winner_local_picks_the_owner_of_the_drawn_ticket() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(3)).unwrap();
+    let participants = p.participants(0);
+
+    // seed 7 over 5 tickets draws ticket #2, Bob's first
+    let outcome = p.svm.process(instructions::select_winner_local(&p.admin, 0, 7, &participants)).unwrap();
+    let finalized = outcome.event::<RoundFinalized>();
+    assert_eq!((finalized.winner, finalized.winning_ticket), (bob, 2));
+    assert_eq!(p.round(0).winner, Some(bob));
+}
+
+#[test]
+fn winner_claims_stake_and_prize() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(4)).unwrap();
+    p.finish_round().unwrap();
+    let prize = p.round(0).total_prize_lamports;
+
+    p.svm.process(instructions::create_claim_ticket(&p.admin, 0, &alice, prize, tickets(4))).unwrap();
+    let ticket: ClaimTicket = p.svm.get(&pda::claim_ticket(0, &alice).0).unwrap();
+    assert_eq!((ticket.prize_amount, ticket.stake_amount, ticket.claimed), (prize, tickets(4), false));
+    assert_eq!(p.state().total_unclaimed_prizes, prize);
+
+    let before = p.svm.lamports(&alice);
+    let claimed = p.svm.process(instructions::claim_prize(&alice, 0)).unwrap().event::<PrizeClaimed>();
+    assert_eq!((claimed.stake_amount, claimed.prize_amount), (tickets(4), prize));
+    assert_eq!(p.svm.lamports(&alice) - before, tickets(4) + prize);
+
+    assert!(p.svm.get::<ClaimTicket>(&pda::claim_ticket(0, &alice).0).unwrap().claimed);
+    assert!(p.round(0).prize_claimed);
+    assert_eq!(p.state().total_unclaimed_prizes, 0);
+    assert_eq!(p.user(&alice).balance, 0);
+}
+
+#[test]
+fn winner_can_create_their_own_claim_ticket() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    let winner = p.finish_round().unwrap().event::<RoundFinalized>().winner;
+
+    p.svm.process(instructions::create_claim_ticket_winner(&winner, 0)).unwrap();
+    let ticket: ClaimTicket = p.svm.get(&pda::claim_ticket(0, &winner).0).unwrap();
+    assert_eq!(ticket.stake_amount, tickets(1));
+    // The seeded prize, not the loser's stake: that stays withdrawable
+    assert_eq!(ticket.prize_amount, p.round(0).total_prize_lamports);
+    assert!(ticket.prize_amount > 0);
+}
+
+#[test]
+fn losers_withdraw_their_stake_after_the_round() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    let winner = p.finish_round().unwrap().event::<RoundFinalized>().winner;
+    let loser = if winner == alice { bob } else { alice };
+
+    let before = p.svm.lamports(&loser);
+    let processed = p.svm.process(instructions::process_withdrawal(&loser, 0)).unwrap().event::<WithdrawalProcessed>();
+    assert_eq!(processed.amount, tickets(1));
+    assert_eq!(p.svm.lamports(&loser) - before, tickets(1));
+    assert_eq!(p.user(&loser).balance, 0);
+}
+
+#[test]
+fn requested_withdrawals_move_balance_to_pending() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(3)).unwrap();
+
+    let requested = p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(1))).unwrap().event::<WithdrawalRequested>();
+    assert_eq!((requested.amount, requested.remaining_balance), (tickets(1), tickets(2)));
+
+    let user = p.user(&alice);
+    assert_eq!(user.balance, tickets(2));
+    assert_eq!(user.pending_withdrawal_amount, tickets(1));
+    assert_eq!(user.pending_withdrawal_round, 0);
+}
+
+#[test]
+fn withdrawing_from_an_open_round_forfeits_the_tickets() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(2)).unwrap();
+
+    let outcome = p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(2))).unwrap();
+    let forfeited = outcome.event::<TicketsForfeited>();
+    assert_eq!((forfeited.first_ticket, forfeited.last_ticket, forfeited.voided_tickets), (0, 1, 2));
+    let round = p.round(0);
+    assert_eq!((round.live_tickets(), round.total_staked_lamports), (2, tickets(2)));
+    assert!(!p.user(&alice).holds_tickets());
+
+    // Whatever the seed, the draw lands on Bob's tickets
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    assert_eq!(finalized.winner, bob);
+    assert!((2..4).contains(&p.round(0).winning_ticket));
+}
+
+#[test]
+fn merged_positions_keep_every_recorded_snapshot() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    let (alice_account, bob_account) = (pda::user_account(&alice).0, pda::user_account(&bob).0);
+
+    // The batches of the first two epochs each reach only one of them
+    p.svm.process(instructions::take_snapshot_batch(0, &[alice_account])).unwrap();
+    p.svm.process(instructions::advance_epoch(&p.admin, 0)).unwrap();
+    p.svm.process(instructions::take_snapshot_batch(0, &[bob_account])).unwrap();
+
+    p.svm.process(instructions::transfer_position(&alice, &bob, 0)).unwrap();
+    let user = p.user(&bob);
+    assert_eq!(user.snapshots_recorded_mask, 0b011);
+    assert_eq!(user.snapshot_balances, [tickets(2), tickets(3), 0]);
+
+    // The merged snapshot of the current epoch stands; the next one records the whole position
+    p.svm.process(instructions::take_snapshot_batch(0, &[bob_account])).unwrap();
+    p.svm.process(instructions::advance_epoch(&p.admin, 0)).unwrap();
+    p.svm.process(instructions::take_snapshot_batch(0, &[bob_account])).unwrap();
+    assert_eq!(p.user(&bob).snapshot_balances, [tickets(2), tickets(3), tickets(3)]);
+}
+
+#[test]
+fn positions_change_hands_without_leaving_the_round() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    let carol = Pubkey::new_unique();
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+
+    let outcome = p.svm.process(instructions::transfer_position(&alice, &carol, 0)).unwrap();
+    let moved = outcome.event::<PositionTransferred>();
+    assert_eq!((moved.from, moved.to, moved.amount), (alice, carol, tickets(2)));
+    let user = p.user(&carol);
+    assert_eq!((user.owner, user.balance, user.ticket_start, user.ticket_end), (carol, tickets(2), 0, 1));
+    assert_eq!(p.user(&alice).balance, 0);
+    assert_eq!(p.round(0).open_positions, 2);
+    assert_eq!(p.state().outstanding_stake, tickets(3));
+
+    // Carol now holds the first two tickets in the draw
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    let expected = if finalized.winning_ticket < 2 { carol } else { bob };
+    assert_eq!(finalized.winner, expected);
+}
+
+#[test]
+fn partial_exits_keep_the_rest_of_the_stake_in_play() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let bob = p.wallet(10);
+    let alice = p.wallet(10);
+    p.deposit(&bob, tickets(1)).unwrap();
+    p.deposit(&alice, tickets(3)).unwrap();
+
+    // One ticket goes, two stay in the draw
+    p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(1))).unwrap();
+    assert_eq!((p.user(&alice).ticket_start, p.user(&alice).ticket_end), (1, 2));
+    let participants = p.participants(0);
+    p.svm.process(instructions::select_winner_local(&p.admin, 0, 0, &participants)).unwrap();
+    assert_eq!(p.round(0).winner, Some(bob));
+
+    // Only the requested part is paid out
+    let before = p.svm.lamports(&alice);
+    let processed = p.svm.process(instructions::process_withdrawal(&alice, 0)).unwrap().event::<WithdrawalProcessed>();
+    assert_eq!(processed.amount, tickets(1));
+    assert_eq!(p.svm.lamports(&alice) - before, tickets(1));
+    let user = p.user(&alice);
+    assert_eq!((user.balance, user.pending_withdrawal_amount), (tickets(2), 0));
+    assert_eq!(p.round(0).open_positions, 2);
+
+    // The rest buys tickets in the next round without leaving the protocol
+    let carol = p.wallet(10);
+    p.deposit(&carol, tickets(1)).unwrap();
+    let rolled = p.svm.process(instructions::roll_over(&alice, 0, 1)).unwrap().event::<PositionRolledOver>();
+    assert_eq!((rolled.amount, rolled.ticket_start, rolled.ticket_end), (tickets(2), 1, 2));
+    let user = p.user(&alice);
+    assert_eq!((user.round_joined, user.ticket_start, user.ticket_end), (1, 1, 2));
+    assert_eq!(p.round(0).open_positions, 1);
+    let round = p.round(1);
+    assert_eq!((round.total_tickets_sold, round.total_staked_lamports, round.open_positions), (3, tickets(3), 2));
+    assert_eq!(p.state().outstanding_stake, tickets(1) + tickets(1) + tickets(2));
+}
+
+#[test]
+fn queued_withdrawals_are_paid_in_request_order_a_batch_at_a_time() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let users: Vec<Pubkey> = (0..MAX_WITHDRAWALS_PER_BATCH + 2).map(|_| p.wallet(10)).collect();
+    for user in &users {
+        p.deposit(user, tickets(2)).unwrap();
+    }
+    // Requests come in the reverse of the deposit order
+    let order: Vec<Pubkey> = users.iter().rev().copied().collect();
+    for user in &order {
+        p.svm.process(instructions::request_withdrawal(user, 0, tickets(1))).unwrap();
+    }
+    let queue_address = pda::withdrawal_queue(0).0;
+    let queue = |p: &Protocol| p.svm.get::<WithdrawalQueue>(&queue_address).unwrap();
+    assert_eq!(queue(&p).entries, order);
+    let winner = p.finish_round().unwrap().event::<RoundFinalized>().winner;
+    let paid_to = |outcome: rafa_svm::Outcome| -> Vec<Pubkey> {
+        let paid = outcome.events::<WithdrawalProcessed>();
+        assert!(paid.iter().all(|e| e.round_id == 0 && e.amount == tickets(1)));
+        paid.into_iter().map(|e| e.user).collect()
+    };
+
+    // Offered every entry, one call still stops after a batch; the winner is passed over
+    let first = paid_to(p.svm.process(instructions::process_withdrawals_batch(0, &order)).unwrap());
+    let (batch, rest) = order.split_at(MAX_WITHDRAWALS_PER_BATCH);
+    assert_eq!(first, batch.iter().copied().filter(|u| *u != winner).collect::<Vec<_>>());
+    assert_eq!(queue(&p).head as usize, MAX_WITHDRAWALS_PER_BATCH);
+
+    // The next call resumes at the head
+    let second = paid_to(p.svm.process(instructions::process_withdrawals_batch(0, rest)).unwrap());
+    assert_eq!(second, rest.iter().copied().filter(|u| *u != winner).collect::<Vec<_>>());
+    assert_eq!(queue(&p).head as usize, order.len());
+
+    // Only the requested part left; the rest of the stake still follows the usual path
+    let loser = *order.iter().find(|u| **u != winner).unwrap();
+    let user = p.user(&loser);
+    assert_eq!((user.balance, user.pending_withdrawal_amount), (tickets(1), 0));
+    assert_eq!(p.round(0).open_positions as usize, users.len());
+    assert_eq!(p.user(&winner).pending_withdrawal_amount, tickets(1));
+
+    // A drained queue closes into the seeded funds
+    let rent = p.svm.lamports(&queue_address);
+    let seed_before = p.state().prize_seed_amount;
+    let closed = p.svm.process(instructions::close_withdrawal_queue(0)).unwrap().event::<WithdrawalQueueClosed>();
+    assert_eq!(closed.rent, rent);
+    assert!(p.svm.account(&queue_address).is_none_or(|a| a.data.is_empty()));
+    assert_eq!(p.state().prize_seed_amount - seed_before, rent);
+}
+
+#[test]
+fn withdrawal_batches_wait_for_liquidity_without_skipping_anyone() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let users = [p.wallet(10), p.wallet(10), p.wallet(10)];
+    for user in &users {
+        p.deposit(user, tickets(1)).unwrap();
+    }
+    let winner = p.finish_round().unwrap().event::<RoundFinalized>().winner;
+    let losers: Vec<Pubkey> = users.into_iter().filter(|u| *u != winner).collect();
+    for user in &losers {
+        p.svm.process(instructions::request_withdrawal(user, 0, tickets(1))).unwrap();
+    }
+
+    // The protocol holds enough for the first request only, as if the rest were still staked
+    let protocol = pda::protocol_state().0;
+    let mut account = p.svm.account(&protocol).unwrap().clone();
+    let withheld = account.lamports - Rent::default().minimum_balance(8 + ProtocolState::SIZE) - tickets(1);
+    account.lamports -= withheld;
+    p.svm.set_account(protocol, account);
+
+    let outcome = p.svm.process(instructions::process_withdrawals_batch(0, &losers)).unwrap();
+    assert_eq!(outcome.event::<WithdrawalProcessed>().user, losers[0]);
+    assert_eq!(outcome.events::<WithdrawalProcessed>().len(), 1);
+    let queue_address = pda::withdrawal_queue(0).0;
+    assert_eq!(p.svm.get::<WithdrawalQueue>(&queue_address).unwrap().head, 1);
+    assert_eq!(p.round(0).open_positions, 2);
+
+    // Nothing moves until the liquidity is back, then the second request is next
+    let outcome = p.svm.process(instructions::process_withdrawals_batch(0, &losers[1..])).unwrap();
+    assert!(outcome.events::<WithdrawalProcessed>().is_empty());
+    p.svm.airdrop(&protocol, withheld);
+    let before = p.svm.lamports(&losers[1]);
+    let outcome = p.svm.process(instructions::process_withdrawals_batch(0, &losers[1..])).unwrap();
+    assert_eq!(outcome.event::<WithdrawalProcessed>().user, losers[1]);
+    assert_eq!(p.svm.lamports(&losers[1]) - before, tickets(1));
+    assert_eq!(p.round(0).open_positions, 1);
+}
+
+#[test]
+fn instant_withdrawal_pays_from_the_buffer_and_voids_the_tickets() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(10)).unwrap();
+    p.deposit(&bob, tickets(10)).unwrap();
+    assert_eq!(p.state().liquid_buffer, tickets(20) * DEFAULT_LIQUID_BUFFER_BPS as u64 / 10_000);
+
+    let seed_before = p.state().prize_seed_amount;
+    let wallet_before = p.svm.lamports(&alice);
+    let outcome = p.svm.process(instructions::instant_withdraw(&alice, 0, tickets(1))).unwrap();
+    let fee = tickets(1) * DEFAULT_EXIT_FEE_BPS as u64 / 10_000;
+    let paid = outcome.event::<InstantWithdrawal>();
+    assert_eq!((paid.amount, paid.exit_fee, paid.remaining_balance), (tickets(1), fee, tickets(9)));
+    let forfeited = outcome.event::<TicketsForfeited>();
+    assert_eq!((forfeited.first_ticket, forfeited.last_ticket), (9, 9));
+    assert_eq!(p.svm.lamports(&alice) - wallet_before, tickets(1) - fee);
+    assert_eq!(p.state().prize_seed_amount - seed_before, fee);
+
+    // The withdrawn ticket leaves the draw but its number is never sold again
+    let round = p.round(0);
+    assert_eq!((round.total_tickets_sold, round.voided_tickets, round.live_tickets()), (20, 1, 19));
+    assert_eq!((p.user(&alice).ticket_start, p.user(&alice).ticket_end), (0, 8));
+    assert_eq!(round.total_staked_lamports, tickets(19));
+
+    let finalized = p.finish_round().unwrap().event::<RoundFinalized>();
+    let round = p.round(0);
+    assert_ne!(round.winning_ticket, 9);
+    let ledger = [(alice, 0, 8), (bob, 10, 19)];
+    let drawn = rafa_draw::draw(round.draw_seed, round.total_tickets_sold, round.live_tickets(), &ledger);
+    assert_eq!(drawn, Some((round.winning_ticket, finalized.winner)));
+}
+
+#[test]
+fn guardian_can_cancel_a_round() {
+    let mut p = Protocol::new();
+    let guardian = p.wallet(1);
+    p.svm.process(instructions::set_role(&p.admin, ProtocolRole::Guardian, guardian)).unwrap();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+
+    p.svm.process(instructions::cancel_round(&guardian, 0)).unwrap();
+    assert!(p.round(0).is_cancelled);
+    assert_eq!(p.state().cancelled_rounds, 1);
+}
+
+#[test]
+fn cancelled_rounds_refund_every_participant() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(1))).unwrap();
+
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+    let round = p.round(0);
+    assert!(round.is_cancelled && round.is_complete);
+    assert_eq!(p.state().cancelled_rounds, 1);
+
+    for (user, amount) in [(alice, tickets(2)), (bob, tickets(1))] {
+        let before = p.svm.lamports(&user);
+        let refund = p.svm.process(instructions::refund_cancelled(&user, 0)).unwrap().event::<RefundProcessed>();
+        assert_eq!(refund.amount, amount);
+        assert_eq!(p.svm.lamports(&user) - before, amount);
+    }
+
+    // The next crank opens round 1
+    p.crank(0).unwrap();
+    assert_eq!(p.state().current_round, 1);
+}
+
+#[test]
+fn admin_handover_takes_two_steps() {
+    let mut p = Protocol::new();
+    let next_admin = p.wallet(1);
+
+    let proposed = p.svm.process(instructions::propose_admin(&p.admin, next_admin)).unwrap().event::<AdminProposed>();
+    assert_eq!((proposed.admin, proposed.pending_admin), (p.admin, next_admin));
+    assert_eq!(p.state().admin, p.admin);
+    let changed = p.svm.process(instructions::accept_admin(&next_admin)).unwrap().event::<AdminChanged>();
+    assert_eq!((changed.previous, changed.admin), (p.admin, next_admin));
+
+    let state = p.state();
+    assert_eq!(state.admin, next_admin);
+    assert_eq!(state.pending_admin, None);
+}
+
+#[test]
+fn roles_status_and_config_changes_are_announced() {
+    let mut p = Protocol::new();
+    let keeper = p.wallet(1);
+
+    let ix = instructions::set_role(&p.admin, ProtocolRole::Keeper, keeper);
+    let role = p.svm.process(ix).unwrap().event::<RoleChanged>();
+    assert_eq!((role.role, role.previous, role.key), (ProtocolRole::Keeper, p.admin, keeper));
+
+    let ix = instructions::set_protocol_status(&p.admin, ProtocolStatus::DepositsPaused);
+    let status = p.svm.process(ix).unwrap().event::<ProtocolStatusChanged>();
+    assert_eq!((status.previous, status.status), (ProtocolStatus::Active, ProtocolStatus::DepositsPaused));
+
+    p.svm.process(instructions::init_change_queue(&p.admin)).unwrap().event::<ChangeQueueCreated>();
+    let ix = instructions::propose_change(&p.admin, ConfigChange::ExitFeeBps(10));
+    let proposed = p.svm.process(ix).unwrap().event::<ChangeProposed>();
+    assert_eq!((proposed.id, proposed.change), (0, ConfigChange::ExitFeeBps(10)));
+    assert_eq!(proposed.execute_after, p.svm.clock().unix_timestamp + DEFAULT_TIMELOCK_SECONDS);
+    p.svm.process(instructions::propose_change(&p.admin, ConfigChange::ExitFeeBps(20))).unwrap();
+
+    let cancelled = p.svm.process(instructions::cancel_change(&p.admin, 1)).unwrap().event::<ChangeCancelled>();
+    assert_eq!((cancelled.id, cancelled.cancelled_by), (1, p.admin));
+    p.svm.warp(DEFAULT_TIMELOCK_SECONDS);
+    let executed = p.svm.process(instructions::execute_change(&p.admin, 0)).unwrap().event::<ChangeExecuted>();
+    assert_eq!((executed.id, executed.change), (0, ConfigChange::ExitFeeBps(10)));
+}
+
+#[test]
+fn multisig_steps_are_announced() {
+    let mut p = Protocol::new();
+    let (a, b) = (p.wallet(1), p.wallet(1));
+
+    let created = p.svm.process(instructions::create_multisig(&p.admin, vec![a, b], 2)).unwrap();
+    let owners = created.event::<MultisigOwnersChanged>();
+    assert_eq!((owners.owners, owners.threshold, owners.owner_set_seqno), (vec![a, b], 2, 0));
+
+    let rotate = instructions::set_multisig_owners(vec![a], 1);
+    let ix = instructions::propose_multisig_tx(&a, 0, &rotate);
+    let proposed = p.svm.process(ix).unwrap().event::<MultisigTxProposed>();
+    assert_eq!((proposed.proposal_id, proposed.proposer, proposed.data), (0, a, rotate.data.clone()));
+    let approved = p.svm.process(instructions::approve_multisig_tx(&b, 0)).unwrap().event::<MultisigTxApproved>();
+    assert_eq!((approved.owner, approved.approvals), (b, 2));
+
+    // The proposal's own event comes out of the inner instruction
+    let executed = p.svm.process(instructions::execute_multisig_tx(0, &rotate)).unwrap();
+    assert_eq!(executed.event::<MultisigTxExecuted>().proposal_id, 0);
+    let owners = executed.event::<MultisigOwnersChanged>();
+    assert_eq!((owners.owners, owners.threshold, owners.owner_set_seqno), (vec![a], 1, 1));
+}
+
+#[test]
+fn settled_accounts_close_and_return_their_rent() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(1)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    assert_eq!(p.round(0).open_positions, 2);
+    let winner = p.finish_round().unwrap().event::<RoundFinalized>().winner;
+    let loser = if winner == alice { bob } else { alice };
+
+    p.svm.process(instructions::process_withdrawal(&loser, 0)).unwrap();
+    p.svm.process(instructions::create_claim_ticket_winner(&winner, 0)).unwrap();
+    p.svm.process(instructions::claim_prize(&winner, 0)).unwrap();
+    assert_eq!(p.round(0).open_positions, 0);
+
+    // The treasury paid round 0's rent, so it returns to the seeded funds
+    let protocol = pda::protocol_state().0;
+    assert_eq!(p.round(0).rent_payer, protocol);
+    let round_rent = p.svm.lamports(&pda::round_state(0).0);
+    let seed_before = p.state().prize_seed_amount;
+    let closed = p.svm.process(instructions::close_round(0, &protocol)).unwrap().event::<RoundClosed>();
+    assert_eq!((closed.rent_receiver, closed.rent), (protocol, round_rent));
+    assert!(p.svm.account(&pda::round_state(0).0).is_none_or(|a| a.data.is_empty()));
+    assert_eq!(p.state().prize_seed_amount - seed_before, round_rent);
+
+    // The winner paid for their own claim ticket
+    let ticket = pda::claim_ticket(0, &winner).0;
+    let ticket_rent = p.svm.lamports(&ticket);
+    let before = p.svm.lamports(&winner);
+    let ix = instructions::close_claim_ticket(0, &winner, &winner);
+    let closed = p.svm.process(ix).unwrap().event::<ClaimTicketClosed>();
+    assert_eq!((closed.winner, closed.rent_receiver, closed.rent), (winner, winner, ticket_rent));
+    assert_eq!(p.svm.lamports(&winner) - before, ticket_rent);
+
+    for user in [alice, bob] {
+        let account_rent = p.svm.lamports(&pda::user_account(&user).0);
+        let before = p.svm.lamports(&user);
+        let closed = p.svm.process(instructions::close_user_account(&user)).unwrap().event::<UserAccountClosed>();
+        assert_eq!((closed.owner, closed.rent), (user, account_rent));
+        assert_eq!(p.svm.lamports(&user) - before, account_rent);
+    }
+    // A closed user account comes back on the next deposit
+    p.deposit(&alice, tickets(1)).unwrap();
+    assert_eq!(p.user(&alice).balance, tickets(1));
+}
+
+#[test]
+fn wind_down_pays_everyone_before_the_protocol_closes() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+    assert_eq!(p.state().outstanding_stake, tickets(3));
+
+    let begun = p.svm.process(instructions::begin_wind_down(&p.admin)).unwrap().event::<WindDownBegun>();
+    assert_eq!(begun.status, ProtocolStatus::WithdrawOnly);
+    assert_eq!(begun.closable_at, p.state().wind_down_started_at + rafa::WIND_DOWN_GRACE_SECONDS);
+    assert_eq!(p.state().status, ProtocolStatus::WithdrawOnly);
+    assert!(p.deposit(&alice, tickets(1)).is_err());
+
+    // The running round is cancelled and everyone takes their stake back
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+    for user in [alice, bob] {
+        p.svm.process(instructions::refund_cancelled(&user, 0)).unwrap();
+    }
+    assert_eq!(p.state().outstanding_stake, 0);
+
+    p.svm.warp(rafa::WIND_DOWN_GRACE_SECONDS);
+    let protocol = pda::protocol_state().0;
+    let left = p.svm.lamports(&protocol);
+    let before = p.svm.lamports(&p.admin);
+    let closed = p.svm.process(instructions::close_protocol_state(&p.admin)).unwrap().event::<ProtocolClosed>();
+    assert_eq!(closed.returned, left);
+    assert_eq!(p.svm.lamports(&p.admin) - before, left);
+    assert_eq!(p.svm.lamports(&protocol), 0);
+}
@@ -0,0 +1,420 @@
+//! Accounts written before a layout change no longer deserialize; the migrate_* instructions
+//! grow them in place, keep every existing field, fill the ones added since with their defaults
+//! and leave them usable again.
+
+use anchor_lang::prelude::{borsh, AccountDeserialize, AccountSerialize, AnchorSerialize, Pubkey, Rent};
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_lang::Discriminator;
+use rafa::{
+    AccountMigrated, ClaimTicket, MultisigProposal, ProtocolState, ProtocolStatus, RoundState, UserAccount,
+    ACCOUNT_VERSION, DEFAULT_EXIT_FEE_BPS, DEFAULT_LIQUID_BUFFER_BPS, DEFAULT_TIMELOCK_SECONDS,
+};
+use rafa_client::{instructions, pda};
+use rafa_svm::fixture::{tickets, Protocol};
+use rafa_svm::Svm;
+
+/// `version` and `reserved`, the bytes every account gained with versioning
+const VERSIONING_BYTES: usize = 1 + 64;
+
+// The layouts the baseline program wrote, before accounts carried a version
+
+#[derive(AnchorSerialize)]
+struct BaselineProtocolState {
+    admin: Pubkey,
+    validator: Pubkey,
+    current_round: u64,
+    prize_seed_amount: u64,
+    total_unclaimed_prizes: u64,
+    bump: u8,
+}
+
+#[derive(AnchorSerialize)]
+struct BaselineRoundState {
+    round_id: u64,
+    epoch_in_round: u8,
+    start_epoch: u64,
+    end_epoch: u64,
+    stake_account: Pubkey,
+    total_staked_lamports: u64,
+    total_prize_lamports: u64,
+    total_tickets_sold: u64,
+    winner: Option<Pubkey>,
+    winning_ticket: u64,
+    is_complete: bool,
+    prize_claimed: bool,
+    vrf_request: Option<Pubkey>,
+    bump: u8,
+}
+
+#[derive(AnchorSerialize)]
+struct BaselineUserAccount {
+    owner: Pubkey,
+    balance: u64,
+    ticket_start: u64,
+    ticket_end: u64,
+    snapshot_balances: [u64; 3],
+    snapshots_recorded_mask: u8,
+    round_joined: u64,
+    pending_withdrawal_amount: u64,
+    pending_withdrawal_round: u64,
+    bump: u8,
+}
+
+#[derive(AnchorSerialize)]
+struct BaselineClaimTicket {
+    round_id: u64,
+    winner: Pubkey,
+    prize_amount: u64,
+    stake_amount: u64,
+    claimed: bool,
+    bump: u8,
+}
+
+/// Replace the account at `address` with `T`'s discriminator and `body`, zero-padded to the
+/// baseline's `size` and funded as that program would have left it: its rent plus whatever else
+/// it held. Returns the new length.
+fn write_baseline<T: Discriminator>(
+    svm: &mut Svm,
+    address: &Pubkey,
+    body: &impl AnchorSerialize,
+    size: usize,
+) -> usize {
+    let rent = Rent::default();
+    let mut account = svm.account(address).unwrap().clone();
+    let held = account.lamports - rent.minimum_balance(account.data.len());
+    let mut data = T::DISCRIMINATOR.to_vec();
+    body.serialize(&mut data).unwrap();
+    data.resize(8 + size, 0);
+    account.lamports = rent.minimum_balance(data.len()) + held;
+    account.data = data;
+    let len = account.data.len();
+    svm.set_account(*address, account);
+    len
+}
+
+/// Rewrite the `T` at `address` without its last `dropped` bytes, the way a layout that had
+/// not yet appended them left it. Returns the new length.
+fn truncate<T>(svm: &mut Svm, address: &Pubkey, value: &T, dropped: usize) -> usize
+where
+    T: AccountSerialize + AccountDeserialize,
+{
+    let rent = Rent::default();
+    let mut account = svm.account(address).unwrap().clone();
+    let held = account.lamports - rent.minimum_balance(account.data.len());
+    let mut data = Vec::new();
+    value.try_serialize(&mut data).unwrap();
+    data.truncate(data.len() - dropped);
+    account.lamports = rent.minimum_balance(data.len()) + held;
+    account.data = data;
+    let len = account.data.len();
+    svm.set_account(*address, account);
+    len
+}
+
+fn extra_rent(old_len: usize, new_len: usize) -> u64 {
+    let rent = Rent::default();
+    rent.minimum_balance(new_len) - rent.minimum_balance(old_len)
+}
+
+#[test]
+fn baseline_protocol_state_gets_the_defaults_initialize_sets() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(4)).unwrap();
+    let before = p.state();
+    let address = pda::protocol_state().0;
+    let baseline = BaselineProtocolState {
+        admin: before.admin,
+        validator: before.validator,
+        current_round: before.current_round,
+        prize_seed_amount: before.prize_seed_amount,
+        total_unclaimed_prizes: before.total_unclaimed_prizes,
+        bump: before.bump,
+    };
+    let old_len = write_baseline::<ProtocolState>(&mut p.svm, &address, &baseline, 89);
+    assert!(p.svm.get::<ProtocolState>(&address).is_none());
+    let held_before = p.svm.lamports(&address);
+
+    let payer = p.wallet(1);
+    let migrated = p.svm.process(instructions::migrate_protocol_state(&payer)).unwrap().event::<AccountMigrated>();
+    assert_eq!((migrated.account, migrated.from_version, migrated.to_version), (address, 0, ACCOUNT_VERSION));
+
+    // Only the new bytes' rent came in; stakes and seed are exactly what they were
+    assert_eq!(p.svm.lamports(&address) - held_before, extra_rent(old_len, 8 + ProtocolState::SIZE));
+    let after = p.state();
+    assert_eq!(after.version, ACCOUNT_VERSION);
+    assert_eq!((after.admin, after.validator, after.bump), (before.admin, before.validator, before.bump));
+    assert_eq!((after.current_round, after.prize_seed_amount), (before.current_round, before.prize_seed_amount));
+    assert_eq!((after.keeper, after.guardian, after.treasurer), (before.admin, before.admin, before.admin));
+    assert_eq!(after.pending_admin, None);
+    assert_eq!(after.status, ProtocolStatus::Active);
+    assert_eq!(after.timelock_delay_seconds, DEFAULT_TIMELOCK_SECONDS);
+    assert_eq!((after.liquid_buffer_bps, after.exit_fee_bps), (DEFAULT_LIQUID_BUFFER_BPS, DEFAULT_EXIT_FEE_BPS));
+    assert_eq!(after.min_tickets_per_deposit, 1);
+    // Stakes taken before tracking began are not counted
+    assert!(!after.liabilities_tracked);
+
+    let bob = p.wallet(10);
+    p.deposit(&bob, tickets(1)).unwrap();
+    p.finish_round().unwrap();
+}
+
+#[test]
+fn protocol_state_from_before_the_buffer_gets_its_defaults() {
+    let mut p = Protocol::new();
+    let address = pda::protocol_state().0;
+    // v3 had zeros where the buffer, fee and ticket minimum now sit
+    let mut v3 = p.state();
+    v3.version = 3;
+    (v3.liquid_buffer_bps, v3.exit_fee_bps, v3.min_tickets_per_deposit) = (0, 0, 0);
+    p.svm.write(&address, &v3);
+
+    let payer = p.wallet(1);
+    p.svm.process(instructions::migrate_protocol_state(&payer)).unwrap();
+    let after = p.state();
+    assert_eq!(after.version, ACCOUNT_VERSION);
+    assert_eq!((after.liquid_buffer_bps, after.exit_fee_bps), (DEFAULT_LIQUID_BUFFER_BPS, DEFAULT_EXIT_FEE_BPS));
+    assert_eq!(after.min_tickets_per_deposit, 1);
+}
+
+#[test]
+fn baseline_round_keeps_its_bump_and_finalizes() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(3)).unwrap();
+    let before = p.round(0);
+    let address = pda::round_state(0).0;
+    let baseline = BaselineRoundState {
+        round_id: before.round_id,
+        epoch_in_round: before.epoch_in_round,
+        start_epoch: before.start_epoch,
+        end_epoch: before.end_epoch,
+        stake_account: before.stake_account,
+        total_staked_lamports: before.total_staked_lamports,
+        total_prize_lamports: before.total_prize_lamports,
+        total_tickets_sold: before.total_tickets_sold,
+        winner: None,
+        winning_ticket: 0,
+        is_complete: false,
+        prize_claimed: false,
+        vrf_request: None,
+        bump: before.bump,
+    };
+    write_baseline::<RoundState>(&mut p.svm, &address, &baseline, 158);
+    assert!(p.svm.get::<RoundState>(&address).is_none());
+
+    let payer = p.wallet(1);
+    p.svm.process(instructions::migrate_round_state(&payer, 0)).unwrap();
+    let round = p.round(0);
+    assert_eq!(round.version, ACCOUNT_VERSION);
+    assert_eq!((round.bump, round.is_cancelled), (before.bump, false));
+    assert_eq!((round.total_tickets_sold, round.draw_slot, round.draw_seed), (3, 0, 0));
+    assert_eq!(round.rent_payer, Pubkey::default());
+
+    p.finish_round().unwrap();
+    let round = p.round(0);
+    assert_eq!(round.winner, Some(alice));
+    assert_ne!(round.draw_slot, 0);
+}
+
+#[test]
+fn baseline_user_account_migrates_and_keeps_its_position() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    let before = p.user(&alice);
+    let address = pda::user_account(&alice).0;
+    let baseline = BaselineUserAccount {
+        owner: before.owner,
+        balance: before.balance,
+        ticket_start: before.ticket_start,
+        ticket_end: before.ticket_end,
+        snapshot_balances: before.snapshot_balances,
+        snapshots_recorded_mask: before.snapshots_recorded_mask,
+        round_joined: before.round_joined,
+        pending_withdrawal_amount: before.pending_withdrawal_amount,
+        pending_withdrawal_round: before.pending_withdrawal_round,
+        bump: before.bump,
+    };
+    let old_len = write_baseline::<UserAccount>(&mut p.svm, &address, &baseline, 106);
+
+    // Unreadable until migrated
+    assert!(p.svm.get::<UserAccount>(&address).is_none());
+    assert!(p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(1))).is_err());
+
+    // Anyone can pay for the migration
+    let payer = p.wallet(1);
+    let payer_before = p.svm.lamports(&payer);
+    p.svm.process(instructions::migrate_user_account(&payer, &alice)).unwrap();
+    assert_eq!(payer_before - p.svm.lamports(&payer), extra_rent(old_len, 8 + UserAccount::SIZE));
+    assert_eq!(p.svm.account(&address).unwrap().data.len(), 8 + UserAccount::SIZE);
+
+    let after = p.user(&alice);
+    assert_eq!(after.version, ACCOUNT_VERSION);
+    assert_eq!((after.owner, after.balance, after.round_joined), (before.owner, before.balance, before.round_joined));
+    assert_eq!((after.ticket_start, after.ticket_end), (before.ticket_start, before.ticket_end));
+    assert_eq!(after.bump, before.bump);
+    assert!(after.extra_ranges.is_empty());
+    p.svm.process(instructions::request_withdrawal(&alice, 0, tickets(1))).unwrap();
+}
+
+#[test]
+fn user_account_from_before_extra_ranges_migrates() {
+    let mut p = Protocol::new();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    let address = pda::user_account(&alice).0;
+    let mut v4 = p.user(&alice);
+    v4.version = 4;
+    // extra_ranges' length is the only thing v5 appended
+    let old_len = truncate(&mut p.svm, &address, &v4, 4);
+    assert!(p.svm.get::<UserAccount>(&address).is_none());
+
+    let payer = p.wallet(1);
+    p.svm.process(instructions::migrate_user_account(&payer, &alice)).unwrap();
+    assert_eq!(old_len + 4, p.svm.account(&address).unwrap().data.len());
+    let after = p.user(&alice);
+    assert_eq!((after.version, after.balance), (ACCOUNT_VERSION, tickets(2)));
+
+    // A top-up after someone else bought can now open another range
+    let bob = p.wallet(10);
+    p.deposit(&bob, tickets(1)).unwrap();
+    p.deposit(&alice, tickets(1)).unwrap();
+    assert_eq!(p.user(&alice).tickets_held(), 3);
+}
+
+#[test]
+fn baseline_claim_ticket_migrates_and_pays_out() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    p.deposit(&alice, tickets(4)).unwrap();
+    p.finish_round().unwrap();
+    let prize = p.round(0).total_prize_lamports;
+    p.svm.process(instructions::create_claim_ticket(&p.admin, 0, &alice, prize, tickets(4))).unwrap();
+    let address = pda::claim_ticket(0, &alice).0;
+    let before: ClaimTicket = p.svm.get(&address).unwrap();
+    let baseline = BaselineClaimTicket {
+        round_id: before.round_id,
+        winner: before.winner,
+        prize_amount: before.prize_amount,
+        stake_amount: before.stake_amount,
+        claimed: before.claimed,
+        bump: before.bump,
+    };
+    write_baseline::<ClaimTicket>(&mut p.svm, &address, &baseline, 58);
+    assert!(p.svm.get::<ClaimTicket>(&address).is_none());
+
+    let payer = p.wallet(1);
+    p.svm.process(instructions::migrate_claim_ticket(&payer, 0, &alice)).unwrap();
+    let ticket: ClaimTicket = p.svm.get(&address).unwrap();
+    assert_eq!(ticket.version, ACCOUNT_VERSION);
+    assert_eq!((ticket.prize_amount, ticket.stake_amount, ticket.bump), (prize, tickets(4), before.bump));
+    // Predates rent tracking: the rent goes back to the protocol on close
+    assert_eq!(ticket.rent_payer, Pubkey::default());
+
+    let held = p.svm.lamports(&alice);
+    p.svm.process(instructions::claim_prize(&alice, 0)).unwrap();
+    assert_eq!(p.svm.lamports(&alice) - held, tickets(4) + prize);
+}
+
+#[test]
+fn multisig_proposal_grows_by_its_own_size() {
+    let mut p = Protocol::new();
+    let (a, b) = (p.wallet(1), p.wallet(1));
+    p.svm.process(instructions::create_multisig(&p.admin, vec![a, b], 2)).unwrap();
+    let proposed = instructions::set_multisig_owners(vec![a], 1);
+    p.svm.process(instructions::propose_multisig_tx(&a, 0, &proposed)).unwrap();
+    let address = pda::multisig_proposal(0).0;
+    let len = p.svm.account(&address).unwrap().data.len();
+    let proposal: MultisigProposal = p.svm.get(&address).unwrap();
+    truncate(&mut p.svm, &address, &proposal, VERSIONING_BYTES);
+
+    p.svm.process(instructions::migrate_multisig_proposal(&a, 0)).unwrap();
+    assert_eq!(p.svm.account(&address).unwrap().data.len(), len);
+    p.svm.process(instructions::approve_multisig_tx(&b, 0)).unwrap();
+    p.svm.process(instructions::execute_multisig_tx(0, &proposed)).unwrap();
+}
+
+#[test]
+fn baseline_protocol_reconciles_and_winds_down_to_a_close() {
+    let mut p = Protocol::new();
+    p.seed(LAMPORTS_PER_SOL).unwrap();
+    let alice = p.wallet(10);
+    let bob = p.wallet(10);
+    p.deposit(&alice, tickets(2)).unwrap();
+    p.deposit(&bob, tickets(1)).unwrap();
+
+    // The whole protocol as the baseline program left it, brought up to date
+    let state = p.state();
+    let baseline = BaselineProtocolState {
+        admin: state.admin,
+        validator: state.validator,
+        current_round: state.current_round,
+        prize_seed_amount: state.prize_seed_amount,
+        total_unclaimed_prizes: state.total_unclaimed_prizes,
+        bump: state.bump,
+    };
+    write_baseline::<ProtocolState>(&mut p.svm, &pda::protocol_state().0, &baseline, 89);
+    let round = p.round(0);
+    let baseline = BaselineRoundState {
+        round_id: 0,
+        epoch_in_round: round.epoch_in_round,
+        start_epoch: round.start_epoch,
+        end_epoch: round.end_epoch,
+        stake_account: round.stake_account,
+        total_staked_lamports: round.total_staked_lamports,
+        total_prize_lamports: round.total_prize_lamports,
+        total_tickets_sold: round.total_tickets_sold,
+        winner: None,
+        winning_ticket: 0,
+        is_complete: false,
+        prize_claimed: false,
+        vrf_request: None,
+        bump: round.bump,
+    };
+    write_baseline::<RoundState>(&mut p.svm, &pda::round_state(0).0, &baseline, 158);
+    for owner in [alice, bob] {
+        let user = p.user(&owner);
+        let baseline = BaselineUserAccount {
+            owner,
+            balance: user.balance,
+            ticket_start: user.ticket_start,
+            ticket_end: user.ticket_end,
+            snapshot_balances: user.snapshot_balances,
+            snapshots_recorded_mask: user.snapshots_recorded_mask,
+            round_joined: user.round_joined,
+            pending_withdrawal_amount: user.pending_withdrawal_amount,
+            pending_withdrawal_round: user.pending_withdrawal_round,
+            bump: user.bump,
+        };
+        write_baseline::<UserAccount>(&mut p.svm, &pda::user_account(&owner).0, &baseline, 106);
+    }
+    let payer = p.wallet(1);
+    p.svm.process(instructions::migrate_protocol_state(&payer)).unwrap();
+    p.svm.process(instructions::migrate_round_state(&payer, 0)).unwrap();
+    for owner in [alice, bob] {
+        p.svm.process(instructions::migrate_user_account(&payer, &owner)).unwrap();
+    }
+    assert!(!p.state().liabilities_tracked && !p.round(0).positions_tracked);
+
+    // Counted from the positions themselves, the running round's checked against its tickets
+    let positions = [pda::user_account(&alice).0, pda::user_account(&bob).0];
+    p.svm.process(instructions::reconcile_round(&p.admin, 0, &positions)).unwrap();
+    p.svm.process(instructions::reconcile_liabilities(&p.admin, &positions)).unwrap();
+    assert_eq!((p.round(0).open_positions, p.round(0).positions_tracked), (2, true));
+    assert_eq!((p.state().outstanding_stake, p.state().liabilities_tracked), (tickets(3), true));
+
+    p.svm.process(instructions::begin_wind_down(&p.admin)).unwrap();
+    p.svm.process(instructions::cancel_round(&p.admin, 0)).unwrap();
+    for owner in [alice, bob] {
+        p.svm.process(instructions::refund_cancelled(&owner, 0)).unwrap();
+    }
+    assert_eq!((p.state().outstanding_stake, p.round(0).open_positions), (0, 0));
+
+    p.svm.warp(rafa::WIND_DOWN_GRACE_SECONDS);
+    p.svm.process(instructions::close_protocol_state(&p.admin)).unwrap();
+    assert_eq!(p.svm.lamports(&pda::protocol_state().0), 0);
+}
@@ -0,0 +1,118 @@
+//! Independent check of a round's draw: fetches the round, recomputes the seed from the clock
+//! at `draw_slot`, the winning ticket from the seed and its holder from the ticket ledger, all
+//! with the same `rafa_draw` functions the program runs, and prints a JSON proof. Exits with
+//! status 1 when any check fails.
+//!
+//! The ledger comes from an indexer database (complete history) or, without one, from the
+//! live UserAccounts, which only hold the round's ranges until participants are paid out.
+
+mod proof;
+
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::epoch_schedule::EpochSchedule;
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use rafa_client::RpcClient;
+use rafa_indexer::store::Store;
+use serde_json::{json, Value};
+
+use crate::proof::LedgerEntry;
+
+#[derive(Parser)]
+#[command(name = "rafa-verify", about = "Recompute and check a rafa round's winner")]
+struct Cli {
+    round_id: u64,
+    #[arg(long, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+    /// rafa-indexer database to read the ticket ledger from
+    #[arg(long)]
+    indexer_db: Option<String>,
+}
+
+fn epoch_schedule(rpc: &RpcClient) -> Result<EpochSchedule> {
+    let result = rpc.call("getEpochSchedule", json!([]))?;
+    let field = |name: &str| result[name].as_u64().with_context(|| format!("getEpochSchedule: missing {name}"));
+    Ok(EpochSchedule {
+        slots_per_epoch: field("slotsPerEpoch")?,
+        leader_schedule_slot_offset: field("leaderScheduleSlotOffset")?,
+        warmup: result["warmup"].as_bool().context("getEpochSchedule: missing warmup")?,
+        first_normal_epoch: field("firstNormalEpoch")?,
+        first_normal_slot: field("firstNormalSlot")?,
+    })
+}
+
+fn parse_user(user: &str) -> Result<Pubkey> {
+    Pubkey::from_str(user).map_err(|e| anyhow!("bad user {user} in indexer: {e}"))
+}
+
+/// Deposit ranges minus the forfeited tickets, each held by whoever the position was last
+/// transferred to
+fn indexer_ledger(path: &str, round_id: u64) -> Result<Vec<LedgerEntry>> {
+    let store = Store::open(path).with_context(|| format!("open indexer database {path}"))?;
+    let mut ledger = store
+        .round_deposits(round_id)?
+        .into_iter()
+        .map(|d| Ok(LedgerEntry { owner: parse_user(&d.user)?, first: d.ticket_start, last: d.ticket_end }))
+        .collect::<Result<Vec<_>>>()?;
+    // Ticket numbers are never sold twice, so a forfeited range voids whichever entry covers it
+    for forfeit in store.round_forfeits(round_id)? {
+        ledger = ledger
+            .into_iter()
+            .flat_map(|e| {
+                if e.last < forfeit.first_ticket || e.first > forfeit.last_ticket {
+                    return vec![e];
+                }
+                let mut kept = Vec::new();
+                if e.first < forfeit.first_ticket {
+                    kept.push(LedgerEntry { owner: e.owner, first: e.first, last: forfeit.first_ticket - 1 });
+                }
+                if e.last > forfeit.last_ticket {
+                    kept.push(LedgerEntry { owner: e.owner, first: forfeit.last_ticket + 1, last: e.last });
+                }
+                kept
+            })
+            .collect();
+    }
+    for transfer in store.round_transfers(round_id)? {
+        let to = parse_user(&transfer.to_user)?;
+        for e in ledger.iter_mut().filter(|e| e.first >= transfer.ticket_start && e.last <= transfer.ticket_end) {
+            e.owner = to;
+        }
+    }
+    Ok(ledger)
+}
+
+fn account_ledger(rpc: &RpcClient, round_id: u64) -> Result<Vec<LedgerEntry>> {
+    Ok(rpc
+        .participants(round_id)?
+        .into_iter()
+        .flat_map(|(_, user)| {
+            user.ranges().map(|(first, last)| LedgerEntry { owner: user.owner, first, last }).collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+fn run(cli: Cli) -> Result<(Value, bool)> {
+    let rpc = RpcClient::new(cli.rpc_url);
+    let round = rpc.round(cli.round_id)?.with_context(|| format!("round {} does not exist", cli.round_id))?;
+    let draw_epoch = epoch_schedule(&rpc)?.get_epoch(round.draw_slot);
+
+    let (source, ledger) = match &cli.indexer_db {
+        Some(path) => ("indexer", indexer_ledger(path, cli.round_id)?),
+        None => ("accounts", account_ledger(&rpc, cli.round_id)?),
+    };
+
+    let proof = proof::verify(&round, draw_epoch, &ledger);
+    Ok((proof.to_json(&round, source, &ledger), proof.passed()))
+}
+
+fn main() -> Result<()> {
+    let (output, passed) = run(Cli::parse())?;
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
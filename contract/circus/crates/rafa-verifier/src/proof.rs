@@ -0,0 +1,233 @@
+//! The checks behind a verdict. Each one compares what the program recorded against what
+//! `rafa_draw` recomputes from public inputs.
+
+use anchor_lang::prelude::Pubkey;
+use rafa::RoundState;
+use serde_json::{json, Value};
+
+/// One holder's tickets, `first..=last`
+pub struct LedgerEntry {
+    pub owner: Pubkey,
+    pub first: u64,
+    pub last: u64,
+}
+
+pub struct Check {
+    name: &'static str,
+    pass: bool,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, pass: bool, detail: String) -> Self {
+        Self { name, pass, detail }
+    }
+}
+
+pub struct Proof {
+    pub checks: Vec<Check>,
+    recomputed_ticket: Option<u64>,
+    recomputed_winner: Option<Pubkey>,
+}
+
+impl Proof {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.pass)
+    }
+
+    pub fn to_json(&self, round: &RoundState, ledger_source: &str, ledger: &[LedgerEntry]) -> Value {
+        json!({
+            "round_id": round.round_id,
+            "result": if self.passed() { "pass" } else { "fail" },
+            "draw_slot": round.draw_slot,
+            "draw_seed": round.draw_seed,
+            "total_tickets_sold": round.total_tickets_sold,
+            "voided_tickets": round.voided_tickets,
+            "winning_ticket": { "recorded": round.winning_ticket, "recomputed": self.recomputed_ticket },
+            "winner": {
+                "recorded": round.winner.map(|k| k.to_string()),
+                "recomputed": self.recomputed_winner.map(|k| k.to_string()),
+            },
+            "checks": self.checks.iter().map(|c| json!({ "check": c.name, "pass": c.pass, "detail": c.detail })).collect::<Vec<_>>(),
+            "ledger_source": ledger_source,
+            "ledger": ledger.iter().map(|e| json!({
+                "owner": e.owner.to_string(),
+                "first_ticket": e.first,
+                "last_ticket": e.last,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Run every check. `draw_epoch` is the cluster epoch of `round.draw_slot`.
+pub fn verify(round: &RoundState, draw_epoch: u64, ledger: &[LedgerEntry]) -> Proof {
+    let mut checks = Vec::new();
+
+    let finalized = round.is_complete && round.winner.is_some();
+    checks.push(Check::new(
+        "finalized",
+        finalized,
+        if finalized { "round has a recorded winner".into() } else { "round has no winner yet".into() },
+    ));
+
+    // The clock the program read at the draw: slot, its epoch, and the round's end time
+    let unix_timestamp = (round.end_epoch / 1000) as i64;
+    let clock_seed = rafa_draw::clock_seed(round.draw_slot, unix_timestamp, draw_epoch);
+    checks.push(Check::new(
+        "seed_from_clock",
+        clock_seed == round.draw_seed,
+        format!(
+            "clock_seed(slot {}, time {unix_timestamp}, epoch {draw_epoch}) = {clock_seed}, recorded {}",
+            round.draw_slot, round.draw_seed
+        ),
+    ));
+
+    checks.push(ledger_check(ledger, round.total_tickets_sold, round.voided_tickets));
+
+    let entries: Vec<_> = ledger.iter().map(|e| (e.owner, e.first, e.last)).collect();
+    let drawn = rafa_draw::draw(round.draw_seed, round.total_tickets_sold, round.live_tickets(), &entries);
+    let recomputed_ticket = drawn.map(|(ticket, _)| ticket);
+    let recomputed_winner = drawn.map(|(_, owner)| owner);
+    checks.push(Check::new(
+        "winning_ticket",
+        recomputed_ticket == Some(round.winning_ticket),
+        format!(
+            "draw(seed {}, {} sold, {} live) = {recomputed_ticket:?}",
+            round.draw_seed,
+            round.total_tickets_sold,
+            round.live_tickets()
+        ),
+    ));
+
+    checks.push(Check::new(
+        "winner",
+        recomputed_winner.is_some() && recomputed_winner == round.winner,
+        match recomputed_winner {
+            Some(owner) => format!("ticket held by {owner}"),
+            None => "no ledger entry holds the winning ticket".into(),
+        },
+    ));
+
+    Proof { checks, recomputed_ticket, recomputed_winner }
+}
+
+/// Every live ticket has exactly one holder; the gaps are the voided tickets
+fn ledger_check(ledger: &[LedgerEntry], total_tickets: u64, voided_tickets: u64) -> Check {
+    let mut ranges: Vec<(u64, u64)> = ledger.iter().map(|e| (e.first, e.last)).collect();
+    ranges.sort();
+    let mut next = 0;
+    let mut held = 0;
+    for (first, last) in ranges {
+        if first < next || last < first || last >= total_tickets {
+            let problem = if first < next { "overlap" } else { "range outside the tickets sold" };
+            return Check::new("ledger", false, format!("{problem} at ticket {first}"));
+        }
+        held += last - first + 1;
+        next = last + 1;
+    }
+    let live = total_tickets.saturating_sub(voided_tickets);
+    Check::new(
+        "ledger",
+        held == live,
+        format!("ledger holds {held} of {total_tickets} sold tickets, {voided_tickets} voided"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AnchorDeserialize;
+    use rafa::UserAccount;
+    use rafa_client::instructions;
+    use rafa_svm::fixture::{tickets, Protocol};
+
+    use super::*;
+
+    fn round(total_tickets_sold: u64, voided_tickets: u64) -> RoundState {
+        let mut round = RoundState::deserialize(&mut &[0u8; RoundState::SIZE][..]).unwrap();
+        round.total_tickets_sold = total_tickets_sold;
+        round.voided_tickets = voided_tickets;
+        round
+    }
+
+    /// Finalize `round` the way the program does: seed from the clock, winner from rafa_draw
+    fn finalize(round: &mut RoundState, ledger: &[LedgerEntry], slot: u64, end_secs: i64, epoch: u64) {
+        let entries: Vec<_> = ledger.iter().map(|e| (e.owner, e.first, e.last)).collect();
+        round.draw_slot = slot;
+        round.end_epoch = end_secs as u64 * 1000;
+        round.draw_seed = rafa_draw::clock_seed(slot, end_secs, epoch);
+        let (ticket, owner) = rafa_draw::draw(round.draw_seed, round.total_tickets_sold, round.live_tickets(), &entries)
+            .expect("complete ledger draws a winner");
+        round.winning_ticket = ticket;
+        round.winner = Some(owner);
+        round.is_complete = true;
+    }
+
+    #[test]
+    fn a_draw_by_rafa_draw_verifies_for_every_seed() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // Tickets 2, 5 and 6 were forfeited
+        let ledger = [
+            LedgerEntry { owner: alice, first: 0, last: 1 },
+            LedgerEntry { owner: bob, first: 3, last: 4 },
+            LedgerEntry { owner: alice, first: 7, last: 9 },
+        ];
+        for slot in 1..200 {
+            let mut round = round(10, 3);
+            finalize(&mut round, &ledger, slot, 1_700_000_000 + slot as i64, 3);
+            let proof = verify(&round, 3, &ledger);
+            assert!(proof.passed(), "slot {slot}: {}", proof.to_json(&round, "test", &ledger));
+        }
+    }
+
+    #[test]
+    fn tampered_rounds_and_ledgers_fail() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ledger = [LedgerEntry { owner: alice, first: 0, last: 4 }, LedgerEntry { owner: bob, first: 5, last: 9 }];
+        let mut honest = round(10, 0);
+        finalize(&mut honest, &ledger, 42, 1_700_000_000, 0);
+        assert!(verify(&honest, 0, &ledger).passed());
+
+        // Another draw epoch gives another seed
+        assert!(!verify(&honest, 1, &ledger).passed());
+
+        let mut other_winner = round(10, 0);
+        finalize(&mut other_winner, &ledger, 42, 1_700_000_000, 0);
+        other_winner.winner = Some(if honest.winner == Some(alice) { bob } else { alice });
+        assert!(!verify(&other_winner, 0, &ledger).passed());
+
+        let mut other_ticket = round(10, 0);
+        finalize(&mut other_ticket, &ledger, 42, 1_700_000_000, 0);
+        other_ticket.winning_ticket = (other_ticket.winning_ticket + 1) % 10;
+        assert!(!verify(&other_ticket, 0, &ledger).passed());
+
+        // A holder left out of the ledger, or two holders of one ticket
+        assert!(!verify(&honest, 0, &ledger[..1]).passed());
+        let overlapping =
+            [LedgerEntry { owner: alice, first: 0, last: 5 }, LedgerEntry { owner: bob, first: 5, last: 9 }];
+        assert!(!verify(&honest, 0, &overlapping).passed());
+    }
+
+    #[test]
+    fn a_round_finalized_by_the_program_verifies() {
+        let mut p = Protocol::new();
+        p.seed(10 * tickets(1)).unwrap();
+        let (alice, bob) = (p.wallet(10), p.wallet(10));
+        p.deposit(&alice, tickets(3)).unwrap();
+        p.deposit(&bob, tickets(2)).unwrap();
+        p.deposit(&alice, tickets(2)).unwrap();
+        p.svm.process(instructions::request_withdrawal(&bob, 0, tickets(1))).unwrap();
+        p.finish_round().unwrap();
+
+        let round = p.round(0);
+        let ledger: Vec<LedgerEntry> = p
+            .participants(0)
+            .iter()
+            .map(|address| p.svm.get::<UserAccount>(address).unwrap())
+            .flat_map(|user| {
+                user.ranges().map(|(first, last)| LedgerEntry { owner: user.owner, first, last }).collect::<Vec<_>>()
+            })
+            .collect();
+        let proof = verify(&round, p.svm.clock().epoch, &ledger);
+        assert!(proof.passed(), "{}", proof.to_json(&round, "accounts", &ledger));
+    }
+}
@@ -1,6 +1,16 @@
+// anchor 0.31 `#[program]` expands to the deprecated AccountInfo::realloc
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
 use anchor_lang::prelude::{AccountDeserialize, AccountSerialize};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::spl_pod::optional_keys::OptionalNonZeroPubkey;
+use anchor_spl::token_interface::spl_token_2022::instruction::AuthorityType;
+use anchor_spl::token_interface::spl_token_metadata_interface::state::{Field, TokenMetadata};
+use anchor_spl::token_interface::{self, Mint, Token2022, TokenAccount};
 
 declare_id!("AwJyUsRnuhMmvY5ft3HW5e96kbVcLXai1WGrn8GhLdNi");
 
@@ -11,6 +21,46 @@ pub const TICKET_PRICE_LAMPORTS: u64 = 10_000_000;
 // In production, change to 1 week = 604800 seconds
 pub const EPOCH_DURATION_SECONDS: i64 = 120; // 2 minutes for demo
 
+// Default delay before a proposed config change can execute: one full round (3 epochs),
+// so depositors always get to see a change coming and exit first
+pub const DEFAULT_TIMELOCK_SECONDS: i64 = 3 * EPOCH_DURATION_SECONDS;
+
+// Minimum time between begin_wind_down and close_protocol_state: one full round, so every
+// participant gets to take their funds out
+pub const WIND_DOWN_GRACE_SECONDS: i64 = 3 * EPOCH_DURATION_SECONDS;
+
+// Basis points in a whole, for the liquid buffer and exit fee rates
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// Share of the escrow kept out of staking so instant_withdraw can pay mid-round
+pub const DEFAULT_LIQUID_BUFFER_BPS: u16 = 1_000;
+
+// Fee instant_withdraw keeps for the prize pool, and the most a config change may set
+pub const DEFAULT_EXIT_FEE_BPS: u16 = 50;
+pub const MAX_EXIT_FEE_BPS: u16 = 1_000;
+
+// Capacity of the ChangeQueue account
+pub const MAX_PENDING_CHANGES: usize = 8;
+
+// Queued withdrawals paid per process_withdrawals_batch call (two accounts each)
+pub const MAX_WITHDRAWALS_PER_BATCH: usize = 10;
+
+// Open positions a round takes: the draw needs every ticket holder in one legacy crank transaction
+pub const MAX_ROUND_PARTICIPANTS: u32 = 24;
+
+// Symbol in the metadata of every ticket receipt mint
+pub const RECEIPT_SYMBOL: &str = "RAFA";
+
+// Upper bound on multisig owners (keeps Multisig/MultisigProposal sizes bounded)
+pub const MAX_MULTISIG_OWNERS: usize = 10;
+
+// Layout version stamped into every account. Accounts only ever gain fields at the end (first
+// out of the reserved bytes), so bumping this and migrating keeps old accounts usable.
+pub const ACCOUNT_VERSION: u8 = 5;
+
+// Bytes a migration may add to an account, at most: the reserved area plus a version's new fields
+const MIGRATION_PADDING: usize = 256;
+
 #[program]
 pub mod rafa {
     use super::*;
@@ -22,16 +72,38 @@ pub mod rafa {
         protocol_state.current_round = 0;
         protocol_state.prize_seed_amount = 0;
         protocol_state.total_unclaimed_prizes = 0;
+        protocol_state.cancelled_rounds = 0;
+        protocol_state.pending_admin = None;
+        protocol_state.keeper = ctx.accounts.admin.key();
+        protocol_state.guardian = ctx.accounts.admin.key();
+        protocol_state.treasurer = ctx.accounts.admin.key();
+        protocol_state.status = ProtocolStatus::Active;
+        protocol_state.timelock_delay_seconds = DEFAULT_TIMELOCK_SECONDS;
         protocol_state.bump = ctx.bumps.protocol_state;
+        protocol_state.version = ACCOUNT_VERSION;
+        protocol_state.outstanding_stake = 0;
+        protocol_state.liabilities_tracked = true;
+        protocol_state.wind_down_started_at = 0;
+        protocol_state.liquid_buffer_bps = DEFAULT_LIQUID_BUFFER_BPS;
+        protocol_state.exit_fee_bps = DEFAULT_EXIT_FEE_BPS;
+        protocol_state.liquid_buffer = 0;
+        protocol_state.max_tickets_per_user = 0;
+        protocol_state.max_tickets_per_round = 0;
+        protocol_state.min_tickets_per_deposit = 1;
+
+        emit_cpi!(ProtocolInitialized {
+            admin: ctx.accounts.admin.key(),
+            validator,
+        });
         Ok(())
     }
 
     pub fn seed_prize(ctx: Context<SeedPrize>, amount: u64) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
 
-        // Transfer lamports from admin to the protocol_state account (acts as MVP prize vault)
+        // Transfer lamports from treasurer to the protocol_state account (acts as MVP prize vault)
         let transfer_accounts = system_program::Transfer {
-            from: ctx.accounts.admin.to_account_info(),
+            from: ctx.accounts.treasurer.to_account_info(),
             to: ctx.accounts.protocol_state.to_account_info(),
         };
         let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), transfer_accounts);
@@ -43,7 +115,13 @@ pub mod rafa {
             .prize_seed_amount
             .checked_add(amount)
             .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let prize_seed_amount = protocol_state.prize_seed_amount;
 
+        emit_cpi!(PrizeSeeded {
+            treasurer: ctx.accounts.treasurer.key(),
+            amount,
+            prize_seed_amount,
+        });
         Ok(())
     }
 
@@ -51,6 +129,7 @@ pub mod rafa {
         let round = &mut ctx.accounts.round_state;
         let protocol = &mut ctx.accounts.protocol_state;
         protocol.current_round = round_id;
+        protocol.rebalance_liquid_buffer();
         round.round_id = round_id;
         round.epoch_in_round = 1;
         round.start_epoch = start_epoch;
@@ -64,220 +143,160 @@ pub mod rafa {
         round.is_complete = false;
         round.prize_claimed = false;
         round.vrf_request = None;
+        round.is_cancelled = false;
         round.bump = ctx.bumps.round_state;
+        round.draw_slot = 0;
+        round.draw_seed = 0;
+        round.version = ACCOUNT_VERSION;
+        round.rent_payer = ctx.accounts.payer.key();
+        round.open_positions = 0;
+        round.positions_tracked = true;
+        round.voided_tickets = 0;
+
+        emit_cpi!(RoundStarted {
+            round_id,
+            start_time_ms: start_epoch,
+            stake_account: ctx.accounts.stake_account.key(),
+            opened_by: ctx.accounts.payer.key(),
+        });
         Ok(())
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        require!(amount > 0, ErrorCode::InvalidAmount);
-
-        // Enforce fixed ticket price: amount must be exact multiple of TICKET_PRICE_LAMPORTS
-        require!(
-            amount % TICKET_PRICE_LAMPORTS == 0,
-            ErrorCode::InvalidTicketAmount
-        );
-
-        // Calculate number of tickets
-        let num_tickets = amount / TICKET_PRICE_LAMPORTS;
-        require!(num_tickets > 0, ErrorCode::InvalidAmount);
-
-        let protocol = &ctx.accounts.protocol_state;
-        let current_round_id = protocol.current_round;
-        let protocol_key = protocol.key();
-
-        // Derive round PDA
-        let round_id_bytes = current_round_id.to_le_bytes();
-        let round_pda_seeds = &[
-            b"round" as &[u8],
-            protocol_key.as_ref(),
-            &round_id_bytes as &[u8],
-        ];
-        let (round_pda, _) = Pubkey::find_program_address(round_pda_seeds, ctx.program_id);
-
-        // Get round state as AccountInfo (we need mutable access)
-        let round_account = ctx.remaining_accounts.get(0)
-            .ok_or(ErrorCode::MissingRoundAccount)?;
-
-        // Check if round exists by checking if account is initialized
-        let round_exists = round_account.data_len() > 0 && round_account.owner == ctx.program_id;
-
-        if !round_exists {
-            // AUTO-START: First buyer starts the round!
-            msg!("🎪 First buyer! Auto-starting round #{}", current_round_id);
-
-            // We cannot create the round account here since we're in remaining_accounts
-            // The round must be created via init_round first OR we need to change account structure
-            // For now, require round to be initialized (keep init_round for admin setup)
-            return Err(ErrorCode::MissingRoundAccount.into());
+    pub fn deposit<'info>(ctx: Context<'_, '_, 'info, 'info, Deposit<'info>>, amount: u64) -> Result<()> {
+        let owner = ctx.accounts.user.key();
+        let outcome = buy_tickets(
+            ctx.program_id,
+            &mut ctx.accounts.protocol_state,
+            &mut ctx.accounts.user_account,
+            ctx.bumps.user_account,
+            &ctx.accounts.user.to_account_info(),
+            owner,
+            &ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+        )?;
+        if let Some(event) = outcome.round_started {
+            emit_cpi!(event);
         }
-
-        require!(round_account.key() == round_pda, ErrorCode::InvalidRoundAccount);
-
-        // Deserialize round state
-        let mut round_data = round_account.try_borrow_mut_data()?;
-        let mut round_slice: &[u8] = &round_data;
-        let mut round_state = RoundState::try_deserialize(&mut round_slice)?;
-
-        // AUTO-ADVANCE EPOCHS & AUTO-FINALIZE based on time
-        let clock = Clock::get()?;
-        let current_time_ms = clock.unix_timestamp as u64 * 1000;
-
-        if !round_state.is_complete {
-            let elapsed_ms = current_time_ms.saturating_sub(round_state.start_epoch);
-            let epochs_passed = elapsed_ms / (EPOCH_DURATION_SECONDS as u64 * 1000);
-            let target_epoch = std::cmp::min(epochs_passed as u8 + 1, 3);
-
-            // Auto-advance epoch
-            if target_epoch > round_state.epoch_in_round {
-                msg!("⏰ Auto-advancing epoch {} → {}", round_state.epoch_in_round, target_epoch);
-                round_state.epoch_in_round = target_epoch;
-            }
-
-            // Check if round should be finalized (epoch 3 ended)
-            if round_state.epoch_in_round >= 3 {
-                let epoch_3_end_ms = round_state.start_epoch + (3 * EPOCH_DURATION_SECONDS as u64 * 1000);
-
-                if current_time_ms >= epoch_3_end_ms && round_state.total_tickets_sold > 0 {
-                    // AUTO-FINALIZE: Select winner!
-                    msg!("🎰 Auto-finalizing round #{}", current_round_id);
-
-                    // Generate pseudo-random seed from clock
-                    let seed = (clock.slot as u64)
-                        .wrapping_mul(clock.unix_timestamp as u64)
-                        .wrapping_add(clock.epoch);
-
-                    let winning_ticket_number = seed % round_state.total_tickets_sold;
-
-                    // Find winner from remaining_accounts (skip first which is round_state)
-                    let mut winner_pubkey: Option<Pubkey> = None;
-                    for user_ai in ctx.remaining_accounts.iter().skip(1) {
-                        if user_ai.data_len() > 0 {
-                            let user_data = user_ai.try_borrow_data()?;
-                            let mut user_slice: &[u8] = &user_data;
-                            if let Ok(user) = UserAccount::try_deserialize(&mut user_slice) {
-                                if user.round_joined == current_round_id &&
-                                   winning_ticket_number >= user.ticket_start &&
-                                   winning_ticket_number <= user.ticket_end {
-                                    winner_pubkey = Some(user.owner);
-                                    msg!("🎉 Winner found: {} (ticket #{})", user.owner, winning_ticket_number);
-                                    break;
-                                }
-                            }
-                        }
-                    }
-
-                    if let Some(winner) = winner_pubkey {
-                        // Calculate prize (use seed amount as prize for now)
-                        let prize_amount = protocol.prize_seed_amount;
-
-                        round_state.winner = Some(winner);
-                        round_state.winning_ticket = winning_ticket_number;
-                        round_state.total_prize_lamports = prize_amount;
-                        round_state.end_epoch = current_time_ms;
-                        round_state.is_complete = true;
-
-                        // Note: ClaimTicket creation will be done in a separate instruction
-                        // for now to keep this simpler
-                        msg!("Round #{} complete! Winner: {}, Prize: {} lamports",
-                             current_round_id, winner, prize_amount);
-                    }
-                }
-            }
+        if let Some(event) = outcome.epoch_advanced {
+            emit_cpi!(event);
         }
-
-        // If round is complete, block deposits
-        if round_state.is_complete {
-            msg!("❌ Round #{} is complete! Deposits blocked. Next round: #{}",
-                 current_round_id, current_round_id + 1);
-
-            // Serialize current round state before returning error
-            let mut round_out: Vec<u8> = Vec::with_capacity(round_data.len());
-            round_state.try_serialize(&mut round_out)?;
-            let copy_len = core::cmp::min(round_out.len(), round_data.len());
-            round_data[..copy_len].copy_from_slice(&round_out[..copy_len]);
-
-            return Err(ErrorCode::RoundComplete.into());
+        if let Some(event) = outcome.round_finalized {
+            emit_cpi!(event);
         }
-
-        // Block deposits if in epoch 3
-        require!(round_state.epoch_in_round < 3, ErrorCode::DepositsClosedEpoch3);
-
-        // Transfer lamports from user to protocol state (escrow)
-        let transfer_accounts = system_program::Transfer {
-            from: ctx.accounts.user.to_account_info(),
-            to: ctx.accounts.protocol_state.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), transfer_accounts);
-        system_program::transfer(cpi_ctx, amount)?;
-
-        // Assign ticket numbers: starting from current total
-        let ticket_start = round_state.total_tickets_sold;
-        let ticket_end = ticket_start + num_tickets - 1;
-
-        // Update round total tickets and staked amount
-        round_state.total_tickets_sold = round_state.total_tickets_sold
-            .checked_add(num_tickets)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
-
-        round_state.total_staked_lamports = round_state.total_staked_lamports
-            .checked_add(amount)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
-
-        // Serialize round state back
-        let mut round_out: Vec<u8> = Vec::with_capacity(round_data.len());
-        round_state.try_serialize(&mut round_out)?;
-        let copy_len = core::cmp::min(round_out.len(), round_data.len());
-        round_data[..copy_len].copy_from_slice(&round_out[..copy_len]);
-        drop(round_data);
-
-        // Update user account
-        let user_acct = &mut ctx.accounts.user_account;
-        user_acct.owner = ctx.accounts.user.key();
-
-        // If user is joining a new round, reset their state
-        if user_acct.round_joined != current_round_id && user_acct.round_joined != 0 {
-            // Reset for new round
-            user_acct.balance = 0;
-            user_acct.ticket_start = 0;
-            user_acct.ticket_end = 0;
-            user_acct.snapshot_balances = [0; 3];
-            user_acct.snapshots_recorded_mask = 0;
-            user_acct.pending_withdrawal_amount = 0;
-            user_acct.pending_withdrawal_round = 0;
+        if let Some(event) = outcome.next_round_started {
+            emit_cpi!(event);
         }
+        emit_cpi!(outcome.deposit_made);
+        Ok(())
+    }
 
-        // Update balance and tickets
-        if user_acct.balance == 0 {
-            // First deposit in this round
-            user_acct.ticket_start = ticket_start;
-            user_acct.ticket_end = ticket_end;
-        } else {
-            // Additional deposit - extend ticket range
-            user_acct.ticket_end = ticket_end;
+    /// Deposit paid by `payer` whose tickets and principal belong to `beneficiary`, for giveaways
+    /// and team-funded entries. Only the beneficiary can withdraw; the payer keeps no claim.
+    /// remaining_accounts as for deposit.
+    pub fn deposit_for<'info>(ctx: Context<'_, '_, 'info, 'info, DepositFor<'info>>, amount: u64) -> Result<()> {
+        let beneficiary = ctx.accounts.beneficiary.key();
+        let outcome = buy_tickets(
+            ctx.program_id,
+            &mut ctx.accounts.protocol_state,
+            &mut ctx.accounts.user_account,
+            ctx.bumps.user_account,
+            &ctx.accounts.payer.to_account_info(),
+            beneficiary,
+            &ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+        )?;
+        if let Some(event) = outcome.round_started {
+            emit_cpi!(event);
         }
-
-        user_acct.balance = user_acct
-            .balance
-            .checked_add(amount)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
-        user_acct.bump = ctx.bumps.user_account;
-
-        // Set round_joined if first time or new round
-        if user_acct.round_joined == 0 || user_acct.round_joined != current_round_id {
-            user_acct.round_joined = current_round_id;
+        if let Some(event) = outcome.epoch_advanced {
+            emit_cpi!(event);
+        }
+        if let Some(event) = outcome.round_finalized {
+            emit_cpi!(event);
         }
+        if let Some(event) = outcome.next_round_started {
+            emit_cpi!(event);
+        }
+        emit_cpi!(outcome.deposit_made);
+        emit_cpi!(DepositFunded {
+            round_id: outcome.deposit_made.round_id,
+            payer: ctx.accounts.payer.key(),
+            beneficiary,
+            amount,
+        });
+        Ok(())
+    }
 
-        msg!("✅ Deposited {} tickets ({} lamports). Tickets: #{}-#{}",
-             num_tickets, amount, ticket_start, ticket_end);
+    /// Deposit whose position is a Token-2022 NFT instead of the payer's UserAccount. The tickets
+    /// belong to a UserAccount keyed by the fresh `receipt_mint`, a PDA of the round, the payer and
+    /// a `nonce` the payer picks per receipt. The mint's metadata records the round and ticket
+    /// range, and its single token goes to the payer. Whoever holds the token
+    /// owns the position: they can sell it, and redeem it once the round is over.
+    /// remaining_accounts as for deposit.
+    pub fn deposit_with_receipt<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DepositWithReceipt<'info>>,
+        amount: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        let _ = nonce;
+        let mint = ctx.accounts.receipt_mint.key();
+        let outcome = buy_tickets(
+            ctx.program_id,
+            &mut ctx.accounts.protocol_state,
+            &mut ctx.accounts.receipt_account,
+            ctx.bumps.receipt_account,
+            &ctx.accounts.payer.to_account_info(),
+            mint,
+            &ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+            amount,
+        )?;
+        ctx.accounts.receipt_account.is_receipt = true;
+        ctx.accounts.issue_receipt(&outcome.deposit_made)?;
+
+        if let Some(event) = outcome.round_started {
+            emit_cpi!(event);
+        }
+        if let Some(event) = outcome.epoch_advanced {
+            emit_cpi!(event);
+        }
+        if let Some(event) = outcome.round_finalized {
+            emit_cpi!(event);
+        }
+        if let Some(event) = outcome.next_round_started {
+            emit_cpi!(event);
+        }
+        emit_cpi!(outcome.deposit_made);
+        msg!("🎟️ Receipt {} minted to {}", mint, ctx.accounts.payer.key());
+        emit_cpi!(ReceiptMinted {
+            round_id: outcome.deposit_made.round_id,
+            mint,
+            holder: ctx.accounts.payer.key(),
+            amount,
+            ticket_start: outcome.deposit_made.ticket_start,
+            ticket_end: outcome.deposit_made.ticket_end,
+        });
         Ok(())
     }
 
     pub fn request_withdrawal(ctx: Context<RequestWithdrawal>, amount: u64) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
+        // Whole tickets only, so whatever stays behind still buys whole tickets
+        require!(amount / TICKET_PRICE_LAMPORTS * TICKET_PRICE_LAMPORTS == amount, ErrorCode::InvalidTicketAmount);
         let user_acct = &mut ctx.accounts.user_account;
         require!(user_acct.balance >= amount, ErrorCode::InvalidAmount);
-        // Forfeit tickets for current round by zeroing mask for this round's remaining epochs
-        user_acct.snapshots_recorded_mask = 0; // MVP simplification
+        // Stake leaving a round that has not drawn yet takes its tickets out of the draw; the
+        // rest of the ranges stay in play
+        let round = &mut ctx.accounts.round_state;
+        let forfeited = if round.is_complete {
+            Vec::new()
+        } else {
+            let voided = round.forfeit_tickets(user_acct, amount / TICKET_PRICE_LAMPORTS)?;
+            round.total_staked_lamports = round.total_staked_lamports.saturating_sub(amount);
+            voided
+        };
         user_acct.balance = user_acct
             .balance
             .checked_sub(amount)
@@ -286,7 +305,34 @@ pub mod rafa {
             .pending_withdrawal_amount
             .checked_add(amount)
             .ok_or(ErrorCode::ArithmeticOverflow)?;
-        user_acct.pending_withdrawal_round = ctx.accounts.protocol_state.current_round;
+        // The stake sits in the round the position was opened in, which may already be over
+        user_acct.pending_withdrawal_round = user_acct.round_joined;
+
+        enqueue_withdrawal(
+            &ctx.accounts.withdrawal_queue,
+            &ctx.accounts.user,
+            &ctx.accounts.system_program,
+            &ctx.accounts.protocol_state.key(),
+            user_acct.round_joined,
+            ctx.bumps.withdrawal_queue,
+        )?;
+
+        for (first_ticket, last_ticket) in forfeited {
+            emit_cpi!(TicketsForfeited {
+                round_id: round.round_id,
+                user: user_acct.owner,
+                first_ticket,
+                last_ticket,
+                total_tickets_sold: round.total_tickets_sold,
+                voided_tickets: round.voided_tickets,
+            });
+        }
+        emit_cpi!(WithdrawalRequested {
+            round_id: user_acct.pending_withdrawal_round,
+            user: user_acct.owner,
+            amount,
+            remaining_balance: user_acct.balance,
+        });
         Ok(())
     }
 
@@ -326,44 +372,53 @@ pub mod rafa {
     pub fn advance_epoch(ctx: Context<AdvanceEpoch>) -> Result<()> {
         let round = &mut ctx.accounts.round_state;
         require!(round.epoch_in_round < 3, ErrorCode::InvalidEpoch);
+        let from_epoch = round.epoch_in_round;
         round.epoch_in_round = round.epoch_in_round.saturating_add(1);
+
+        emit_cpi!(EpochAdvanced {
+            round_id: round.round_id,
+            from_epoch,
+            to_epoch: round.epoch_in_round,
+        });
         Ok(())
     }
 
     pub fn select_winner_local(ctx: Context<SelectWinnerLocal>, seed: u64) -> Result<()> {
         let round = &mut ctx.accounts.round_state;
 
-        // Require at least 1 ticket sold
-        require!(round.total_tickets_sold > 0, ErrorCode::InvalidAmount);
-
-        // Calculate winning ticket number: random number from 0 to total_tickets_sold-1
-        let winning_ticket_number = seed % round.total_tickets_sold;
+        // Cancelled rounds can never be finalized
+        require!(!round.is_cancelled, ErrorCode::RoundCancelled);
 
-        // Find which user owns this ticket
-        let mut winner: Option<Pubkey> = None;
-        for ai in ctx.remaining_accounts.iter() {
-            let data = ai.try_borrow_data()?;
-            let mut input_slice: &[u8] = &data;
-            let user: UserAccount = match UserAccount::try_deserialize(&mut input_slice) {
-                Ok(u) => u,
-                Err(_) => continue,
-            };
-
-            // Check if winning ticket is in this user's range
-            if winning_ticket_number >= user.ticket_start && winning_ticket_number <= user.ticket_end {
-                winner = Some(user.owner);
-                msg!("Winner found: {} owns ticket #{}", user.owner, winning_ticket_number);
-                break;
-            }
-        }
+        // Require at least 1 ticket still held
+        require!(round.live_tickets() > 0, ErrorCode::InvalidAmount);
 
-        let chosen = winner.ok_or(ErrorCode::InvalidAmount)?;
+        // Winning ticket out of 0..total_tickets_sold, re-drawn past forfeited ones, and its owner
+        let (winning_ticket_number, chosen) =
+            draw_winner(round, seed, ctx.remaining_accounts)?.ok_or(ErrorCode::InvalidAmount)?;
         msg!("select_winner_local: total_tickets={} winning_ticket={} winner={}",
              round.total_tickets_sold, winning_ticket_number, chosen);
 
+        // The seed comes from the keeper, not the clock, so verifiers report it as such
+        let clock = Clock::get()?;
         round.winner = Some(chosen);
         round.winning_ticket = winning_ticket_number;
+        round.end_epoch = clock.unix_timestamp as u64 * 1000;
+        round.draw_slot = clock.slot;
+        round.draw_seed = seed;
+        round.total_prize_lamports = reserve_prize(&mut ctx.accounts.protocol_state)?;
         round.is_complete = true;
+
+        emit_cpi!(RoundFinalized {
+            round_id: round.round_id,
+            winner: chosen,
+            winning_ticket: winning_ticket_number,
+            total_tickets_sold: round.total_tickets_sold,
+            total_staked_lamports: round.total_staked_lamports,
+            prize_amount: round.total_prize_lamports,
+            end_time_ms: round.end_epoch,
+            draw_slot: round.draw_slot,
+            draw_seed: round.draw_seed,
+        });
         Ok(())
     }
 
@@ -390,7 +445,7 @@ pub mod rafa {
 
         // Transfer funds from protocol to winner
         let protocol_lamports = ctx.accounts.protocol_state.to_account_info().lamports();
-        let available_lamports = protocol_lamports.checked_sub(min_rent).unwrap_or(0);
+        let available_lamports = protocol_lamports.saturating_sub(min_rent);
 
         require!(available_lamports >= total_payout, ErrorCode::InsufficientFunds);
 
@@ -411,17 +466,87 @@ pub mod rafa {
 
         // Reset user account if they were part of this round
         if user_acct.round_joined == round_id {
+            let stake = user_acct
+                .balance
+                .checked_add(user_acct.pending_withdrawal_amount)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+            if stake > 0 {
+                round.settle_position();
+                ctx.accounts.protocol_state.release_stake(stake);
+            }
             user_acct.balance = 0;
-            user_acct.ticket_start = 0;
-            user_acct.ticket_end = 0;
+            user_acct.pending_withdrawal_amount = 0;
+            user_acct.clear_ranges();
         }
 
         msg!("✅ Prize claimed for Round #{}!", round_id);
+        emit_cpi!(PrizeClaimed {
+            round_id,
+            winner: ctx.accounts.user.key(),
+            stake_amount: ctx.accounts.claim_ticket.stake_amount,
+            prize_amount: ctx.accounts.claim_ticket.prize_amount,
+        });
+        Ok(())
+    }
+
+    /// Leave a running round early: `amount` (whole tickets) comes out of the liquid buffer at
+    /// once, minus the exit fee, which goes to the prize pool. The withdrawn tickets are
+    /// forfeited and no longer take part in the draw.
+    pub fn instant_withdraw(ctx: Context<InstantWithdraw>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount / TICKET_PRICE_LAMPORTS * TICKET_PRICE_LAMPORTS == amount, ErrorCode::InvalidTicketAmount);
+        let user_acct = &mut ctx.accounts.user_account;
+        require!(user_acct.balance >= amount, ErrorCode::InvalidAmount);
+
+        let protocol = &mut ctx.accounts.protocol_state;
+        let fee = protocol.exit_fee(amount);
+        let payout = amount - fee;
+        require!(payout <= protocol.liquid_buffer, ErrorCode::InsufficientLiquidity);
+        let min_rent = Rent::get()?.minimum_balance(8 + ProtocolState::SIZE);
+        let available_lamports = protocol.to_account_info().lamports().saturating_sub(min_rent);
+        require!(available_lamports >= payout, ErrorCode::InsufficientFunds);
+
+        **protocol.to_account_info().try_borrow_mut_lamports()? -= payout;
+        **ctx.accounts.user.to_account_info().try_borrow_mut_lamports()? += payout;
+        protocol.liquid_buffer -= payout;
+        protocol.release_stake(amount);
+        protocol.prize_seed_amount = protocol.prize_seed_amount
+            .checked_add(fee)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        let round = &mut ctx.accounts.round_state;
+        let forfeited = round.forfeit_tickets(user_acct, amount / TICKET_PRICE_LAMPORTS)?;
+        round.total_staked_lamports = round.total_staked_lamports.saturating_sub(amount);
+        user_acct.balance -= amount;
+        if user_acct.balance == 0 && user_acct.pending_withdrawal_amount == 0 {
+            round.settle_position();
+        }
+
+        msg!("⚡ Instant withdrawal from Round #{}: user={} amount={} fee={}",
+             round.round_id, user_acct.owner, amount, fee);
+        for (first_ticket, last_ticket) in forfeited {
+            emit_cpi!(TicketsForfeited {
+                round_id: round.round_id,
+                user: user_acct.owner,
+                first_ticket,
+                last_ticket,
+                total_tickets_sold: round.total_tickets_sold,
+                voided_tickets: round.voided_tickets,
+            });
+        }
+        emit_cpi!(InstantWithdrawal {
+            round_id: round.round_id,
+            user: user_acct.owner,
+            amount,
+            exit_fee: fee,
+            remaining_balance: user_acct.balance,
+        });
         Ok(())
     }
 
     /// Process withdrawal for non-winners after round completes
-    /// Losers can withdraw their stake from any completed round
+    /// Losers can withdraw their stake from any completed round. A requested withdrawal is paid
+    /// on its own and the remaining balance stays put for roll_over; without one, everything is paid.
     pub fn process_withdrawal(ctx: Context<ProcessWithdrawal>, round_id: u64) -> Result<()> {
         let round = &ctx.accounts.round_state;
         let user_acct = &mut ctx.accounts.user_account;
@@ -431,10 +556,12 @@ pub mod rafa {
             require!(winner != user_acct.owner, ErrorCode::WinnerMustClaim);
         }
 
-        // Get withdrawal amount (pending + balance)
-        let withdrawal_amount = user_acct.pending_withdrawal_amount
-            .checked_add(user_acct.balance)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        // Get withdrawal amount (the pending request, or the whole balance)
+        let withdrawal_amount = if user_acct.pending_withdrawal_amount > 0 {
+            user_acct.pending_withdrawal_amount
+        } else {
+            user_acct.balance
+        };
 
         require!(withdrawal_amount > 0, ErrorCode::NothingToWithdraw);
 
@@ -446,25 +573,248 @@ pub mod rafa {
 
         // Transfer funds from protocol to user
         let protocol_lamports = ctx.accounts.protocol_state.to_account_info().lamports();
-        let available_lamports = protocol_lamports.checked_sub(min_rent).unwrap_or(0);
+        let available_lamports = protocol_lamports.saturating_sub(min_rent);
 
         require!(available_lamports >= withdrawal_amount, ErrorCode::InsufficientFunds);
 
         **ctx.accounts.protocol_state.to_account_info().try_borrow_mut_lamports()? -= withdrawal_amount;
         **ctx.accounts.user.to_account_info().try_borrow_mut_lamports()? += withdrawal_amount;
 
-        // Reset user account
-        user_acct.balance = 0;
-        user_acct.pending_withdrawal_amount = 0;
-        user_acct.ticket_start = 0;
-        user_acct.ticket_end = 0;
+        // Reset user account once nothing is left
+        if user_acct.pending_withdrawal_amount > 0 {
+            user_acct.pending_withdrawal_amount = 0;
+        } else {
+            user_acct.balance = 0;
+        }
+        if user_acct.balance == 0 {
+            user_acct.clear_ranges();
+            ctx.accounts.round_state.settle_position();
+        }
+        ctx.accounts.protocol_state.release_stake(withdrawal_amount);
 
         msg!("✅ Withdrawal processed for Round #{}!", round_id);
+        emit_cpi!(WithdrawalProcessed {
+            round_id,
+            user: ctx.accounts.user.key(),
+            amount: withdrawal_amount,
+        });
+        Ok(())
+    }
+
+    /// Carry a loser's remaining balance from a completed round into the current one as fresh
+    /// tickets, as if it were deposited again. Pending withdrawals must be paid out first; the
+    /// current round must already be open and still taking deposits.
+    pub fn roll_over(ctx: Context<RollOver>, from_round: u64) -> Result<()> {
+        let previous = &mut ctx.accounts.previous_round;
+        let user_acct = &mut ctx.accounts.user_account;
+        if let Some(winner) = previous.winner {
+            require!(winner != user_acct.owner, ErrorCode::WinnerMustClaim);
+        }
+        require!(user_acct.pending_withdrawal_amount == 0, ErrorCode::PendingWithdrawal);
+        let amount = user_acct.balance;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(amount / TICKET_PRICE_LAMPORTS * TICKET_PRICE_LAMPORTS == amount, ErrorCode::InvalidTicketAmount);
+        let num_tickets = amount / TICKET_PRICE_LAMPORTS;
+
+        let round = &mut ctx.accounts.round_state;
+        let now_ms = Clock::get()?.unix_timestamp as u64 * 1000;
+        let epoch_3_start_ms = round.start_epoch + 2 * EPOCH_DURATION_SECONDS as u64 * 1000;
+        require!(round.epoch_in_round < 3 && now_ms < epoch_3_start_ms, ErrorCode::DepositsClosedEpoch3);
+        ctx.accounts.protocol_state.check_ticket_caps(0, round.live_tickets(), num_tickets)?;
+
+        previous.settle_position();
+        let ticket_start = round.total_tickets_sold;
+        let ticket_end = ticket_start + num_tickets - 1;
+        round.total_tickets_sold = round.total_tickets_sold
+            .checked_add(num_tickets)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        round.total_staked_lamports = round.total_staked_lamports
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(round.open_positions < MAX_ROUND_PARTICIPANTS, ErrorCode::RoundFull);
+        round.open_positions = round.open_positions
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        user_acct.round_joined = round.round_id;
+        user_acct.clear_ranges();
+        user_acct.ticket_start = ticket_start;
+        user_acct.ticket_end = ticket_end;
+        user_acct.snapshot_balances = [0; 3];
+        user_acct.snapshots_recorded_mask = 0;
+        user_acct.pending_withdrawal_round = 0;
+
+        msg!("🔁 Rolled {} lamports from Round #{} into Round #{}: user={} tickets {}-{}",
+             amount, from_round, round.round_id, user_acct.owner, ticket_start, ticket_end);
+        emit_cpi!(PositionRolledOver {
+            from_round,
+            round_id: round.round_id,
+            user: user_acct.owner,
+            amount,
+            ticket_start,
+            ticket_end,
+            total_tickets_sold: round.total_tickets_sold,
+            total_staked_lamports: round.total_staked_lamports,
+        });
+        Ok(())
+    }
+
+    /// Hand the signer's position in a running round (stake, tickets and snapshots) to another
+    /// wallet in one step, e.g. for a sale or a wallet migration. A recipient already in the same
+    /// round takes the tickets on as further ranges of its own position; stake left in an earlier
+    /// round must be settled first. The signer's pending withdrawals must be paid out first.
+    pub fn transfer_position(ctx: Context<TransferPosition>) -> Result<()> {
+        let from = &mut ctx.accounts.user_account;
+        require!(from.pending_withdrawal_amount == 0, ErrorCode::PendingWithdrawal);
+        require!(from.balance > 0, ErrorCode::InvalidAmount);
+        // Both sides would be the same account, and the merge would count the stake twice
+        require!(ctx.accounts.recipient.key() != from.owner, ErrorCode::RecipientHasPosition);
+        let recipient_info = ctx.accounts.recipient_account.to_account_info();
+        let to = &mut ctx.accounts.recipient_account;
+        let moved: Vec<(u64, u64)> = from.ranges().collect();
+        let amount = from.balance;
+
+        if to.balance == 0 && to.pending_withdrawal_amount == 0 {
+            to.owner = ctx.accounts.recipient.key();
+            to.balance = from.balance;
+            to.ticket_start = from.ticket_start;
+            to.ticket_end = from.ticket_end;
+            to.extra_ranges = from.extra_ranges.clone();
+            to.snapshot_balances = from.snapshot_balances;
+            to.snapshots_recorded_mask = from.snapshots_recorded_mask;
+            to.round_joined = from.round_joined;
+            to.pending_withdrawal_amount = 0;
+            to.pending_withdrawal_round = 0;
+            to.bump = ctx.bumps.recipient_account;
+            to.version = ACCOUNT_VERSION;
+        } else {
+            require!(to.round_joined == from.round_joined, ErrorCode::RecipientHasPosition);
+            // Each epoch either side recorded keeps both sides' weight. A side that missed the
+            // current epoch adds its balance now, as the batch would have; one that missed an
+            // earlier epoch adds nothing, as no batch can record it any more.
+            let current = usize::from(ctx.accounts.round_state.epoch_in_round).checked_sub(1);
+            let recorded = to.snapshots_recorded_mask | from.snapshots_recorded_mask;
+            for index in (0..to.snapshot_balances.len()).filter(|index| recorded & (1 << index) != 0) {
+                let weight = |side: &UserAccount| {
+                    if side.snapshots_recorded_mask & (1 << index) != 0 {
+                        side.snapshot_balances[index]
+                    } else if current == Some(index) {
+                        side.balance
+                    } else {
+                        0
+                    }
+                };
+                to.snapshot_balances[index] =
+                    weight(to).checked_add(weight(from)).ok_or(ErrorCode::ArithmeticOverflow)?;
+            }
+            to.snapshots_recorded_mask = recorded;
+            to.balance = to.balance.checked_add(amount).ok_or(ErrorCode::ArithmeticOverflow)?;
+            for &(first, last) in &moved {
+                to.add_range(first, last);
+            }
+            // Two positions became one
+            ctx.accounts.round_state.settle_position();
+        }
+        fit_user_account(
+            &recipient_info,
+            to.extra_ranges.len(),
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        from.balance = 0;
+        from.clear_ranges();
+        from.snapshot_balances = [0; 3];
+        from.snapshots_recorded_mask = 0;
+
+        msg!("🤝 Position in Round #{} moved: {} -> {} amount={} tickets={}",
+             to.round_joined, from.owner, to.owner, amount, to.tickets_held());
+        // One event per range moved, each with the stake its tickets stand for
+        for (ticket_start, ticket_end) in moved {
+            emit_cpi!(PositionTransferred {
+                round_id: to.round_joined,
+                from: from.owner,
+                to: to.owner,
+                amount: (ticket_end - ticket_start + 1) * TICKET_PRICE_LAMPORTS,
+                ticket_start,
+                ticket_end,
+            });
+        }
+        Ok(())
+    }
+
+    /// Pay queued withdrawal requests of a completed round in request order (anyone can call)
+    /// remaining_accounts are (UserAccount, owner wallet) pairs for the queue entries from `head`
+    /// on, at most MAX_WITHDRAWALS_PER_BATCH of them. Entries that no longer hold a pending
+    /// withdrawal in this round (withdrawn in person, winner, closed) are passed over. The batch
+    /// stops at the first request the protocol cannot cover yet, so later callers resume there.
+    pub fn process_withdrawals_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessWithdrawalsBatch<'info>>,
+        round_id: u64,
+    ) -> Result<()> {
+        let winner = ctx.accounts.round_state.winner;
+        let protocol_info = ctx.accounts.protocol_state.to_account_info();
+        let min_rent = Rent::get()?.minimum_balance(8 + ProtocolState::SIZE);
+        let start = ctx.accounts.withdrawal_queue.head;
+
+        for pair in ctx.remaining_accounts.chunks_exact(2).take(MAX_WITHDRAWALS_PER_BATCH) {
+            let queue = &mut ctx.accounts.withdrawal_queue;
+            let Some(&owner) = queue.entries.get(queue.head as usize) else {
+                break;
+            };
+            let (user_info, wallet) = (&pair[0], &pair[1]);
+            let (expected_user, _) = Pubkey::find_program_address(&[b"user", owner.as_ref()], &crate::ID);
+            require!(
+                user_info.key() == expected_user && wallet.key() == owner,
+                ErrorCode::InvalidQueueEntry
+            );
+
+            // A closed UserAccount has nothing left to pay
+            if user_info.data_is_empty() {
+                queue.head += 1;
+                continue;
+            }
+            require!(user_info.owner == &crate::ID, ErrorCode::InvalidQueueEntry);
+            let mut user: UserAccount = UserAccount::try_deserialize(&mut &user_info.try_borrow_data()?[..])?;
+            let amount = user.pending_withdrawal_amount;
+            // The winner's pending stake is paid with the prize by claim_prize
+            if user.round_joined != round_id || amount == 0 || winner == Some(owner) {
+                queue.head += 1;
+                continue;
+            }
+
+            let available_lamports = protocol_info.lamports().saturating_sub(min_rent);
+            if available_lamports < amount {
+                msg!("⏳ Withdrawal queue of round #{} waiting for {} lamports of liquidity", round_id, amount);
+                break;
+            }
+            **protocol_info.try_borrow_mut_lamports()? -= amount;
+            **wallet.try_borrow_mut_lamports()? += amount;
+
+            user.pending_withdrawal_amount = 0;
+            if user.balance == 0 {
+                user.clear_ranges();
+                ctx.accounts.round_state.settle_position();
+            }
+            let mut out: Vec<u8> = Vec::with_capacity(8 + UserAccount::SIZE);
+            user.try_serialize(&mut out)?;
+            user_info.try_borrow_mut_data()?[..out.len()].copy_from_slice(&out);
+            ctx.accounts.protocol_state.release_stake(amount);
+            queue.head += 1;
+
+            emit_cpi!(WithdrawalProcessed { round_id, user: owner, amount });
+        }
+
+        let queue = &ctx.accounts.withdrawal_queue;
+        msg!(
+            "💸 Withdrawal queue of round #{}: entries {}..{} done, {} left",
+            round_id, start, queue.head, queue.entries.len() - queue.head as usize
+        );
         Ok(())
     }
 
     /// Create a ClaimTicket PDA for a round's winner
-    /// Called by admin after round completes, or automatically during deposit finalization
+    /// Called by the treasurer after round completes, or automatically during deposit finalization
     pub fn create_claim_ticket(
         ctx: Context<CreateClaimTicket>,
         round_id: u64,
@@ -487,6 +837,8 @@ pub mod rafa {
         claim_ticket.stake_amount = stake_amount;
         claim_ticket.claimed = false;
         claim_ticket.bump = ctx.bumps.claim_ticket;
+        claim_ticket.version = ACCOUNT_VERSION;
+        claim_ticket.rent_payer = ctx.accounts.treasurer.key();
 
         // Update protocol state to track unclaimed prize
         let protocol = &mut ctx.accounts.protocol_state;
@@ -497,6 +849,12 @@ pub mod rafa {
         msg!("🎫 ClaimTicket created for Round #{}: winner={} prize={} stake={}",
              round_id, winner, prize_amount, stake_amount);
 
+        emit_cpi!(ClaimTicketCreated {
+            round_id,
+            winner,
+            prize_amount,
+            stake_amount,
+        });
         Ok(())
     }
 
@@ -518,18 +876,22 @@ pub mod rafa {
         // Ensure caller is the winner
         require!(round.winner.unwrap() == winner.key(), ErrorCode::NotWinner);
 
-        // Calculate prize amount (total staked minus winner's stake)
-        let prize_amount = round.total_staked_lamports
-            .checked_sub(user_acct.balance)
-            .unwrap_or(0);
+        // The prize is the one reserved at finalization; the other participants' stakes stay
+        // theirs to withdraw. The stake includes any withdrawal requested during the round.
+        let prize_amount = round.total_prize_lamports;
+        let stake_amount = user_acct.balance
+            .checked_add(user_acct.pending_withdrawal_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
 
         // Initialize claim ticket
         claim_ticket.round_id = round_id;
         claim_ticket.winner = winner.key();
         claim_ticket.prize_amount = prize_amount;
-        claim_ticket.stake_amount = user_acct.balance;
+        claim_ticket.stake_amount = stake_amount;
         claim_ticket.claimed = false;
         claim_ticket.bump = ctx.bumps.claim_ticket;
+        claim_ticket.version = ACCOUNT_VERSION;
+        claim_ticket.rent_payer = winner.key();
 
         // Update protocol state to track unclaimed prize
         let protocol = &mut ctx.accounts.protocol_state;
@@ -538,8 +900,14 @@ pub mod rafa {
             .ok_or(ErrorCode::ArithmeticOverflow)?;
 
         msg!("🎫 ClaimTicket created by winner for Round #{}: prize={} stake={}",
-             round_id, prize_amount, user_acct.balance);
-
+             round_id, prize_amount, stake_amount);
+
+        emit_cpi!(ClaimTicketCreated {
+            round_id,
+            winner: winner.key(),
+            prize_amount,
+            stake_amount,
+        });
         Ok(())
     }
 
@@ -547,252 +915,2510 @@ pub mod rafa {
     /// This instruction allows anyone to trigger epoch advancement and round finalization
     /// without needing to make a deposit. This prevents the round from getting "stuck"
     /// when no deposits are made for extended periods.
-    pub fn crank(ctx: Context<Crank>) -> Result<()> {
-        let protocol = &ctx.accounts.protocol_state;
+    pub fn crank<'info>(ctx: Context<'_, '_, 'info, 'info, Crank<'info>>) -> Result<()> {
         let round = &mut ctx.accounts.round_state;
+        let clock = Clock::get()?;
+        let current_time_ms = clock.unix_timestamp as u64 * 1000;
+        let mut epoch_advanced: Option<EpochAdvanced> = None;
+        let mut round_finalized: Option<RoundFinalized> = None;
 
-        // Only process if round is not complete
+        // Only advance/finalize if round is not complete
         if round.is_complete {
-            msg!("Round already complete, no action needed");
-            return Ok(());
-        }
+            msg!("Round already complete, no epoch action needed");
+        } else {
+            let elapsed_ms = current_time_ms.saturating_sub(round.start_epoch);
+            let epochs_passed = elapsed_ms / (EPOCH_DURATION_SECONDS as u64 * 1000);
+            let target_epoch = std::cmp::min(epochs_passed as u8 + 1, 3);
 
-        let clock = Clock::get()?;
-        let current_time_ms = clock.unix_timestamp as u64 * 1000;
+            // Auto-advance epoch
+            if target_epoch > round.epoch_in_round {
+                msg!("⏰ Crank: Auto-advancing epoch {} → {}", round.epoch_in_round, target_epoch);
+                epoch_advanced = Some(EpochAdvanced {
+                    round_id: round.round_id,
+                    from_epoch: round.epoch_in_round,
+                    to_epoch: target_epoch,
+                });
+                round.epoch_in_round = target_epoch;
+            }
 
-        let elapsed_ms = current_time_ms.saturating_sub(round.start_epoch);
-        let epochs_passed = elapsed_ms / (EPOCH_DURATION_SECONDS as u64 * 1000);
-        let target_epoch = std::cmp::min(epochs_passed as u8 + 1, 3);
+            // Check if round should be finalized (epoch 3 ended)
+            if round.epoch_in_round >= 3 {
+                let epoch_3_end_ms = round.start_epoch + (3 * EPOCH_DURATION_SECONDS as u64 * 1000);
 
-        // Auto-advance epoch
-        if target_epoch > round.epoch_in_round {
-            msg!("⏰ Crank: Auto-advancing epoch {} → {}", round.epoch_in_round, target_epoch);
-            round.epoch_in_round = target_epoch;
-        }
+                if current_time_ms >= epoch_3_end_ms && round.live_tickets() > 0 {
+                    // AUTO-FINALIZE: Select winner!
+                    msg!("🎰 Crank: Auto-finalizing round #{}", round.round_id);
 
-        // Check if round should be finalized (epoch 3 ended)
-        if round.epoch_in_round >= 3 {
-            let epoch_3_end_ms = round.start_epoch + (3 * EPOCH_DURATION_SECONDS as u64 * 1000);
+                    // Pseudo-random seed from the clock, see rafa_draw
+                    let seed = rafa_draw::clock_seed(clock.slot, clock.unix_timestamp, clock.epoch);
 
-            if current_time_ms >= epoch_3_end_ms && round.total_tickets_sold > 0 {
-                // AUTO-FINALIZE: Select winner!
-                msg!("🎰 Crank: Auto-finalizing round #{}", round.round_id);
-
-                // Generate pseudo-random seed from clock
-                let seed = (clock.slot as u64)
-                    .wrapping_mul(clock.unix_timestamp as u64)
-                    .wrapping_add(clock.epoch);
-
-                let winning_ticket_number = seed % round.total_tickets_sold;
-
-                // Find winner from remaining_accounts
-                let mut winner_pubkey: Option<Pubkey> = None;
-                for user_ai in ctx.remaining_accounts.iter() {
-                    if user_ai.data_len() > 0 {
-                        let user_data = user_ai.try_borrow_data()?;
-                        let mut user_slice: &[u8] = &user_data;
-                        if let Ok(user) = UserAccount::try_deserialize(&mut user_slice) {
-                            if user.round_joined == round.round_id &&
-                               winning_ticket_number >= user.ticket_start &&
-                               winning_ticket_number <= user.ticket_end {
-                                winner_pubkey = Some(user.owner);
-                                msg!("🎉 Winner found: {} (ticket #{})", user.owner, winning_ticket_number);
-                                break;
-                            }
-                        }
+                    // Find winner from remaining_accounts
+                    let drawn = draw_winner(round, seed, ctx.remaining_accounts)?;
+
+                    if let Some((winning_ticket_number, winner)) = drawn {
+                        msg!("🎉 Winner found: {} (ticket #{})", winner, winning_ticket_number);
+                        // The prize comes out of the seeded funds
+                        let prize_amount = reserve_prize(&mut ctx.accounts.protocol_state)?;
+
+                        round.winner = Some(winner);
+                        round.winning_ticket = winning_ticket_number;
+                        round.draw_slot = clock.slot;
+                        round.draw_seed = seed;
+                        round.total_prize_lamports = prize_amount;
+                        round.end_epoch = current_time_ms;
+                        round.is_complete = true;
+
+                        msg!("Round #{} complete! Winner: {}, Prize: {} lamports",
+                             round.round_id, winner, prize_amount);
+                        round_finalized = Some(RoundFinalized {
+                            round_id: round.round_id,
+                            winner,
+                            winning_ticket: winning_ticket_number,
+                            total_tickets_sold: round.total_tickets_sold,
+                            total_staked_lamports: round.total_staked_lamports,
+                            prize_amount,
+                            end_time_ms: current_time_ms,
+                            draw_slot: clock.slot,
+                            draw_seed: seed,
+                        });
                     }
                 }
+            }
+        }
 
-                if let Some(winner) = winner_pubkey {
-                    // Calculate prize
-                    let prize_amount = protocol.prize_seed_amount;
+        // AUTO-START: if the current round is done and the caller passed the next RoundState PDA
+        // (plus the system program) in remaining_accounts, open it funded by the treasury
+        let round_id = round.round_id;
+        let round_complete = round.is_complete;
+        let stake_account = round.stake_account;
 
-                    round.winner = Some(winner);
-                    round.winning_ticket = winning_ticket_number;
-                    round.total_prize_lamports = prize_amount;
-                    round.end_epoch = current_time_ms;
-                    round.is_complete = true;
+        if let Some(event) = epoch_advanced {
+            emit_cpi!(event);
+        }
+        if let Some(event) = round_finalized {
+            emit_cpi!(event);
+        }
 
-                    msg!("Round #{} complete! Winner: {}, Prize: {} lamports",
-                         round.round_id, winner, prize_amount);
+        if round_complete && round_id == ctx.accounts.protocol_state.current_round {
+            let next_round_id = round_id.checked_add(1).ok_or(ErrorCode::ArithmeticOverflow)?;
+            let protocol_key = ctx.accounts.protocol_state.key();
+            let (next_round_pda, _) = find_round_pda(&protocol_key, next_round_id, ctx.program_id);
+            let next_round_account = ctx.remaining_accounts.iter().find(|ai| ai.key() == next_round_pda);
+            let system_program_account = ctx.remaining_accounts.iter().find(|ai| ai.key() == system_program::ID);
+
+            if let (Some(next_round_account), Some(system_program_account)) = (next_round_account, system_program_account) {
+                // Best effort: the crank has no payer, so a treasury too short for the rent leaves
+                // the round for the next depositor instead of rolling back the finalization
+                if treasury_covers_round_rent(&ctx.accounts.protocol_state, next_round_account)? {
+                    msg!("🎪 Crank: Auto-starting round #{}", next_round_id);
+                    open_round_account(
+                        ctx.program_id,
+                        &ctx.accounts.protocol_state.to_account_info(),
+                        &mut ctx.accounts.protocol_state,
+                        next_round_account,
+                        None,
+                        system_program_account,
+                        next_round_id,
+                        stake_account,
+                        current_time_ms,
+                    )?;
+                    emit_cpi!(RoundStarted {
+                        round_id: next_round_id,
+                        start_time_ms: current_time_ms,
+                        stake_account,
+                        opened_by: Pubkey::default(),
+                    });
+                } else {
+                    msg!("⏭️ Crank: Treasury can't cover rent, round #{} left for the next deposit", next_round_id);
                 }
             }
         }
 
+        let round = &ctx.accounts.round_state;
         msg!("✅ Crank complete: Epoch {}, Complete: {}", round.epoch_in_round, round.is_complete);
         Ok(())
     }
 
-    /// Close the ProtocolState account and recover rent (admin only)
-    /// DANGER: This will reset the entire protocol! Only use for testing/reinitialization.
-    pub fn close_protocol_state(ctx: Context<CloseProtocolState>) -> Result<()> {
-        let protocol = &ctx.accounts.protocol_state;
+    /// Cancel a broken round (admin or guardian)
+    /// Blocks finalization for good and opens the refund path for every participant.
+    /// The round counts as complete, so the next deposit or crank can start the following round.
+    pub fn cancel_round(ctx: Context<CancelRound>, round_id: u64) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
 
-        msg!("⚠️  CLOSING ProtocolState account!");
-        msg!("Admin: {}", protocol.admin);
-        msg!("Current round: {}", protocol.current_round);
-        msg!("Unclaimed prizes: {} lamports", protocol.total_unclaimed_prizes);
+        require!(!round.is_cancelled, ErrorCode::RoundCancelled);
+        require!(round.winner.is_none(), ErrorCode::RoundAlreadyFinalized);
 
-        // Safety check: require no unclaimed prizes
-        require!(
-            protocol.total_unclaimed_prizes == 0,
-            ErrorCode::UnclaimedPrizesExist
-        );
+        let clock = Clock::get()?;
+        round.is_cancelled = true;
+        round.is_complete = true;
+        round.end_epoch = clock.unix_timestamp as u64 * 1000;
+
+        let protocol = &mut ctx.accounts.protocol_state;
+        protocol.cancelled_rounds = protocol.cancelled_rounds
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
 
+        msg!("🛑 Round #{} cancelled: {} tickets / {} lamports to refund",
+             round_id, round.total_tickets_sold, round.total_staked_lamports);
+        emit_cpi!(RoundCancelled {
+            round_id,
+            total_tickets_sold: round.total_tickets_sold,
+            total_staked_lamports: round.total_staked_lamports,
+        });
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(
-        init,
-        payer = admin,
-        seeds = [b"state"],
-        bump,
-        space = 8 + ProtocolState::SIZE,
-    )]
-    pub protocol_state: Account<'info, ProtocolState>,
-    pub system_program: Program<'info, System>,
-}
+    /// Refund a participant's full deposit from a cancelled round (callable by anyone)
+    /// Funds always go to the owner of the UserAccount, so keepers can push refunds to everyone.
+    pub fn refund_cancelled(ctx: Context<RefundCancelled>, round_id: u64) -> Result<()> {
+        let user_acct = &mut ctx.accounts.user_account;
 
-#[derive(Accounts)]
-pub struct SeedPrize<'info> {
-    #[account(mut, address = protocol_state.admin)]
+        let refund_amount = user_acct.balance
+            .checked_add(user_acct.pending_withdrawal_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        require!(refund_amount > 0, ErrorCode::NothingToWithdraw);
+
+        // Calculate rent exemption minimum
+        let min_rent = Rent::get()?.minimum_balance(8 + ProtocolState::SIZE);
+
+        // Transfer funds from protocol to participant
+        let protocol_lamports = ctx.accounts.protocol_state.to_account_info().lamports();
+        let available_lamports = protocol_lamports.saturating_sub(min_rent);
+
+        require!(available_lamports >= refund_amount, ErrorCode::InsufficientFunds);
+
+        **ctx.accounts.protocol_state.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
+        **ctx.accounts.participant.to_account_info().try_borrow_mut_lamports()? += refund_amount;
+
+        // Reset user account
+        user_acct.balance = 0;
+        user_acct.pending_withdrawal_amount = 0;
+        user_acct.clear_ranges();
+        ctx.accounts.round_state.settle_position();
+        ctx.accounts.protocol_state.release_stake(refund_amount);
+
+        msg!("💸 Refunded {} lamports to {} from cancelled Round #{}",
+             refund_amount, user_acct.owner, round_id);
+        emit_cpi!(RefundProcessed {
+            round_id,
+            user: user_acct.owner,
+            amount: refund_amount,
+        });
+        Ok(())
+    }
+
+    /// Cash in a ticket receipt once its round is over, winner or not (holder only). The principal,
+    /// plus the prize when the receipt holds the winning ticket, goes to whoever holds the token
+    /// now. The token is burned and the token account, mint and receipt position are closed,
+    /// their rent to the holder.
+    pub fn redeem_receipt(ctx: Context<RedeemReceipt>) -> Result<()> {
+        let mint = ctx.accounts.receipt_mint.key();
+        let receipt = &ctx.accounts.receipt_account;
+        let round = &mut ctx.accounts.round_state;
+        let principal = receipt.balance
+            .checked_add(receipt.pending_withdrawal_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let won = round.winner == Some(mint);
+        let prize = if won { round.total_prize_lamports } else { 0 };
+        let payout = principal.checked_add(prize).ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        // Calculate rent exemption minimum
+        let min_rent = Rent::get()?.minimum_balance(8 + ProtocolState::SIZE);
+        let protocol_lamports = ctx.accounts.protocol_state.to_account_info().lamports();
+        let available_lamports = protocol_lamports.saturating_sub(min_rent);
+        require!(available_lamports >= payout, ErrorCode::InsufficientFunds);
+
+        **ctx.accounts.protocol_state.to_account_info().try_borrow_mut_lamports()? -= payout;
+        **ctx.accounts.holder.to_account_info().try_borrow_mut_lamports()? += payout;
+
+        if principal > 0 {
+            round.settle_position();
+            ctx.accounts.protocol_state.release_stake(principal);
+        }
+        if won {
+            round.prize_claimed = true;
+        }
+        let round_id = round.round_id;
+        ctx.accounts.retire_receipt()?;
+
+        msg!("🎟️ Receipt {} redeemed by {}: stake={} prize={}",
+             mint, ctx.accounts.holder.key(), principal, prize);
+        emit_cpi!(ReceiptRedeemed {
+            round_id,
+            mint,
+            holder: ctx.accounts.holder.key(),
+            stake_amount: principal,
+            prize_amount: prize,
+        });
+        Ok(())
+    }
+
+    /// Change the protocol status (admin or guardian)
+    /// The guardian can only move towards a more restrictive status; only the admin can unpause.
+    pub fn set_protocol_status(ctx: Context<SetProtocolStatus>, status: ProtocolStatus) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        let previous = protocol.status;
+
+        if ctx.accounts.authority.key() != protocol.admin {
+            require!(status > previous, ErrorCode::GuardianCannotUnpause);
+        }
+        // Wind-down is one way: deposits and rounds never come back
+        if protocol.winding_down() {
+            require!(status >= ProtocolStatus::WithdrawOnly, ErrorCode::WindingDown);
+        }
+
+        protocol.status = status;
+        msg!("🚦 Protocol status: {:?} → {:?} (by {})", previous, status, ctx.accounts.authority.key());
+        emit_cpi!(ProtocolStatusChanged {
+            authority: ctx.accounts.authority.key(),
+            previous,
+            status,
+        });
+        Ok(())
+    }
+
+    /// Assign the keeper, guardian or treasurer key (admin only)
+    pub fn set_role(ctx: Context<SetRole>, role: ProtocolRole, key: Pubkey) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        let slot = match role {
+            ProtocolRole::Keeper => &mut protocol.keeper,
+            ProtocolRole::Guardian => &mut protocol.guardian,
+            ProtocolRole::Treasurer => &mut protocol.treasurer,
+        };
+        msg!("🔑 {:?}: {} → {}", role, slot, key);
+        let previous = *slot;
+        *slot = key;
+        emit_cpi!(RoleChanged { role, previous, key });
+        Ok(())
+    }
+
+    /// Step 1 of admin rotation: nominate the next admin (admin only)
+    /// Nothing changes until the nominee accepts; proposing again replaces the nominee.
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        protocol.pending_admin = Some(new_admin);
+        msg!("🔑 Admin handover proposed: {} → {}", protocol.admin, new_admin);
+        emit_cpi!(AdminProposed {
+            admin: protocol.admin,
+            pending_admin: new_admin,
+        });
+        Ok(())
+    }
+
+    /// Step 2 of admin rotation: the nominee signs to take over
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        let new_admin = ctx.accounts.new_admin.key();
+        msg!("🔑 Admin handover accepted: {} → {}", protocol.admin, new_admin);
+        let previous = protocol.admin;
+        protocol.admin = new_admin;
+        protocol.pending_admin = None;
+        emit_cpi!(AdminChanged { previous, admin: new_admin });
+        Ok(())
+    }
+
+    /// Create the timelocked config change queue (admin only, once)
+    pub fn init_change_queue(ctx: Context<InitChangeQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.change_queue;
+        queue.next_change_id = 0;
+        queue.changes = Vec::new();
+        queue.bump = ctx.bumps.change_queue;
+        queue.version = ACCOUNT_VERSION;
+        emit_cpi!(ChangeQueueCreated { change_queue: queue.key() });
+        Ok(())
+    }
+
+    /// Queue a config change (admin only)
+    /// It can only be executed once `timelock_delay_seconds` have passed.
+    pub fn propose_change(ctx: Context<ProposeChange>, change: ConfigChange) -> Result<()> {
+        change.validate()?;
+
+        let protocol = &ctx.accounts.protocol_state;
+        let queue = &mut ctx.accounts.change_queue;
+        require!(queue.changes.len() < MAX_PENDING_CHANGES, ErrorCode::ChangeQueueFull);
+
+        let now = Clock::get()?.unix_timestamp;
+        let execute_after = now
+            .checked_add(protocol.timelock_delay_seconds)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let id = queue.next_change_id;
+
+        queue.changes.push(PendingChange {
+            id,
+            change: change.clone(),
+            proposed_at: now,
+            execute_after,
+        });
+        queue.next_change_id = id.checked_add(1).ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        msg!("⏳ Config change #{} queued: {:?}, executable after {}", id, change, execute_after);
+        emit_cpi!(ChangeProposed { id, change, execute_after });
+        Ok(())
+    }
+
+    /// Apply a queued config change once its timelock has expired (admin only)
+    pub fn execute_change(ctx: Context<ExecuteChange>, change_id: u64) -> Result<()> {
+        let queue = &mut ctx.accounts.change_queue;
+        let index = queue.changes.iter()
+            .position(|c| c.id == change_id)
+            .ok_or(ErrorCode::ChangeNotFound)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= queue.changes[index].execute_after, ErrorCode::TimelockNotExpired);
+
+        let pending = queue.changes.remove(index);
+        pending.change.apply(&mut ctx.accounts.protocol_state);
+
+        msg!("✅ Config change #{} executed: {:?}", change_id, pending.change);
+        emit_cpi!(ChangeExecuted { id: change_id, change: pending.change });
+        Ok(())
+    }
+
+    /// Drop a queued config change (admin or guardian)
+    pub fn cancel_change(ctx: Context<CancelChange>, change_id: u64) -> Result<()> {
+        let queue = &mut ctx.accounts.change_queue;
+        let index = queue.changes.iter()
+            .position(|c| c.id == change_id)
+            .ok_or(ErrorCode::ChangeNotFound)?;
+
+        let pending = queue.changes.remove(index);
+        msg!("🗑️  Config change #{} cancelled: {:?}", change_id, pending.change);
+        emit_cpi!(ChangeCancelled {
+            id: change_id,
+            change: pending.change,
+            cancelled_by: ctx.accounts.authority.key(),
+        });
+        Ok(())
+    }
+
+    /// Create the M-of-N multisig for this protocol (admin only, once)
+    /// Hand it a role by pointing the role at `multisig_signer` (e.g. propose_admin + an
+    /// executed accept_admin proposal); its proposals can then run any admin-gated instruction.
+    pub fn create_multisig(ctx: Context<CreateMultisig>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        validate_multisig_owners(&owners, threshold)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.owner_set_seqno = 0;
+        multisig.proposal_count = 0;
+        multisig.signer_bump = ctx.bumps.multisig_signer;
+        multisig.bump = ctx.bumps.multisig;
+        multisig.version = ACCOUNT_VERSION;
+
+        msg!("🔐 Multisig created: {}-of-{}, signer={}",
+             threshold, multisig.owners.len(), ctx.accounts.multisig_signer.key());
+        emit_cpi!(MultisigOwnersChanged {
+            owners: multisig.owners.clone(),
+            threshold,
+            owner_set_seqno: multisig.owner_set_seqno,
+        });
+        Ok(())
+    }
+
+    /// Propose a rafa instruction to be executed with the multisig signer (any owner)
+    /// The proposer's approval is recorded immediately.
+    pub fn propose_multisig_tx(
+        ctx: Context<ProposeMultisigTx>,
+        accounts: Vec<ProposalAccount>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        require!(multisig_allowed(ctx.accounts.protocol_state.status, &data), ErrorCode::ProtocolFrozen);
+        let multisig = &mut ctx.accounts.multisig;
+        let owner_index = multisig.owners.iter()
+            .position(|o| *o == ctx.accounts.proposer.key())
+            .ok_or(ErrorCode::NotMultisigOwner)?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.multisig = multisig.key();
+        proposal.id = multisig.proposal_count;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.accounts = accounts;
+        proposal.data = data;
+        proposal.approvals = vec![false; multisig.owners.len()];
+        proposal.approvals[owner_index] = true;
+        proposal.owner_set_seqno = multisig.owner_set_seqno;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+        proposal.version = ACCOUNT_VERSION;
+
+        multisig.proposal_count = multisig.proposal_count
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        msg!("🔐 Multisig proposal #{} created by {}", proposal.id, proposal.proposer);
+        emit_cpi!(MultisigTxProposed {
+            proposal_id: proposal.id,
+            proposer: proposal.proposer,
+            data: proposal.data.clone(),
+        });
+        Ok(())
+    }
+
+    /// Approve a pending multisig proposal (any owner)
+    pub fn approve_multisig_tx(ctx: Context<ApproveMultisigTx>, proposal_id: u64) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let owner_index = multisig.owners.iter()
+            .position(|o| *o == ctx.accounts.owner.key())
+            .ok_or(ErrorCode::NotMultisigOwner)?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.approvals[owner_index] = true;
+
+        let approvals = proposal.approvals.iter().filter(|a| **a).count();
+        msg!("🔐 Multisig proposal #{} approved by {} ({}/{})",
+             proposal_id, ctx.accounts.owner.key(), approvals, multisig.threshold);
+        emit_cpi!(MultisigTxApproved {
+            proposal_id,
+            owner: ctx.accounts.owner.key(),
+            approvals: approvals as u8,
+        });
+        Ok(())
+    }
+
+    /// Execute a proposal once it reached the threshold (callable by anyone)
+    /// remaining_accounts must contain every account listed in the proposal.
+    pub fn execute_multisig_tx<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteMultisigTx<'info>>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+
+        let approvals = proposal.approvals.iter().filter(|a| **a).count();
+        require!(approvals >= multisig.threshold as usize, ErrorCode::NotEnoughApprovals);
+
+        // Mark executed and persist it before the CPI so the proposal can never run twice,
+        // not even by re-entering through its own instruction
+        proposal.executed = true;
+        proposal.exit(ctx.program_id)?;
+
+        let signer_key = ctx.accounts.multisig_signer.key();
+        let ix = Instruction {
+            program_id: crate::ID,
+            accounts: proposal.accounts.iter()
+                .map(|a| AccountMeta {
+                    pubkey: a.pubkey,
+                    is_signer: a.is_signer || a.pubkey == signer_key,
+                    is_writable: a.is_writable,
+                })
+                .collect(),
+            data: proposal.data.clone(),
+        };
+
+        let multisig_key = multisig.key();
+        let signer_seeds: &[&[u8]] = &[b"multisig-signer", multisig_key.as_ref(), &[multisig.signer_bump]];
+        invoke_signed(&ix, ctx.remaining_accounts, &[signer_seeds])?;
+
+        msg!("🔐 Multisig proposal #{} executed", proposal_id);
+        emit_cpi!(MultisigTxExecuted { proposal_id });
+        Ok(())
+    }
+
+    /// Replace the multisig owner set (must be executed through the multisig itself)
+    /// Invalidates every proposal created under the previous owner set.
+    pub fn set_multisig_owners(ctx: Context<SetMultisigOwners>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        validate_multisig_owners(&owners, threshold)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.owner_set_seqno = multisig.owner_set_seqno
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        msg!("🔐 Multisig owners updated: {}-of-{}", threshold, multisig.owners.len());
+        emit_cpi!(MultisigOwnersChanged {
+            owners: multisig.owners.clone(),
+            threshold,
+            owner_set_seqno: multisig.owner_set_seqno,
+        });
+        Ok(())
+    }
+
+    /// First stage of a teardown (admin only): deposits and round progression stop for good,
+    /// withdrawals stay open. The admin then cancels the current round if it is still running
+    /// (cancel_round), participants withdraw or get refunded, and after WIND_DOWN_GRACE_SECONDS
+    /// close_protocol_state can run.
+    pub fn begin_wind_down(ctx: Context<BeginWindDown>) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        require!(!protocol.winding_down(), ErrorCode::WindingDown);
+
+        let now = Clock::get()?.unix_timestamp;
+        protocol.wind_down_started_at = now;
+        protocol.status = protocol.status.max(ProtocolStatus::WithdrawOnly);
+
+        msg!("🌅 Wind-down started: close possible from {} once every stake is paid out",
+             now + WIND_DOWN_GRACE_SECONDS);
+        emit_cpi!(WindDownBegun {
+            admin: ctx.accounts.admin.key(),
+            status: protocol.status,
+            closable_at: now + WIND_DOWN_GRACE_SECONDS,
+        });
+        Ok(())
+    }
+
+    /// Last stage of a teardown (admin only): close the ProtocolState, sending what is left
+    /// (rent and unspent seed) to the admin. Refused until the wind-down grace period is over
+    /// and nothing is owed to anyone.
+    pub fn close_protocol_state(ctx: Context<CloseProtocolState>) -> Result<()> {
+        let protocol = &ctx.accounts.protocol_state;
+        require!(protocol.winding_down(), ErrorCode::NotWindingDown);
+        require!(
+            Clock::get()?.unix_timestamp >= protocol.wind_down_started_at + WIND_DOWN_GRACE_SECONDS,
+            ErrorCode::WindDownGracePeriod
+        );
+        require!(
+            protocol.total_unclaimed_prizes == 0,
+            ErrorCode::UnclaimedPrizesExist
+        );
+        // Protocols initialized before stakes were tracked cannot show they owe nothing
+        require!(
+            protocol.liabilities_tracked && protocol.outstanding_stake == 0,
+            ErrorCode::OutstandingStake
+        );
+
+        msg!("⚠️  CLOSING ProtocolState account!");
+        msg!("Admin: {}", protocol.admin);
+        msg!("Current round: {}", protocol.current_round);
+        msg!("Returned to admin: {} lamports", protocol.to_account_info().lamports());
+        emit_cpi!(ProtocolClosed {
+            admin: protocol.admin,
+            returned: protocol.to_account_info().lamports(),
+        });
+        Ok(())
+    }
+
+    /// Close a finished round once every participant is paid out, returning its rent to whoever
+    /// paid it (anyone can call)
+    pub fn close_round(ctx: Context<CloseRound>, round_id: u64) -> Result<()> {
+        let round = &ctx.accounts.round_state;
+        require!(round.is_complete, ErrorCode::RoundNotComplete);
+        // Rounds opened before positions were counted can never show they are settled
+        require!(round.positions_tracked && round.open_positions == 0, ErrorCode::RoundNotSettled);
+        require!(round.winner.is_none() || round.prize_claimed, ErrorCode::RoundNotSettled);
+
+        let rent = round.to_account_info().lamports();
+        credit_returned_rent(&mut ctx.accounts.protocol_state, &ctx.accounts.rent_payer.key(), rent)?;
+        msg!("🧹 Closed round #{}, {} lamports rent to {}", round_id, rent, ctx.accounts.rent_payer.key());
+        emit_cpi!(RoundClosed { round_id, rent_receiver: ctx.accounts.rent_payer.key(), rent });
+        Ok(())
+    }
+
+    /// Close a claimed ClaimTicket, returning its rent to whoever paid it (anyone can call)
+    pub fn close_claim_ticket(ctx: Context<CloseClaimTicket>, round_id: u64, winner: Pubkey) -> Result<()> {
+        let rent = ctx.accounts.claim_ticket.to_account_info().lamports();
+        credit_returned_rent(&mut ctx.accounts.protocol_state, &ctx.accounts.rent_payer.key(), rent)?;
+        msg!("🧹 Closed claim ticket of {} for round #{}", winner, round_id);
+        emit_cpi!(ClaimTicketClosed { round_id, winner, rent_receiver: ctx.accounts.rent_payer.key(), rent });
+        Ok(())
+    }
+
+    /// Close a round's WithdrawalQueue once every entry is processed (anyone can call). Requesters
+    /// paid its rent a slot at a time, so it goes to the protocol's seeded funds.
+    pub fn close_withdrawal_queue(ctx: Context<CloseWithdrawalQueue>, round_id: u64) -> Result<()> {
+        let rent = ctx.accounts.withdrawal_queue.to_account_info().lamports();
+        let protocol_key = ctx.accounts.protocol_state.key();
+        credit_returned_rent(&mut ctx.accounts.protocol_state, &protocol_key, rent)?;
+        msg!("🧹 Closed withdrawal queue of round #{}, {} lamports rent to the treasury", round_id, rent);
+        emit_cpi!(WithdrawalQueueClosed { round_id, rent });
+        Ok(())
+    }
+
+    /// Close the signer's UserAccount once nothing is staked in it; the rent goes back to them
+    pub fn close_user_account(ctx: Context<CloseUserAccount>) -> Result<()> {
+        emit_cpi!(UserAccountClosed {
+            owner: ctx.accounts.owner.key(),
+            rent: ctx.accounts.user_account.to_account_info().lamports(),
+        });
+        Ok(())
+    }
+
+    /// Count the open positions of a round migrated from the baseline, which never tracked them,
+    /// so close_round can tell once it is settled (keeper or admin). remaining_accounts are every
+    /// UserAccount still staked in the round, each once. While the round runs they must hold all
+    /// of its live tickets; after the draw the caller vouches for the list.
+    pub fn reconcile_round(ctx: Context<ReconcileRound>, round_id: u64) -> Result<()> {
+        let round = &mut ctx.accounts.round_state;
+        require!(!round.positions_tracked, ErrorCode::AlreadyReconciled);
+        let positions = open_stakes(ctx.remaining_accounts)?;
+        require!(
+            positions.iter().all(|user| user.round_joined == round_id),
+            ErrorCode::NotAnOpenPosition
+        );
+        if !round.is_complete && !round.is_cancelled {
+            let held: u64 = positions.iter().map(UserAccount::tickets_held).sum();
+            require!(held == round.live_tickets(), ErrorCode::IncompleteTicketLedger);
+        }
+
+        round.open_positions = positions.len() as u32;
+        round.positions_tracked = true;
+        msg!("🧮 Round #{} reconciled: {} open positions", round_id, round.open_positions);
+        emit_cpi!(RoundReconciled { round_id, open_positions: round.open_positions });
+        Ok(())
+    }
+
+    /// Count the stake owed back to users on a protocol migrated from the baseline, which never
+    /// tracked it, so close_protocol_state can tell once everyone is paid (admin only).
+    /// remaining_accounts are every UserAccount holding a balance or pending withdrawal, each once.
+    pub fn reconcile_liabilities(ctx: Context<ReconcileLiabilities>) -> Result<()> {
+        let protocol = &mut ctx.accounts.protocol_state;
+        require!(!protocol.liabilities_tracked, ErrorCode::AlreadyReconciled);
+        let positions = open_stakes(ctx.remaining_accounts)?;
+        let mut owed: u64 = 0;
+        for user in &positions {
+            owed = owed
+                .checked_add(user.balance)
+                .and_then(|owed| owed.checked_add(user.pending_withdrawal_amount))
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+
+        protocol.outstanding_stake = owed;
+        protocol.liabilities_tracked = true;
+        msg!("🧮 Liabilities reconciled: {} lamports owed to {} positions", owed, positions.len());
+        emit_cpi!(LiabilitiesReconciled { outstanding_stake: owed, positions: positions.len() as u32 });
+        Ok(())
+    }
+
+    // Layout migrations: anyone may run them, the payer covers the rent of the extra bytes.
+    // Accounts written before the current ACCOUNT_VERSION fail to deserialize in every other
+    // instruction until migrated.
+
+    pub fn migrate_protocol_state(ctx: Context<Migrate>) -> Result<()> {
+        let migrated = ctx.accounts.migrate::<ProtocolState>()?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    pub fn migrate_round_state(ctx: Context<Migrate>) -> Result<()> {
+        let migrated = ctx.accounts.migrate::<RoundState>()?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    pub fn migrate_user_account(ctx: Context<Migrate>) -> Result<()> {
+        let migrated = ctx.accounts.migrate::<UserAccount>()?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    pub fn migrate_claim_ticket(ctx: Context<Migrate>) -> Result<()> {
+        let migrated = ctx.accounts.migrate::<ClaimTicket>()?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    pub fn migrate_change_queue(ctx: Context<Migrate>) -> Result<()> {
+        let migrated = ctx.accounts.migrate::<ChangeQueue>()?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    pub fn migrate_multisig(ctx: Context<Migrate>) -> Result<()> {
+        let migrated = ctx.accounts.migrate::<Multisig>()?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+
+    pub fn migrate_multisig_proposal(ctx: Context<Migrate>) -> Result<()> {
+        let migrated = ctx.accounts.migrate::<MultisigProposal>()?;
+        emit_cpi!(migrated);
+        Ok(())
+    }
+}
+
+/// Whether the multisig may propose, approve or execute a proposal running `data`. While Frozen
+/// only set_protocol_status goes through, so a multisig-held admin can still lift the freeze.
+fn multisig_allowed(status: ProtocolStatus, data: &[u8]) -> bool {
+    status.operations_allowed() || data.starts_with(instruction::SetProtocolStatus::DISCRIMINATOR)
+}
+
+/// Derive the RoundState PDA for `round_id`
+pub fn find_round_pda(protocol_state: &Pubkey, round_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"round", protocol_state.as_ref(), &round_id.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Where a closed account's rent goes: whoever paid it, or the protocol itself when the
+/// treasury did or the account predates rent tracking
+pub fn rent_receiver(rent_payer: Pubkey, protocol_state: Pubkey) -> Pubkey {
+    if rent_payer == Pubkey::default() {
+        protocol_state
+    } else {
+        rent_payer
+    }
+}
+
+/// Rent coming back to the protocol account rejoins the seeded funds it was paid from
+fn credit_returned_rent(protocol: &mut Account<ProtocolState>, receiver: &Pubkey, rent: u64) -> Result<()> {
+    if *receiver == protocol.key() {
+        protocol.prize_seed_amount = protocol.prize_seed_amount
+            .checked_add(rent)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    }
+    Ok(())
+}
+
+/// Decode `accounts` for the reconcile instructions: each must be a UserAccount at its owner's
+/// PDA that still holds stake, and none may be passed twice
+fn open_stakes(accounts: &[AccountInfo]) -> Result<Vec<UserAccount>> {
+    let mut positions: Vec<UserAccount> = Vec::with_capacity(accounts.len());
+    for ai in accounts {
+        require!(ai.owner == &crate::ID, ErrorCode::NotAnOpenPosition);
+        let user = UserAccount::try_deserialize(&mut &ai.try_borrow_data()?[..])
+            .map_err(|_| ErrorCode::NotAnOpenPosition)?;
+        let address = Pubkey::create_program_address(&[b"user", user.owner.as_ref(), &[user.bump]], &crate::ID)
+            .map_err(|_| ErrorCode::NotAnOpenPosition)?;
+        require!(
+            address == ai.key() && (user.balance > 0 || user.pending_withdrawal_amount > 0),
+            ErrorCode::NotAnOpenPosition
+        );
+        require!(
+            !positions.iter().any(|other| other.owner == user.owner),
+            ErrorCode::DuplicateParticipant
+        );
+        positions.push(user);
+    }
+    Ok(positions)
+}
+
+/// Events a deposit produces, in order, for the instruction handler to emit
+struct DepositOutcome {
+    round_started: Option<RoundStarted>,
+    epoch_advanced: Option<EpochAdvanced>,
+    round_finalized: Option<RoundFinalized>,
+    next_round_started: Option<RoundStarted>,
+    deposit_made: DepositMade,
+}
+
+/// Shared body of deposit and deposit_for: `payer` funds `amount` worth of tickets in the
+/// current round, credited to `owner`'s `user_account`. Opens, advances and finalizes rounds on
+/// the way exactly as a deposit always has.
+#[allow(clippy::too_many_arguments)]
+fn buy_tickets<'info>(
+    program_id: &Pubkey,
+    protocol: &mut Account<'info, ProtocolState>,
+    user_account: &mut Account<'info, UserAccount>,
+    user_account_bump: u8,
+    payer: &AccountInfo<'info>,
+    owner: Pubkey,
+    system_program_info: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<DepositOutcome> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+
+    // Enforce fixed ticket price: amount must be exact multiple of TICKET_PRICE_LAMPORTS
+    require!(
+        amount / TICKET_PRICE_LAMPORTS * TICKET_PRICE_LAMPORTS == amount,
+        ErrorCode::InvalidTicketAmount
+    );
+
+    // Calculate number of tickets
+    let num_tickets = amount / TICKET_PRICE_LAMPORTS;
+    require!(num_tickets > 0, ErrorCode::InvalidAmount);
+    require!(num_tickets >= u64::from(protocol.min_tickets_per_deposit), ErrorCode::BelowMinimumTickets);
+
+    let mut round_started = None;
+    let mut epoch_advanced = None;
+    let mut round_finalized = None;
+    let mut next_round_started = None;
+
+    let current_round_id = protocol.current_round;
+    let protocol_key = protocol.key();
+
+    // Derive round PDA
+    let (round_pda, _) = find_round_pda(&protocol_key, current_round_id, program_id);
+
+    // Get round state as AccountInfo (we need mutable access)
+    let round_account = remaining_accounts.first()
+        .ok_or(ErrorCode::MissingRoundAccount)?;
+    require!(round_account.key() == round_pda, ErrorCode::InvalidRoundAccount);
+
+    let clock = Clock::get()?;
+    let current_time_ms = clock.unix_timestamp as u64 * 1000;
+
+    // Check if round exists by checking if account is initialized
+    let round_exists = round_account.data_len() > 0 && round_account.owner == program_id;
+
+    if !round_exists {
+        // AUTO-START: First buyer starts the round!
+        msg!("🎪 First buyer! Auto-starting round #{}", current_round_id);
+        open_round_account(
+            program_id,
+            &protocol.to_account_info(),
+            protocol,
+            round_account,
+            Some(payer),
+            system_program_info,
+            current_round_id,
+            Pubkey::default(),
+            current_time_ms,
+        )?;
+        round_started = Some(RoundStarted {
+            round_id: current_round_id,
+            start_time_ms: current_time_ms,
+            stake_account: Pubkey::default(),
+            opened_by: payer.key(),
+        });
+    }
+
+    // Deserialize round state
+    let mut round_data = round_account.try_borrow_mut_data()?;
+    let mut round_slice: &[u8] = &round_data;
+    let mut round_state = RoundState::try_deserialize(&mut round_slice)?;
+
+    // AUTO-ADVANCE EPOCHS & AUTO-FINALIZE based on time
+    if !round_state.is_complete {
+        let elapsed_ms = current_time_ms.saturating_sub(round_state.start_epoch);
+        let epochs_passed = elapsed_ms / (EPOCH_DURATION_SECONDS as u64 * 1000);
+        let target_epoch = std::cmp::min(epochs_passed as u8 + 1, 3);
+
+        // Auto-advance epoch
+        if target_epoch > round_state.epoch_in_round {
+            msg!("⏰ Auto-advancing epoch {} → {}", round_state.epoch_in_round, target_epoch);
+            epoch_advanced = Some(EpochAdvanced {
+                round_id: current_round_id,
+                from_epoch: round_state.epoch_in_round,
+                to_epoch: target_epoch,
+            });
+            round_state.epoch_in_round = target_epoch;
+        }
+
+        // Check if round should be finalized (epoch 3 ended)
+        if round_state.epoch_in_round >= 3 {
+            let epoch_3_end_ms = round_state.start_epoch + (3 * EPOCH_DURATION_SECONDS as u64 * 1000);
+
+            if current_time_ms >= epoch_3_end_ms && round_state.live_tickets() > 0 {
+                // AUTO-FINALIZE: Select winner!
+                msg!("🎰 Auto-finalizing round #{}", current_round_id);
+
+                // Pseudo-random seed from the clock, see rafa_draw
+                let seed = rafa_draw::clock_seed(clock.slot, clock.unix_timestamp, clock.epoch);
+
+                // Find winner from remaining_accounts (skip first which is round_state)
+                let drawn = draw_winner(&round_state, seed, &remaining_accounts[1..])?;
+
+                if let Some((winning_ticket_number, winner)) = drawn {
+                    msg!("🎉 Winner found: {} (ticket #{})", winner, winning_ticket_number);
+                    // The prize comes out of the seeded funds
+                    let prize_amount = reserve_prize(protocol)?;
+
+                    round_state.winner = Some(winner);
+                    round_state.winning_ticket = winning_ticket_number;
+                    round_state.draw_slot = clock.slot;
+                    round_state.draw_seed = seed;
+                    round_state.total_prize_lamports = prize_amount;
+                    round_state.end_epoch = current_time_ms;
+                    round_state.is_complete = true;
+
+                    round_finalized = Some(RoundFinalized {
+                        round_id: current_round_id,
+                        winner,
+                        winning_ticket: winning_ticket_number,
+                        total_tickets_sold: round_state.total_tickets_sold,
+                        total_staked_lamports: round_state.total_staked_lamports,
+                        prize_amount,
+                        end_time_ms: current_time_ms,
+                        draw_slot: clock.slot,
+                        draw_seed: seed,
+                    });
+
+                    // Note: ClaimTicket creation will be done in a separate instruction
+                    // for now to keep this simpler
+                    msg!("Round #{} complete! Winner: {}, Prize: {} lamports",
+                         current_round_id, winner, prize_amount);
+                }
+            }
+        }
+    }
+
+    // If round is complete, roll over into the next round when its PDA was passed in
+    // remaining_accounts[1]; otherwise block deposits
+    if round_state.is_complete {
+        // Serialize current round state before moving on
+        let mut round_out: Vec<u8> = Vec::with_capacity(round_data.len());
+        round_state.try_serialize(&mut round_out)?;
+        let copy_len = core::cmp::min(round_out.len(), round_data.len());
+        round_data[..copy_len].copy_from_slice(&round_out[..copy_len]);
+        drop(round_data);
+
+        let next_round_id = current_round_id
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let (next_round_pda, _) = find_round_pda(&protocol_key, next_round_id, program_id);
+        let next_round_account = match remaining_accounts.get(1) {
+            Some(ai) if ai.key() == next_round_pda => ai,
+            _ => {
+                msg!("❌ Round #{} is complete! Deposits blocked. Next round: #{}",
+                     current_round_id, next_round_id);
+                return Err(ErrorCode::RoundComplete.into());
+            }
+        };
+
+        msg!("🎪 Round #{} is complete! Auto-starting round #{}", current_round_id, next_round_id);
+        open_round_account(
+            program_id,
+            &protocol.to_account_info(),
+            protocol,
+            next_round_account,
+            Some(payer),
+            system_program_info,
+            next_round_id,
+            round_state.stake_account,
+            current_time_ms,
+        )?;
+        next_round_started = Some(RoundStarted {
+            round_id: next_round_id,
+            start_time_ms: current_time_ms,
+            stake_account: round_state.stake_account,
+            opened_by: payer.key(),
+        });
+
+        round_data = next_round_account.try_borrow_mut_data()?;
+        let mut round_slice: &[u8] = &round_data;
+        round_state = RoundState::try_deserialize(&mut round_slice)?;
+    }
+    let current_round_id = round_state.round_id;
+
+    // Block deposits if in epoch 3
+    require!(round_state.epoch_in_round < 3, ErrorCode::DepositsClosedEpoch3);
+
+    // Tickets the owner already holds in this round count towards the per-wallet cap
+    let held_tickets = if user_account.round_joined == current_round_id {
+        user_account.balance / TICKET_PRICE_LAMPORTS
+    } else {
+        0
+    };
+    protocol.check_ticket_caps(held_tickets, round_state.live_tickets(), num_tickets)?;
+
+    // Transfer lamports from user to protocol state (escrow)
+    let transfer_accounts = system_program::Transfer {
+        from: payer.clone(),
+        to: protocol.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(system_program_info.clone(), transfer_accounts);
+    system_program::transfer(cpi_ctx, amount)?;
+    protocol.outstanding_stake = protocol.outstanding_stake
+        .checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    protocol.add_liquid_share(amount);
+
+    // Nothing staked yet: this deposit opens the user's position in the round
+    let user_acct = &*user_account;
+    if user_acct.balance == 0 && user_acct.pending_withdrawal_amount == 0 {
+        require!(round_state.open_positions < MAX_ROUND_PARTICIPANTS, ErrorCode::RoundFull);
+        round_state.open_positions = round_state.open_positions
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    }
+
+    // Assign ticket numbers: starting from current total
+    let ticket_start = round_state.total_tickets_sold;
+    let ticket_end = ticket_start + num_tickets - 1;
+
+    // Update round total tickets and staked amount
+    round_state.total_tickets_sold = round_state.total_tickets_sold
+        .checked_add(num_tickets)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    round_state.total_staked_lamports = round_state.total_staked_lamports
+        .checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    // Serialize round state back
+    let mut round_out: Vec<u8> = Vec::with_capacity(round_data.len());
+    round_state.try_serialize(&mut round_out)?;
+    let copy_len = core::cmp::min(round_out.len(), round_data.len());
+    round_data[..copy_len].copy_from_slice(&round_out[..copy_len]);
+    drop(round_data);
+
+    // Update user account
+    let user_info = user_account.to_account_info();
+    let user_acct = &mut *user_account;
+    user_acct.owner = owner;
+
+    // If user is joining a new round, their previous position must already be paid out
+    // (withdrawn, claimed or refunded); otherwise that stake would be lost or paid twice
+    if user_acct.round_joined != current_round_id {
+        require!(
+            user_acct.balance == 0 && user_acct.pending_withdrawal_amount == 0,
+            ErrorCode::UnsettledPosition
+        );
+        user_acct.snapshot_balances = [0; 3];
+        user_acct.snapshots_recorded_mask = 0;
+        user_acct.pending_withdrawal_round = 0;
+    }
+
+    // Update balance and tickets
+    if user_acct.balance == 0 && user_acct.pending_withdrawal_amount == 0 {
+        // First deposit in this round
+        user_acct.clear_ranges();
+        user_acct.ticket_start = ticket_start;
+        user_acct.ticket_end = ticket_end;
+    } else {
+        // Additional deposit: extends the last range, or opens another one when others have
+        // bought tickets since
+        user_acct.add_range(ticket_start, ticket_end);
+    }
+    fit_user_account(&user_info, user_acct.extra_ranges.len(), payer, system_program_info)?;
+
+    user_acct.balance = user_acct
+        .balance
+        .checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    user_acct.bump = user_account_bump;
+    user_acct.version = ACCOUNT_VERSION;
+
+    // Set round_joined if first time or new round
+    if user_acct.round_joined == 0 || user_acct.round_joined != current_round_id {
+        user_acct.round_joined = current_round_id;
+    }
+
+    msg!("✅ Deposited {} tickets ({} lamports). Tickets: #{}-#{}",
+         num_tickets, amount, ticket_start, ticket_end);
+    Ok(DepositOutcome {
+        round_started,
+        epoch_advanced,
+        round_finalized,
+        next_round_started,
+        deposit_made: DepositMade {
+            round_id: current_round_id,
+            user: owner,
+            amount,
+            tickets: num_tickets,
+            ticket_start,
+            ticket_end,
+            total_tickets_sold: round_state.total_tickets_sold,
+            total_staked_lamports: round_state.total_staked_lamports,
+        },
+    })
+}
+
+/// `(owner, first_ticket, last_ticket)` of every range held by a UserAccount among `accounts`
+/// that joined `round`: the ticket ledger the draw picks its winner from. Each holder may appear
+/// once and the ranges may not overlap, so passing an account twice cannot make a partial ledger
+/// look complete.
+fn ticket_ledger(accounts: &[AccountInfo], round: &RoundState) -> Result<Vec<(Pubkey, u64, u64)>> {
+    let mut ledger = Vec::new();
+    let mut holders: Vec<(Pubkey, Pubkey)> = Vec::new();
+    for ai in accounts {
+        if ai.owner != &crate::ID || ai.data_len() == 0 {
+            continue;
+        }
+        let data = ai.try_borrow_data()?;
+        let mut slice: &[u8] = &data;
+        if let Ok(user) = UserAccount::try_deserialize(&mut slice) {
+            if user.round_joined == round.round_id {
+                require!(
+                    !holders.iter().any(|(key, owner)| *key == ai.key() || *owner == user.owner),
+                    ErrorCode::DuplicateParticipant
+                );
+                holders.push((ai.key(), user.owner));
+                ledger.extend(user.ranges().map(|(first, last)| (user.owner, first, last)));
+            }
+        }
+    }
+
+    let mut ranges: Vec<(u64, u64)> = ledger.iter().map(|(_, first, last)| (*first, *last)).collect();
+    ranges.sort_unstable();
+    require!(
+        ranges.windows(2).all(|pair| pair[0].1 < pair[1].0)
+            && ranges.last().is_none_or(|(_, last)| *last < round.total_tickets_sold),
+        ErrorCode::OverlappingTicketRanges
+    );
+    Ok(ledger)
+}
+
+/// Winning ticket and owner for `round` drawn from `seed`, see rafa_draw::draw. `None` when no
+/// participant of the round is among `accounts`; otherwise they must hold every live ticket.
+fn draw_winner(round: &RoundState, seed: u64, accounts: &[AccountInfo]) -> Result<Option<(u64, Pubkey)>> {
+    let ledger = ticket_ledger(accounts, round)?;
+    if ledger.is_empty() {
+        return Ok(None);
+    }
+    let held: u64 = ledger.iter().map(|(_, first, last)| last - first + 1).sum();
+    require!(held == round.live_tickets(), ErrorCode::IncompleteTicketLedger);
+    Ok(rafa_draw::draw(seed, round.total_tickets_sold, round.live_tickets(), &ledger))
+}
+
+/// Move a finalized round's prize out of the seeded funds. One round's rent stays behind so the
+/// next round can still be opened from the treasury; every seeded lamport is spent only once.
+fn reserve_prize(protocol: &mut ProtocolState) -> Result<u64> {
+    let next_round_rent = Rent::get()?.minimum_balance(8 + RoundState::SIZE);
+    let prize = protocol.prize_seed_amount.saturating_sub(next_round_rent);
+    protocol.prize_seed_amount -= prize;
+    Ok(prize)
+}
+
+/// Whether the treasury's seeded funds cover the rent `open_round_account` still needs for
+/// `round_info`
+fn treasury_covers_round_rent(protocol: &ProtocolState, round_info: &AccountInfo) -> Result<bool> {
+    let rent = Rent::get()?.minimum_balance(8 + RoundState::SIZE);
+    Ok(protocol.prize_seed_amount >= rent.saturating_sub(round_info.lamports()))
+}
+
+/// Create and initialize the RoundState PDA for `round_id` without an admin `init_round` call.
+/// Rent is covered by the treasury's seeded funds when they suffice (so whoever triggers the
+/// round is effectively reimbursed); otherwise `payer` tops up the shortfall.
+#[allow(clippy::too_many_arguments)]
+fn open_round_account<'info>(
+    program_id: &Pubkey,
+    protocol_info: &AccountInfo<'info>,
+    protocol: &mut ProtocolState,
+    round_info: &AccountInfo<'info>,
+    payer: Option<&AccountInfo<'info>>,
+    system_program_info: &AccountInfo<'info>,
+    round_id: u64,
+    stake_account: Pubkey,
+    start_time_ms: u64,
+) -> Result<()> {
+    let (expected_pda, bump) = find_round_pda(&protocol_info.key(), round_id, program_id);
+    require!(round_info.key() == expected_pda, ErrorCode::InvalidRoundAccount);
+    require!(
+        round_info.data_len() == 0 && round_info.owner == &system_program::ID,
+        ErrorCode::RoundAlreadyExists
+    );
+    require!(system_program_info.key() == system_program::ID, ErrorCode::MissingSystemProgram);
+
+    let space = 8 + RoundState::SIZE;
+    let rent = Rent::get()?.minimum_balance(space);
+    let shortfall = rent.saturating_sub(round_info.lamports());
+
+    // The treasury's rent goes back to it on close_round, like anything prefunded
+    let mut rent_payer = protocol_info.key();
+    if shortfall > 0 {
+        if protocol.prize_seed_amount >= shortfall {
+            // Treasury pays the rent out of seeded funds, never out of user escrow
+            **protocol_info.try_borrow_mut_lamports()? -= shortfall;
+            **round_info.try_borrow_mut_lamports()? += shortfall;
+            protocol.prize_seed_amount -= shortfall;
+            msg!("💰 Treasury paid {} lamports rent for round #{}", shortfall, round_id);
+        } else {
+            let payer = payer.ok_or(ErrorCode::InsufficientFunds)?;
+            let cpi_ctx = CpiContext::new(
+                system_program_info.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: round_info.clone(),
+                },
+            );
+            system_program::transfer(cpi_ctx, shortfall)?;
+            rent_payer = payer.key();
+            msg!("💰 Treasury short on seed funds, {} paid {} lamports rent", payer.key(), shortfall);
+        }
+    }
+
+    let round_id_bytes = round_id.to_le_bytes();
+    let signer_seeds: &[&[u8]] = &[b"round", protocol_info.key.as_ref(), &round_id_bytes, &[bump]];
+
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Allocate { account_to_allocate: round_info.clone() },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Assign { account_to_assign: round_info.clone() },
+            &[signer_seeds],
+        ),
+        program_id,
+    )?;
+
+    let round = RoundState {
+        round_id,
+        epoch_in_round: 1,
+        start_epoch: start_time_ms,
+        end_epoch: 0,
+        stake_account,
+        total_staked_lamports: 0,
+        total_prize_lamports: 0,
+        total_tickets_sold: 0,
+        winner: None,
+        winning_ticket: 0,
+        is_complete: false,
+        prize_claimed: false,
+        vrf_request: None,
+        is_cancelled: false,
+        bump,
+        draw_slot: 0,
+        draw_seed: 0,
+        version: ACCOUNT_VERSION,
+        rent_payer,
+        open_positions: 0,
+        positions_tracked: true,
+        voided_tickets: 0,
+        reserved: [0; 19],
+    };
+    let mut data = round_info.try_borrow_mut_data()?;
+    let mut out: Vec<u8> = Vec::with_capacity(data.len());
+    round.try_serialize(&mut out)?;
+    data[..out.len()].copy_from_slice(&out);
+
+    protocol.current_round = round_id;
+    protocol.rebalance_liquid_buffer();
+    Ok(())
+}
+
+/// Append `user` to `round_id`'s WithdrawalQueue unless they are already waiting in it,
+/// creating the queue on the first request. The user pays the rent of their slot.
+fn enqueue_withdrawal<'info>(
+    queue_info: &AccountInfo<'info>,
+    user: &Signer<'info>,
+    system_program_info: &Program<'info, System>,
+    protocol_key: &Pubkey,
+    round_id: u64,
+    bump: u8,
+) -> Result<()> {
+    let mut queue = if queue_info.data_is_empty() {
+        let round_id_bytes = round_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[b"withdrawals", protocol_key.as_ref(), &round_id_bytes, &[bump]];
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program_info.to_account_info(),
+                system_program::Allocate { account_to_allocate: queue_info.clone() },
+                &[signer_seeds],
+            ),
+            (8 + WithdrawalQueue::space(0)) as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program_info.to_account_info(),
+                system_program::Assign { account_to_assign: queue_info.clone() },
+                &[signer_seeds],
+            ),
+            &crate::ID,
+        )?;
+        WithdrawalQueue {
+            round_id,
+            head: 0,
+            entries: Vec::new(),
+            bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; 64],
+        }
+    } else {
+        require!(queue_info.owner == &crate::ID, ErrorCode::InvalidQueueEntry);
+        WithdrawalQueue::try_deserialize(&mut &queue_info.try_borrow_data()?[..])?
+    };
+
+    // Entries before `head` are done; a user paid earlier may queue again for the rest
+    if queue.entries[queue.head as usize..].contains(&user.key()) {
+        return Ok(());
+    }
+    queue.entries.push(user.key());
+
+    let space = 8 + WithdrawalQueue::space(queue.entries.len());
+    let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(queue_info.lamports());
+    if shortfall > 0 {
+        let cpi_ctx = CpiContext::new(
+            system_program_info.to_account_info(),
+            system_program::Transfer {
+                from: user.to_account_info(),
+                to: queue_info.clone(),
+            },
+        );
+        system_program::transfer(cpi_ctx, shortfall)?;
+    }
+    queue_info.resize(space)?;
+
+    let mut out: Vec<u8> = Vec::with_capacity(space);
+    queue.try_serialize(&mut out)?;
+    queue_info.try_borrow_mut_data()?[..out.len()].copy_from_slice(&out);
+    Ok(())
+}
+
+/// Grow the UserAccount at `info` to hold `extra_ranges` ranges past the first before Anchor
+/// writes it back, with `payer` covering the rent of the new bytes
+fn fit_user_account<'info>(
+    info: &AccountInfo<'info>,
+    extra_ranges: usize,
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+) -> Result<()> {
+    let space = 8 + UserAccount::space(extra_ranges);
+    if space <= info.data_len() {
+        return Ok(());
+    }
+    let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+    if shortfall > 0 {
+        let cpi_ctx = CpiContext::new(
+            system_program_info.clone(),
+            system_program::Transfer {
+                from: payer.clone(),
+                to: info.clone(),
+            },
+        );
+        system_program::transfer(cpi_ctx, shortfall)?;
+    }
+    info.resize(space)?;
+    Ok(())
+}
+
+fn validate_multisig_owners(owners: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(
+        !owners.is_empty() && owners.len() <= MAX_MULTISIG_OWNERS,
+        ErrorCode::InvalidMultisigOwners
+    );
+    for (i, owner) in owners.iter().enumerate() {
+        require!(!owners[..i].contains(owner), ErrorCode::InvalidMultisigOwners);
+    }
+    require!(
+        threshold > 0 && threshold as usize <= owners.len(),
+        ErrorCode::InvalidMultisigThreshold
+    );
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
     pub admin: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"state"],
+        bump,
+        space = 8 + ProtocolState::SIZE,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SeedPrize<'info> {
+    #[account(mut, address = protocol_state.treasurer @ ErrorCode::Unauthorized)]
+    pub treasurer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.deposits_allowed() @ ErrorCode::DepositsPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct InitRound<'info> {
+    #[account(mut, address = protocol_state.keeper @ ErrorCode::Unauthorized)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.progression_allowed() @ ErrorCode::RoundProgressionPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    /// CHECK: stake account created off-program for now; authority held by PDA in future edits
+    pub stake_account: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        // Derive PDA off protocol_state and round_id
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump,
+        space = 8 + RoundState::SIZE,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.deposits_allowed() @ ErrorCode::DepositsPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        // An existing account keeps the size its ticket ranges grew it to
+        space = user_account.data_len().max(8 + UserAccount::SIZE),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    pub system_program: Program<'info, System>,
+    // remaining_accounts[0] should be RoundState PDA (mutable)
+    // remaining_accounts[1] may be the next RoundState PDA (mutable) to auto-start it once
+    // the current round is complete
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DepositFor<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any wallet; it only names the UserAccount the tickets are credited to
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.deposits_allowed() @ ErrorCode::DepositsPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"user", beneficiary.key().as_ref()],
+        bump,
+        // An existing account keeps the size its ticket ranges grew it to
+        space = user_account.data_len().max(8 + UserAccount::SIZE),
+        // A receipt's range is fixed in its metadata
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    pub system_program: Program<'info, System>,
+    // remaining_accounts as for Deposit
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(amount: u64, nonce: u64)]
+pub struct DepositWithReceipt<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.deposits_allowed() @ ErrorCode::DepositsPaused,
+        // A wallet could otherwise hold any number of tickets through receipts it keeps
+        constraint = protocol_state.max_tickets_per_user == 0 @ ErrorCode::ReceiptsCapped,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    /// A PDA, so no key exists that could sign as the position's owner; the protocol mints its
+    /// one token and closes it on redemption
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            b"receipt_mint".as_ref(),
+            &protocol_state.current_round.to_le_bytes(),
+            payer.key().as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        bump,
+        mint::decimals = 0,
+        mint::authority = protocol_state,
+        mint::token_program = token_program,
+        extensions::metadata_pointer::authority = protocol_state,
+        extensions::metadata_pointer::metadata_address = receipt_mint,
+        extensions::close_authority::authority = protocol_state,
+    )]
+    pub receipt_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"user", receipt_mint.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SIZE,
+    )]
+    pub receipt_account: Account<'info, UserAccount>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = payer,
+        associated_token::token_program = token_program,
+    )]
+    pub payer_receipt: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    // remaining_accounts as for Deposit
+}
+
+impl<'info> DepositWithReceipt<'info> {
+    /// Write the deposit into the mint's metadata, mint the one receipt token to the payer and
+    /// drop the mint authority so the supply stays at one
+    fn issue_receipt(&self, deposit: &DepositMade) -> Result<()> {
+        let bump = [self.protocol_state.bump];
+        let signer_seeds: &[&[u8]] = &[b"state", &bump];
+        let mint = self.receipt_mint.to_account_info();
+        let authority = self.protocol_state.to_account_info();
+        let token_program = self.token_program.to_account_info();
+
+        let name = format!(
+            "Rafa round #{} tickets #{}-#{}",
+            deposit.round_id, deposit.ticket_start, deposit.ticket_end
+        );
+        let fields = [
+            ("round_id", deposit.round_id.to_string()),
+            ("ticket_start", deposit.ticket_start.to_string()),
+            ("ticket_end", deposit.ticket_end.to_string()),
+            ("amount", deposit.amount.to_string()),
+        ];
+        let metadata = TokenMetadata {
+            update_authority: OptionalNonZeroPubkey::try_from(Some(authority.key()))?,
+            mint: mint.key(),
+            name: name.clone(),
+            symbol: RECEIPT_SYMBOL.to_string(),
+            uri: String::new(),
+            additional_metadata: fields.iter().map(|(key, value)| (key.to_string(), value.clone())).collect(),
+        };
+
+        // Token-2022 grows the mint to hold the metadata but leaves its rent to the caller
+        let space = mint.data_len() + metadata.tlv_size_of()?;
+        let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(mint.lamports());
+        if shortfall > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer { from: self.payer.to_account_info(), to: mint.clone() },
+                ),
+                shortfall,
+            )?;
+        }
+
+        token_interface::token_metadata_initialize(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                token_interface::TokenMetadataInitialize {
+                    program_id: token_program.clone(),
+                    metadata: mint.clone(),
+                    update_authority: authority.clone(),
+                    mint_authority: authority.clone(),
+                    mint: mint.clone(),
+                },
+                &[signer_seeds],
+            ),
+            metadata.name,
+            metadata.symbol,
+            metadata.uri,
+        )?;
+        for (key, value) in fields {
+            token_interface::token_metadata_update_field(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    token_interface::TokenMetadataUpdateField {
+                        program_id: token_program.clone(),
+                        metadata: mint.clone(),
+                        update_authority: authority.clone(),
+                    },
+                    &[signer_seeds],
+                ),
+                Field::Key(key.to_string()),
+                value,
+            )?;
+        }
+
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                token_interface::MintTo {
+                    mint: mint.clone(),
+                    to: self.payer_receipt.to_account_info(),
+                    authority: authority.clone(),
+                },
+                &[signer_seeds],
+            ),
+            1,
+        )?;
+        token_interface::set_authority(
+            CpiContext::new_with_signer(
+                token_program,
+                token_interface::SetAuthority { current_authority: authority, account_or_mint: mint },
+                &[signer_seeds],
+            ),
+            AuthorityType::MintTokens,
+            None,
+        )
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        constraint = user_account.owner == user.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &user_account.round_joined.to_le_bytes()],
+        bump = round_state.bump,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    /// CHECK: WithdrawalQueue of the round the position is in, created by the first request;
+    /// read and written in enqueue_withdrawal
+    #[account(
+        mut,
+        seeds = [b"withdrawals", protocol_state.key().as_ref(), &user_account.round_joined.to_le_bytes()],
+        bump,
+    )]
+    pub withdrawal_queue: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TakeSnapshotBatch<'info> {
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.progression_allowed() @ ErrorCode::RoundProgressionPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+    // remaining_accounts: Vec<UserAccount> expected
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AdvanceEpoch<'info> {
+    #[account(address = protocol_state.keeper @ ErrorCode::Unauthorized)]
+    pub keeper: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.progression_allowed() @ ErrorCode::RoundProgressionPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SelectWinnerLocal<'info> {
+    #[account(address = protocol_state.keeper @ ErrorCode::Unauthorized)]
+    pub keeper: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.progression_allowed() @ ErrorCode::RoundProgressionPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct ClaimPrize<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+    #[account(
+        mut,
+        seeds = [b"claim", round_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = !claim_ticket.claimed @ ErrorCode::AlreadyClaimed,
+        constraint = claim_ticket.winner == user.key() @ ErrorCode::NotWinner,
+        constraint = claim_ticket.round_id == round_id @ ErrorCode::InvalidRound,
+    )]
+    pub claim_ticket: Account<'info, ClaimTicket>,
+    #[account(
+        mut,
+        seeds = [b"user", user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.owner == user.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InstantWithdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"user", user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.owner == user.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &user_account.round_joined.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = !round_state.is_complete @ ErrorCode::InstantWithdrawClosed,
+    )]
+    pub round_state: Account<'info, RoundState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct ProcessWithdrawal<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = round_state.is_complete @ ErrorCode::RoundNotComplete,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    #[account(
+        mut,
+        seeds = [b"user", user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.owner == user.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+        constraint = user_account.round_joined == round_id @ ErrorCode::WrongRound,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(from_round: u64)]
+pub struct RollOver<'info> {
+    pub user: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.deposits_allowed() @ ErrorCode::DepositsPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"user", user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.owner == user.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+        constraint = user_account.round_joined == from_round @ ErrorCode::WrongRound,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &from_round.to_le_bytes()],
+        bump = previous_round.bump,
+        constraint = previous_round.is_complete @ ErrorCode::RoundNotComplete,
+    )]
+    pub previous_round: Account<'info, RoundState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &protocol_state.current_round.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = !round_state.is_complete @ ErrorCode::RoundComplete,
+    )]
+    pub round_state: Account<'info, RoundState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TransferPosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: any wallet; it only names the UserAccount the position moves to
+    pub recipient: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"user", user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.owner == user.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", recipient.key().as_ref()],
+        bump,
+        // An existing account keeps the size its ticket ranges grew it to
+        space = recipient_account.data_len().max(8 + UserAccount::SIZE),
+        constraint = !recipient_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub recipient_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &user_account.round_joined.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = !round_state.is_complete @ ErrorCode::TransferClosed,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct ProcessWithdrawalsBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = round_state.is_complete @ ErrorCode::RoundNotComplete,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    #[account(
+        mut,
+        seeds = [b"withdrawals", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = withdrawal_queue.bump,
+    )]
+    pub withdrawal_queue: Account<'info, WithdrawalQueue>,
+    // remaining_accounts: (UserAccount PDA, owner wallet) pairs, both mutable, in queue order
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64, prize_amount: u64, stake_amount: u64)]
+pub struct CreateClaimTicket<'info> {
+    #[account(mut, address = protocol_state.treasurer @ ErrorCode::Unauthorized)]
+    pub treasurer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = round_state.is_complete @ ErrorCode::RoundNotComplete,
+        constraint = round_state.winner.is_some() @ ErrorCode::NoTicketsSold,
+        // The claimed ticket may have been closed since; a new one would pay the prize twice
+        constraint = !round_state.prize_claimed @ ErrorCode::AlreadyClaimed,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    #[account(
+        init,
+        payer = treasurer,
+        seeds = [b"claim", round_id.to_le_bytes().as_ref(), round_state.winner.unwrap().as_ref()],
+        bump,
+        space = 8 + ClaimTicket::SIZE,
+    )]
+    pub claim_ticket: Account<'info, ClaimTicket>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct CreateClaimTicketWinner<'info> {
+    #[account(mut)]
+    pub winner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = round_state.is_complete @ ErrorCode::RoundNotComplete,
+        constraint = round_state.winner.is_some() @ ErrorCode::NoTicketsSold,
+        constraint = round_state.winner.unwrap() == winner.key() @ ErrorCode::NotWinner,
+        constraint = !round_state.prize_claimed @ ErrorCode::AlreadyClaimed,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    #[account(
+        seeds = [b"user", winner.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.owner == winner.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init,
+        payer = winner,
+        seeds = [b"claim", round_id.to_le_bytes().as_ref(), winner.key().as_ref()],
+        bump,
+        space = 8 + ClaimTicket::SIZE,
+    )]
+    pub claim_ticket: Account<'info, ClaimTicket>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Crank<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.progression_allowed() @ ErrorCode::RoundProgressionPaused,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+    // remaining_accounts: Vec<UserAccount> for winner selection, optionally followed by the
+    // next RoundState PDA and the system program to auto-start the next round
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct CancelRound<'info> {
+    #[account(
+        constraint = authority.key() == protocol_state.admin
+            || authority.key() == protocol_state.guardian @ ErrorCode::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = round_state.bump
+    )]
+    pub round_state: Account<'info, RoundState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64)]
+pub struct RefundCancelled<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = round_state.is_cancelled @ ErrorCode::RoundNotCancelled,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    #[account(
+        mut,
+        seeds = [b"user", participant.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.round_joined == round_id @ ErrorCode::WrongRound,
+        // The owner of a receipt position is its mint; the holder refunds it through redeem_receipt
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    /// CHECK: refund destination, must be the owner recorded on user_account
+    #[account(mut, address = user_account.owner)]
+    pub participant: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RedeemReceipt<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, mint::token_program = token_program)]
+    pub receipt_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = receipt_mint,
+        token::authority = holder,
+        token::token_program = token_program,
+        constraint = holder_receipt.amount == 1 @ ErrorCode::NotReceiptHolder,
+    )]
+    pub holder_receipt: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"user", receipt_mint.key().as_ref()],
+        bump = receipt_account.bump,
+        // Only deposit_with_receipt flags a position, and only for the mint it creates
+        constraint = receipt_account.is_receipt @ ErrorCode::NotAReceipt,
+        close = holder,
+    )]
+    pub receipt_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"round", protocol_state.key().as_ref(), &receipt_account.round_joined.to_le_bytes()],
+        bump = round_state.bump,
+        constraint = round_state.is_complete @ ErrorCode::RoundNotComplete,
+    )]
+    pub round_state: Account<'info, RoundState>,
+    pub token_program: Program<'info, Token2022>,
+}
+
+impl RedeemReceipt<'_> {
+    /// Burn the holder's receipt token and close its token account and the mint, rent to the holder
+    fn retire_receipt(&self) -> Result<()> {
+        let bump = [self.protocol_state.bump];
+        let signer_seeds: &[&[u8]] = &[b"state", &bump];
+        let token_program = self.token_program.to_account_info();
+        let mint = self.receipt_mint.to_account_info();
+        let holder = self.holder.to_account_info();
+
+        token_interface::burn(
+            CpiContext::new(
+                token_program.clone(),
+                token_interface::Burn {
+                    mint: mint.clone(),
+                    from: self.holder_receipt.to_account_info(),
+                    authority: holder.clone(),
+                },
+            ),
+            1,
+        )?;
+        token_interface::close_account(CpiContext::new(
+            token_program.clone(),
+            token_interface::CloseAccount {
+                account: self.holder_receipt.to_account_info(),
+                destination: holder.clone(),
+                authority: holder.clone(),
+            },
+        ))?;
+        token_interface::close_account(CpiContext::new_with_signer(
+            token_program,
+            token_interface::CloseAccount {
+                account: mint,
+                destination: holder,
+                authority: self.protocol_state.to_account_info(),
+            },
+            &[signer_seeds],
+        ))
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetProtocolStatus<'info> {
+    #[account(
+        constraint = authority.key() == protocol_state.admin
+            || authority.key() == protocol_state.guardian @ ErrorCode::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
     #[account(mut, seeds = [b"state"], bump = protocol_state.bump)]
     pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    #[account(address = protocol_state.admin)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(address = protocol_state.admin)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(constraint = protocol_state.pending_admin == Some(new_admin.key()) @ ErrorCode::NotPendingAdmin)]
+    pub new_admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitChangeQueue<'info> {
+    #[account(mut, address = protocol_state.admin)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"changes", protocol_state.key().as_ref()],
+        bump,
+        space = 8 + ChangeQueue::SIZE,
+    )]
+    pub change_queue: Account<'info, ChangeQueue>,
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProposeChange<'info> {
+    #[account(address = protocol_state.admin)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"changes", protocol_state.key().as_ref()],
+        bump = change_queue.bump,
+    )]
+    pub change_queue: Account<'info, ChangeQueue>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteChange<'info> {
+    #[account(address = protocol_state.admin)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"changes", protocol_state.key().as_ref()],
+        bump = change_queue.bump,
+    )]
+    pub change_queue: Account<'info, ChangeQueue>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelChange<'info> {
+    #[account(
+        constraint = authority.key() == protocol_state.admin
+            || authority.key() == protocol_state.guardian @ ErrorCode::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"changes", protocol_state.key().as_ref()],
+        bump = change_queue.bump,
+    )]
+    pub change_queue: Account<'info, ChangeQueue>,
+}
+
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(round_id: u64)]
-pub struct InitRound<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    #[account(mut, seeds = [b"state"], bump = protocol_state.bump)]
+pub struct CreateMultisig<'info> {
+    #[account(mut, address = protocol_state.admin)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
     pub protocol_state: Account<'info, ProtocolState>,
-    /// CHECK: stake account created off-program for now; authority held by PDA in future edits
-    pub stake_account: UncheckedAccount<'info>,
     #[account(
         init,
-        payer = payer,
-        // Derive PDA off protocol_state and round_id
-        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        payer = admin,
+        seeds = [b"multisig", protocol_state.key().as_ref()],
         bump,
-        space = 8 + RoundState::SIZE,
+        space = 8 + Multisig::SIZE,
     )]
-    pub round_state: Account<'info, RoundState>,
+    pub multisig: Account<'info, Multisig>,
+    /// CHECK: data-less PDA that signs executed proposals; can also hold lamports to pay rent
+    #[account(seeds = [b"multisig-signer", multisig.key().as_ref()], bump)]
+    pub multisig_signer: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct Deposit<'info> {
+#[instruction(accounts: Vec<ProposalAccount>, data: Vec<u8>)]
+pub struct ProposeMultisigTx<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut, seeds = [b"state"], bump = protocol_state.bump)]
+    pub proposer: Signer<'info>,
+    #[account(seeds = [b"state"], bump = protocol_state.bump)]
     pub protocol_state: Account<'info, ProtocolState>,
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        mut,
+        seeds = [b"multisig", protocol_state.key().as_ref()],
+        bump = multisig.bump,
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        init,
+        payer = proposer,
+        seeds = [b"proposal", multisig.key().as_ref(), &multisig.proposal_count.to_le_bytes()],
         bump,
-        space = 8 + UserAccount::SIZE,
+        space = 8 + MultisigProposal::space(accounts.len(), data.len(), multisig.owners.len()),
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub proposal: Account<'info, MultisigProposal>,
     pub system_program: Program<'info, System>,
-    // remaining_accounts[0] should be RoundState PDA (mutable)
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct RequestWithdrawal<'info> {
-    pub user: Signer<'info>,
+#[instruction(proposal_id: u64)]
+pub struct ApproveMultisigTx<'info> {
+    pub owner: Signer<'info>,
     #[account(seeds = [b"state"], bump = protocol_state.bump)]
     pub protocol_state: Account<'info, ProtocolState>,
-    #[account(mut, seeds = [b"user", user.key().as_ref()], bump, constraint = user_account.owner == user.key())]
-    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        seeds = [b"multisig", protocol_state.key().as_ref()],
+        bump = multisig.bump,
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        seeds = [b"proposal", multisig.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = !proposal.executed @ ErrorCode::ProposalAlreadyExecuted,
+        constraint = proposal.owner_set_seqno == multisig.owner_set_seqno @ ErrorCode::StaleProposal,
+        constraint = multisig_allowed(protocol_state.status, &proposal.data) @ ErrorCode::ProtocolFrozen,
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct TakeSnapshotBatch<'info> {
+#[instruction(proposal_id: u64)]
+pub struct ExecuteMultisigTx<'info> {
     #[account(seeds = [b"state"], bump = protocol_state.bump)]
     pub protocol_state: Account<'info, ProtocolState>,
     #[account(
-        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
-        bump = round_state.bump
+        seeds = [b"multisig", protocol_state.key().as_ref()],
+        bump = multisig.bump,
     )]
-    pub round_state: Account<'info, RoundState>,
-    // remaining_accounts: Vec<UserAccount> expected
+    pub multisig: Account<'info, Multisig>,
+    /// CHECK: PDA signer for the proposal's instruction, verified by seeds
+    #[account(seeds = [b"multisig-signer", multisig.key().as_ref()], bump = multisig.signer_bump)]
+    pub multisig_signer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"proposal", multisig.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = !proposal.executed @ ErrorCode::ProposalAlreadyExecuted,
+        constraint = proposal.owner_set_seqno == multisig.owner_set_seqno @ ErrorCode::StaleProposal,
+        constraint = multisig_allowed(protocol_state.status, &proposal.data) @ ErrorCode::ProtocolFrozen,
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+    // remaining_accounts: every account referenced by the proposal, in any order
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct AdvanceEpoch<'info> {
-    #[account(address = protocol_state.admin)]
-    pub admin: Signer<'info>,
-    #[account(seeds = [b"state"], bump = protocol_state.bump)]
+pub struct SetMultisigOwners<'info> {
+    #[account(seeds = [b"multisig-signer", multisig.key().as_ref()], bump = multisig.signer_bump)]
+    pub multisig_signer: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
     pub protocol_state: Account<'info, ProtocolState>,
     #[account(
         mut,
-        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
-        bump = round_state.bump
+        seeds = [b"multisig", protocol_state.key().as_ref()],
+        bump = multisig.bump,
     )]
-    pub round_state: Account<'info, RoundState>,
+    pub multisig: Account<'info, Multisig>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-pub struct SelectWinnerLocal<'info> {
-    #[account(address = protocol_state.admin)]
+pub struct BeginWindDown<'info> {
+    #[account(address = protocol_state.admin @ ErrorCode::Unauthorized)]
     pub admin: Signer<'info>,
-    #[account(seeds = [b"state"], bump = protocol_state.bump)]
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
     pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseProtocolState<'info> {
     #[account(
         mut,
-        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
-        bump = round_state.bump
+        address = protocol_state.admin,
     )]
-    pub round_state: Account<'info, RoundState>,
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+        close = admin,
+    )]
+    pub protocol_state: Account<'info, ProtocolState>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(round_id: u64)]
-pub struct ClaimPrize<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut, seeds = [b"state"], bump = protocol_state.bump)]
+pub struct CloseRound<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+        // Closing the current round would let the next deposit open it afresh
+        constraint = round_id < protocol_state.current_round @ ErrorCode::RoundIsCurrent,
+    )]
     pub protocol_state: Account<'info, ProtocolState>,
     #[account(
         mut,
         seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
-        bump = round_state.bump
+        bump = round_state.bump,
+        close = rent_payer,
     )]
     pub round_state: Account<'info, RoundState>,
+    /// CHECK: receives the rent, must be rent_receiver of the round (the protocol_state itself
+    /// when the treasury paid)
+    #[account(mut, address = rent_receiver(round_state.rent_payer, protocol_state.key()))]
+    pub rent_payer: UncheckedAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(round_id: u64, winner: Pubkey)]
+pub struct CloseClaimTicket<'info> {
     #[account(
         mut,
-        seeds = [b"claim", round_id.to_le_bytes().as_ref(), user.key().as_ref()],
-        bump,
-        constraint = !claim_ticket.claimed @ ErrorCode::AlreadyClaimed,
-        constraint = claim_ticket.winner == user.key() @ ErrorCode::NotWinner,
-        constraint = claim_ticket.round_id == round_id @ ErrorCode::InvalidRound,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
     )]
-    pub claim_ticket: Account<'info, ClaimTicket>,
+    pub protocol_state: Account<'info, ProtocolState>,
     #[account(
         mut,
-        seeds = [b"user", user.key().as_ref()],
-        bump = user_account.bump,
-        constraint = user_account.owner == user.key()
+        seeds = [b"claim", round_id.to_le_bytes().as_ref(), winner.as_ref()],
+        bump = claim_ticket.bump,
+        constraint = claim_ticket.claimed @ ErrorCode::PrizeNotClaimed,
+        close = rent_payer,
     )]
-    pub user_account: Account<'info, UserAccount>,
-    pub system_program: Program<'info, System>,
+    pub claim_ticket: Account<'info, ClaimTicket>,
+    /// CHECK: receives the rent, must be rent_receiver of the ticket
+    #[account(mut, address = rent_receiver(claim_ticket.rent_payer, protocol_state.key()))]
+    pub rent_payer: UncheckedAccount<'info>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(round_id: u64)]
-pub struct ProcessWithdrawal<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut, seeds = [b"state"], bump = protocol_state.bump)]
+pub struct CloseWithdrawalQueue<'info> {
+    #[account(
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.withdrawals_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
     pub protocol_state: Account<'info, ProtocolState>,
     #[account(
         seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
@@ -802,99 +3428,419 @@ pub struct ProcessWithdrawal<'info> {
     pub round_state: Account<'info, RoundState>,
     #[account(
         mut,
-        seeds = [b"user", user.key().as_ref()],
-        bump = user_account.bump,
-        constraint = user_account.owner == user.key(),
-        constraint = user_account.round_joined == round_id @ ErrorCode::WrongRound,
+        seeds = [b"withdrawals", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
+        bump = withdrawal_queue.bump,
+        constraint = withdrawal_queue.head as usize == withdrawal_queue.entries.len()
+            @ ErrorCode::WithdrawalQueueNotEmpty,
+        close = protocol_state,
     )]
-    pub user_account: Account<'info, UserAccount>,
-    pub system_program: Program<'info, System>,
+    pub withdrawal_queue: Account<'info, WithdrawalQueue>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(round_id: u64, prize_amount: u64, stake_amount: u64)]
-pub struct CreateClaimTicket<'info> {
-    #[account(mut, address = protocol_state.admin)]
-    pub admin: Signer<'info>,
-    #[account(mut, seeds = [b"state"], bump = protocol_state.bump)]
-    pub protocol_state: Account<'info, ProtocolState>,
+pub struct CloseUserAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
     #[account(
-        seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
-        bump = round_state.bump,
-        constraint = round_state.is_complete @ ErrorCode::RoundNotComplete,
-        constraint = round_state.winner.is_some() @ ErrorCode::NoTicketsSold,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
     )]
-    pub round_state: Account<'info, RoundState>,
+    pub protocol_state: Account<'info, ProtocolState>,
     #[account(
-        init,
-        payer = admin,
-        seeds = [b"claim", round_id.to_le_bytes().as_ref(), round_state.winner.unwrap().as_ref()],
-        bump,
-        space = 8 + ClaimTicket::SIZE,
+        mut,
+        seeds = [b"user", owner.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.owner == owner.key(),
+        constraint = !user_account.is_receipt @ ErrorCode::ReceiptPosition,
+        constraint = user_account.balance == 0 && user_account.pending_withdrawal_amount == 0
+            @ ErrorCode::UserAccountNotEmpty,
+        close = owner,
     )]
-    pub claim_ticket: Account<'info, ClaimTicket>,
-    pub system_program: Program<'info, System>,
+    pub user_account: Account<'info, UserAccount>,
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(round_id: u64)]
-pub struct CreateClaimTicketWinner<'info> {
-    #[account(mut)]
-    pub winner: Signer<'info>,
-    #[account(mut, seeds = [b"state"], bump = protocol_state.bump)]
+pub struct ReconcileRound<'info> {
+    #[account(
+        constraint = authority.key() == protocol_state.admin
+            || authority.key() == protocol_state.keeper @ ErrorCode::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
+    )]
     pub protocol_state: Account<'info, ProtocolState>,
     #[account(
+        mut,
         seeds = [b"round", protocol_state.key().as_ref(), &round_id.to_le_bytes()],
-        bump = round_state.bump,
-        constraint = round_state.is_complete @ ErrorCode::RoundNotComplete,
-        constraint = round_state.winner.is_some() @ ErrorCode::NoTicketsSold,
-        constraint = round_state.winner.unwrap() == winner.key() @ ErrorCode::NotWinner,
+        bump = round_state.bump
     )]
     pub round_state: Account<'info, RoundState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReconcileLiabilities<'info> {
+    #[account(address = protocol_state.admin @ ErrorCode::Unauthorized)]
+    pub admin: Signer<'info>,
     #[account(
-        seeds = [b"user", winner.key().as_ref()],
-        bump = user_account.bump,
-        constraint = user_account.owner == winner.key(),
-    )]
-    pub user_account: Account<'info, UserAccount>,
-    #[account(
-        init,
-        payer = winner,
-        seeds = [b"claim", round_id.to_le_bytes().as_ref(), winner.key().as_ref()],
-        bump,
-        space = 8 + ClaimTicket::SIZE,
+        mut,
+        seeds = [b"state"],
+        bump = protocol_state.bump,
+        constraint = protocol_state.status.operations_allowed() @ ErrorCode::ProtocolFrozen,
     )]
-    pub claim_ticket: Account<'info, ClaimTicket>,
+    pub protocol_state: Account<'info, ProtocolState>,
+}
+
+/// Any rafa account written under an older layout. The account type is checked by each
+/// migrate_* instruction through the discriminator, so no seeds are needed here.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: read for its status only, in whatever layout it is still in
+    #[account(seeds = [b"state"], bump)]
+    pub protocol_state: UncheckedAccount<'info>,
+    /// CHECK: owned by rafa; the discriminator is checked in Migrate::migrate
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct Crank<'info> {
-    #[account(seeds = [b"state"], bump = protocol_state.bump)]
-    pub protocol_state: Account<'info, ProtocolState>,
-    #[account(
-        mut,
-        seeds = [b"round", protocol_state.key().as_ref(), &round_state.round_id.to_le_bytes()],
-        bump = round_state.bump
-    )]
-    pub round_state: Account<'info, RoundState>,
-    // remaining_accounts: Vec<UserAccount> for winner selection
+impl Migrate<'_> {
+    /// Status of the protocol, readable before the ProtocolState itself is migrated
+    fn protocol_status(&self) -> Result<ProtocolStatus> {
+        let (_, protocol) = ProtocolState::upgrade(&self.protocol_state.try_borrow_data()?)?;
+        Ok(protocol.status)
+    }
+
+    /// Rewrite `account` as a `T` on the current layout, see Versioned::upgrade. The account
+    /// grows to the space of the upgraded value.
+    fn migrate<T: Versioned>(&self) -> Result<AccountMigrated> {
+        require!(self.protocol_status()?.operations_allowed(), ErrorCode::ProtocolFrozen);
+        let info = self.account.to_account_info();
+        let old_len = info.data_len();
+
+        let (from_version, value) = T::upgrade(&info.try_borrow_data()?)?;
+        require!(from_version < ACCOUNT_VERSION, ErrorCode::AccountAlreadyMigrated);
+        let new_len = value.space().max(old_len);
+
+        // The payer funds exactly the rent of the new bytes; whatever else the account holds
+        // (the protocol's escrow) stays untouched
+        let rent = Rent::get()?;
+        let extra_rent = rent.minimum_balance(new_len).saturating_sub(rent.minimum_balance(old_len));
+        if extra_rent > 0 {
+            let cpi_ctx = CpiContext::new(
+                self.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: self.payer.to_account_info(),
+                    to: info.clone(),
+                },
+            );
+            system_program::transfer(cpi_ctx, extra_rent)?;
+        }
+        info.resize(new_len)?;
+
+        let mut out: Vec<u8> = Vec::with_capacity(new_len);
+        value.try_serialize(&mut out)?;
+        info.try_borrow_mut_data()?[..out.len()].copy_from_slice(&out);
+
+        msg!(
+            "📦 Migrated {} from layout v{} to v{} ({} → {} bytes)",
+            info.key, from_version, ACCOUNT_VERSION, old_len, new_len
+        );
+        Ok(AccountMigrated {
+            account: info.key(),
+            from_version,
+            to_version: ACCOUNT_VERSION,
+            extra_rent,
+        })
+    }
+}
+
+/// Account types the migrate_* instructions bring up to ACCOUNT_VERSION
+trait Versioned: AccountSerialize + AccountDeserialize {
+    /// Decode `data` (discriminator included), written under the current or any earlier layout,
+    /// into the current one stamped with ACCOUNT_VERSION, every field added since at its default.
+    /// Also returns the layout version `data` was in.
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)>;
+
+    /// Account size on the current layout, discriminator included
+    fn space(&self) -> usize;
+}
+
+/// Decode `data` as a prefix of `T`'s current layout, the fields appended since reading as zero.
+/// From v1 on fields only ever come out of the reserved bytes or at the end.
+fn upgrade_appended<T: AccountDeserialize>(data: &[u8], version: impl FnOnce(&mut T) -> &mut u8) -> Result<(u8, T)> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + MIGRATION_PADDING, 0);
+    let mut value = T::try_deserialize(&mut padded.as_slice())?;
+    let from_version = core::mem::replace(version(&mut value), ACCOUNT_VERSION);
+    Ok((from_version, value))
+}
+
+/// Decode `data` as the baseline (v0) layout `V` of `T`, which had no version byte and is told
+/// apart by its size
+fn decode_v0<T: Discriminator, V: AnchorDeserialize>(data: &[u8], size: usize) -> Result<Option<V>> {
+    if data.len() != 8 + size {
+        return Ok(None);
+    }
+    require!(data.starts_with(T::DISCRIMINATOR), anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+    Ok(Some(V::deserialize(&mut &data[8..])?))
+}
+
+/// ProtocolState as the baseline program wrote it
+#[derive(AnchorDeserialize)]
+struct ProtocolStateV0 {
+    admin: Pubkey,
+    validator: Pubkey,
+    current_round: u64,
+    prize_seed_amount: u64,
+    total_unclaimed_prizes: u64,
+    bump: u8,
+}
+
+impl ProtocolStateV0 {
+    const SIZE: usize = 32 + 32 + 8 + 8 + 8 + 1;
+}
+
+/// RoundState as the baseline program wrote it
+#[derive(AnchorDeserialize)]
+struct RoundStateV0 {
+    round_id: u64,
+    epoch_in_round: u8,
+    start_epoch: u64,
+    end_epoch: u64,
+    stake_account: Pubkey,
+    total_staked_lamports: u64,
+    total_prize_lamports: u64,
+    total_tickets_sold: u64,
+    winner: Option<Pubkey>,
+    winning_ticket: u64,
+    is_complete: bool,
+    prize_claimed: bool,
+    vrf_request: Option<Pubkey>,
+    bump: u8,
+}
+
+impl RoundStateV0 {
+    const SIZE: usize = 8 + 1 + 8 + 8 + 32 + 8 + 8 + 8 + (1 + 32) + 8 + 1 + 1 + (1 + 32) + 1;
+}
+
+/// UserAccount as the baseline program wrote it
+#[derive(AnchorDeserialize)]
+struct UserAccountV0 {
+    owner: Pubkey,
+    balance: u64,
+    ticket_start: u64,
+    ticket_end: u64,
+    snapshot_balances: [u64; 3],
+    snapshots_recorded_mask: u8,
+    round_joined: u64,
+    pending_withdrawal_amount: u64,
+    pending_withdrawal_round: u64,
+    bump: u8,
+}
+
+impl UserAccountV0 {
+    const SIZE: usize = 32 + 8 + 8 + 8 + (3 * 8) + 1 + 8 + 8 + 8 + 1;
+}
+
+/// ClaimTicket as the baseline program wrote it
+#[derive(AnchorDeserialize)]
+struct ClaimTicketV0 {
+    round_id: u64,
+    winner: Pubkey,
+    prize_amount: u64,
+    stake_amount: u64,
+    claimed: bool,
+    bump: u8,
+}
+
+impl ClaimTicketV0 {
+    const SIZE: usize = 8 + 32 + 8 + 8 + 1 + 1;
+}
+
+impl Versioned for ProtocolState {
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)> {
+        if let Some(old) = decode_v0::<Self, ProtocolStateV0>(data, ProtocolStateV0::SIZE)? {
+            // Every role stays with the admin, as initialize hands them out
+            let state = ProtocolState {
+                admin: old.admin,
+                validator: old.validator,
+                current_round: old.current_round,
+                prize_seed_amount: old.prize_seed_amount,
+                total_unclaimed_prizes: old.total_unclaimed_prizes,
+                cancelled_rounds: 0,
+                pending_admin: None,
+                keeper: old.admin,
+                guardian: old.admin,
+                treasurer: old.admin,
+                status: ProtocolStatus::Active,
+                timelock_delay_seconds: DEFAULT_TIMELOCK_SECONDS,
+                bump: old.bump,
+                version: ACCOUNT_VERSION,
+                outstanding_stake: 0,
+                liabilities_tracked: false,
+                wind_down_started_at: 0,
+                liquid_buffer_bps: DEFAULT_LIQUID_BUFFER_BPS,
+                exit_fee_bps: DEFAULT_EXIT_FEE_BPS,
+                liquid_buffer: 0,
+                max_tickets_per_user: 0,
+                max_tickets_per_round: 0,
+                min_tickets_per_deposit: 1,
+                reserved: [0; 23],
+            };
+            return Ok((0, state));
+        }
+        let (from_version, mut state) = upgrade_appended::<Self>(data, |a| &mut a.version)?;
+        // v4 took the buffer and fee out of the reserved bytes
+        if from_version < 4 {
+            state.liquid_buffer_bps = DEFAULT_LIQUID_BUFFER_BPS;
+            state.exit_fee_bps = DEFAULT_EXIT_FEE_BPS;
+        }
+        if state.min_tickets_per_deposit == 0 {
+            state.min_tickets_per_deposit = 1;
+        }
+        Ok((from_version, state))
+    }
+
+    fn space(&self) -> usize {
+        8 + Self::SIZE
+    }
+}
+
+impl Versioned for RoundState {
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)> {
+        let Some(old) = decode_v0::<Self, RoundStateV0>(data, RoundStateV0::SIZE)? else {
+            // The fields added from v2 on all start at zero
+            return upgrade_appended::<Self>(data, |a| &mut a.version);
+        };
+        let round = RoundState {
+            round_id: old.round_id,
+            epoch_in_round: old.epoch_in_round,
+            start_epoch: old.start_epoch,
+            end_epoch: old.end_epoch,
+            stake_account: old.stake_account,
+            total_staked_lamports: old.total_staked_lamports,
+            total_prize_lamports: old.total_prize_lamports,
+            total_tickets_sold: old.total_tickets_sold,
+            winner: old.winner,
+            winning_ticket: old.winning_ticket,
+            is_complete: old.is_complete,
+            prize_claimed: old.prize_claimed,
+            vrf_request: old.vrf_request,
+            is_cancelled: false,
+            bump: old.bump,
+            draw_slot: 0,
+            draw_seed: 0,
+            version: ACCOUNT_VERSION,
+            rent_payer: Pubkey::default(),
+            open_positions: 0,
+            positions_tracked: false,
+            voided_tickets: 0,
+            reserved: [0; 19],
+        };
+        Ok((0, round))
+    }
+
+    fn space(&self) -> usize {
+        8 + Self::SIZE
+    }
+}
+
+impl Versioned for UserAccount {
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)> {
+        let Some(old) = decode_v0::<Self, UserAccountV0>(data, UserAccountV0::SIZE)? else {
+            // v5 appended extra_ranges, which reads as an empty list before
+            return upgrade_appended::<Self>(data, |a| &mut a.version);
+        };
+        let user = UserAccount {
+            owner: old.owner,
+            balance: old.balance,
+            ticket_start: old.ticket_start,
+            ticket_end: old.ticket_end,
+            snapshot_balances: old.snapshot_balances,
+            snapshots_recorded_mask: old.snapshots_recorded_mask,
+            round_joined: old.round_joined,
+            pending_withdrawal_amount: old.pending_withdrawal_amount,
+            pending_withdrawal_round: old.pending_withdrawal_round,
+            bump: old.bump,
+            version: ACCOUNT_VERSION,
+            is_receipt: false,
+            reserved: [0; 63],
+            extra_ranges: Vec::new(),
+        };
+        Ok((0, user))
+    }
+
+    fn space(&self) -> usize {
+        8 + Self::space(self.extra_ranges.len())
+    }
+}
+
+impl Versioned for ClaimTicket {
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)> {
+        let Some(old) = decode_v0::<Self, ClaimTicketV0>(data, ClaimTicketV0::SIZE)? else {
+            return upgrade_appended::<Self>(data, |a| &mut a.version);
+        };
+        let ticket = ClaimTicket {
+            round_id: old.round_id,
+            winner: old.winner,
+            prize_amount: old.prize_amount,
+            stake_amount: old.stake_amount,
+            claimed: old.claimed,
+            bump: old.bump,
+            version: ACCOUNT_VERSION,
+            rent_payer: Pubkey::default(),
+            reserved: [0; 32],
+        };
+        Ok((0, ticket))
+    }
+
+    fn space(&self) -> usize {
+        8 + Self::SIZE
+    }
+}
+
+// The config queue and multisig came after the baseline; their pre-versioning layouts only
+// lacked the appended version and reserved bytes
+
+impl Versioned for ChangeQueue {
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)> {
+        upgrade_appended::<Self>(data, |a| &mut a.version)
+    }
+
+    fn space(&self) -> usize {
+        8 + Self::SIZE
+    }
 }
 
-#[derive(Accounts)]
-pub struct CloseProtocolState<'info> {
-    #[account(
-        mut,
-        address = protocol_state.admin,
-    )]
-    pub admin: Signer<'info>,
-    #[account(
-        mut,
-        seeds = [b"state"],
-        bump = protocol_state.bump,
-        close = admin,
-    )]
-    pub protocol_state: Account<'info, ProtocolState>,
+impl Versioned for Multisig {
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)> {
+        upgrade_appended::<Self>(data, |a| &mut a.version)
+    }
+
+    fn space(&self) -> usize {
+        8 + Self::SIZE
+    }
+}
+
+impl Versioned for MultisigProposal {
+    fn upgrade(data: &[u8]) -> Result<(u8, Self)> {
+        upgrade_appended::<Self>(data, |a| &mut a.version)
+    }
+
+    fn space(&self) -> usize {
+        8 + Self::space(self.accounts.len(), self.data.len(), self.approvals.len())
+    }
 }
 
 #[account]
@@ -904,12 +3850,123 @@ pub struct ProtocolState {
     pub current_round: u64,
     pub prize_seed_amount: u64,  // Initial seed for prize pool (kept for backwards compat)
     pub total_unclaimed_prizes: u64,  // Track all pending prize claims
+    pub cancelled_rounds: u64,        // Number of rounds cancelled via cancel_round
+    pub pending_admin: Option<Pubkey>, // Nominee from propose_admin, waiting for accept_admin
+    pub keeper: Pubkey,               // Runs epochs, snapshots, winner selection and init_round
+    pub guardian: Pubkey,             // May pause the protocol, but never unpause
+    pub treasurer: Pubkey,            // Seeds prizes and creates claim tickets
+    pub status: ProtocolStatus,
+    pub timelock_delay_seconds: i64,  // Delay between propose_change and execute_change
     pub bump: u8,
+    pub version: u8,                  // Layout version, see ACCOUNT_VERSION
+    pub outstanding_stake: u64,       // Stake owed back to users: every balance and pending withdrawal
+    pub liabilities_tracked: bool,    // outstanding_stake counted since initialize (v3 protocols)
+    pub wind_down_started_at: i64,    // Unix time of begin_wind_down, 0 while operating
+    pub liquid_buffer_bps: u16,       // Share of the escrow left unstaked for instant withdrawals
+    pub exit_fee_bps: u16,            // Fee on instant withdrawals, paid into the prize pool
+    pub liquid_buffer: u64,           // Lamports instant withdrawals may still pay out
+    pub max_tickets_per_user: u32,    // Most tickets one wallet may hold in a round, 0 = no cap
+    pub max_tickets_per_round: u32,   // Most live tickets a round may hold, 0 = no cap
+    pub min_tickets_per_deposit: u32, // Fewest tickets a deposit may buy (0 on old accounts acts as 1)
+    pub reserved: [u8; 23],           // Room for new fields without a realloc
 }
 
 impl ProtocolState {
-    // admin (32) + validator (32) + current_round (8) + prize_seed_amount (8) + total_unclaimed_prizes (8) + bump (1)
-    pub const SIZE: usize = 32 + 32 + 8 + 8 + 8 + 1;
+    // admin (32) + validator (32) + current_round (8) + prize_seed_amount (8) + total_unclaimed_prizes (8)
+    // + cancelled_rounds (8) + pending_admin (1 + 32) + keeper (32) + guardian (32) + treasurer (32)
+    // + status (1) + timelock_delay_seconds (8) + bump (1) + version (1) + outstanding_stake (8)
+    // + liabilities_tracked (1) + wind_down_started_at (8) + liquid_buffer_bps (2) + exit_fee_bps (2)
+    // + liquid_buffer (8) + max_tickets_per_user (4) + max_tickets_per_round (4)
+    // + min_tickets_per_deposit (4) + reserved (23)
+    pub const SIZE: usize =
+        32 + 32 + 8 + 8 + 8 + 8 + (1 + 32) + 32 + 32 + 32 + 1 + 8 + 1 + 1 + 8 + 1 + 8 + 2 + 2 + 8 + 4 + 4 + 4 + 23;
+
+    pub fn winding_down(&self) -> bool {
+        self.wind_down_started_at > 0
+    }
+
+    /// A stake was paid back to its owner
+    fn release_stake(&mut self, amount: u64) {
+        self.outstanding_stake = self.outstanding_stake.saturating_sub(amount);
+    }
+
+    /// Part of a new deposit that stays liquid
+    fn add_liquid_share(&mut self, amount: u64) {
+        let share = (amount as u128 * self.liquid_buffer_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        self.liquid_buffer = self.liquid_buffer.saturating_add(share);
+    }
+
+    /// Stake is redistributed when a round opens: the buffer goes back to its share of the escrow
+    fn rebalance_liquid_buffer(&mut self) {
+        self.liquid_buffer = 0;
+        self.add_liquid_share(self.outstanding_stake);
+    }
+
+    /// Buying `num_tickets` on top of the `held` tickets a wallet has in a round that already has
+    /// `live` tickets stays within the configured caps
+    fn check_ticket_caps(&self, held: u64, live: u64, num_tickets: u64) -> Result<()> {
+        if self.max_tickets_per_user > 0 {
+            require!(
+                held.saturating_add(num_tickets) <= u64::from(self.max_tickets_per_user),
+                ErrorCode::WalletTicketCapExceeded
+            );
+        }
+        if self.max_tickets_per_round > 0 {
+            require!(
+                live.saturating_add(num_tickets) <= u64::from(self.max_tickets_per_round),
+                ErrorCode::RoundTicketCapExceeded
+            );
+        }
+        Ok(())
+    }
+
+    /// Fee kept on an instant withdrawal of `amount`
+    pub fn exit_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.exit_fee_bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+}
+
+/// Operational roles the admin can hand to separate keys
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolRole {
+    Keeper,
+    Guardian,
+    Treasurer,
+}
+
+/// Emergency status of the protocol, ordered from least to most restrictive
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolStatus {
+    /// Everything enabled
+    Active,
+    /// New deposits rejected; rounds keep running to completion
+    DepositsPaused,
+    /// Only claims, withdrawals and refunds; rounds stop progressing
+    WithdrawOnly,
+    /// Nothing but the status switch, directly or as a multisig proposal
+    Frozen,
+}
+
+impl ProtocolStatus {
+    pub fn deposits_allowed(&self) -> bool {
+        *self == ProtocolStatus::Active
+    }
+
+    /// Epoch advancement, snapshots, finalization and starting rounds
+    pub fn progression_allowed(&self) -> bool {
+        *self <= ProtocolStatus::DepositsPaused
+    }
+
+    /// Claims, withdrawals and refunds
+    pub fn withdrawals_allowed(&self) -> bool {
+        *self != ProtocolStatus::Frozen
+    }
+
+    /// Admin and multisig actions, config changes, migrations and account upkeep. Only the
+    /// status switch itself (and multisig proposals making it) goes through while Frozen.
+    pub fn operations_allowed(&self) -> bool {
+        *self != ProtocolStatus::Frozen
+    }
 }
 
 #[account]
@@ -927,14 +3984,69 @@ pub struct RoundState {
     pub is_complete: bool,            // Round finished (winner selected)
     pub prize_claimed: bool,          // Winner claimed their prize
     pub vrf_request: Option<Pubkey>,
+    pub is_cancelled: bool,           // Round cancelled, participants refunded via refund_cancelled
     pub bump: u8,
+    pub draw_slot: u64,               // Slot the winner was drawn in
+    pub draw_seed: u64,               // Randomness input of the draw, see rafa_draw
+    pub version: u8,                  // Layout version, see ACCOUNT_VERSION
+    pub rent_payer: Pubkey,           // Gets the rent back on close_round, see rent_receiver
+    pub open_positions: u32,          // Participants whose stake is not paid out yet
+    pub positions_tracked: bool,      // open_positions counted since the round opened (v2 rounds)
+    pub voided_tickets: u64,          // Forfeited tickets inside 0..total_tickets_sold, owned by nobody
+    pub reserved: [u8; 19],           // Room for new fields without a realloc
 }
 
 impl RoundState {
     // round_id (8) + epoch_in_round (1) + start_epoch (8) + end_epoch (8) + stake_account (32)
     // + total_staked_lamports (8) + total_prize_lamports (8) + total_tickets_sold (8)
-    // + winner (1 + 32) + winning_ticket (8) + is_complete (1) + prize_claimed (1) + vrf_request (1 + 32) + bump (1)
-    pub const SIZE: usize = 8 + 1 + 8 + 8 + 32 + 8 + 8 + 8 + (1 + 32) + 8 + 1 + 1 + (1 + 32) + 1;
+    // + winner (1 + 32) + winning_ticket (8) + is_complete (1) + prize_claimed (1) + vrf_request (1 + 32)
+    // + is_cancelled (1) + bump (1) + draw_slot (8) + draw_seed (8) + version (1) + rent_payer (32)
+    // + open_positions (4) + positions_tracked (1) + voided_tickets (8) + reserved (19)
+    pub const SIZE: usize =
+        8 + 1 + 8 + 8 + 32 + 8 + 8 + 8 + (1 + 32) + 8 + 1 + 1 + (1 + 32) + 1 + 1 + 8 + 8 + 1 + 32 + 4 + 1 + 8 + 19;
+
+    /// Tickets that can still win
+    pub fn live_tickets(&self) -> u64 {
+        self.total_tickets_sold.saturating_sub(self.voided_tickets)
+    }
+
+    /// A participant's stake was paid out (withdrawn, claimed or refunded)
+    fn settle_position(&mut self) {
+        self.open_positions = self.open_positions.saturating_sub(1);
+    }
+
+    /// Void the last `tickets` of `user`'s ranges, newest range first, and return the voided
+    /// `(first, last)` ranges. Ticket numbers are never handed out twice, so a later deposit
+    /// only extends a cut range when nobody else has bought since.
+    fn forfeit_tickets(&mut self, user: &mut UserAccount, tickets: u64) -> Result<Vec<(u64, u64)>> {
+        require!(tickets <= user.tickets_held(), ErrorCode::InvalidAmount);
+        self.voided_tickets = self.voided_tickets
+            .checked_add(tickets)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let mut voided = Vec::new();
+        let mut left = tickets;
+        while left > 0 {
+            let extra = !user.extra_ranges.is_empty();
+            let (first, last) = match user.extra_ranges.last_mut() {
+                Some(range) => (&mut range.first, &mut range.last),
+                None => (&mut user.ticket_start, &mut user.ticket_end),
+            };
+            let len = *last + 1 - *first;
+            let cut = left.min(len);
+            voided.push((*last + 1 - cut, *last));
+            if cut < len {
+                *last -= cut;
+            } else if !extra {
+                // Empty range just past the voided tickets
+                *first = *last + 1;
+            }
+            if cut == len && extra {
+                user.extra_ranges.pop();
+            }
+            left -= cut;
+        }
+        Ok(voided)
+    }
 }
 
 #[account]
@@ -949,12 +4061,237 @@ pub struct UserAccount {
     pub pending_withdrawal_amount: u64,
     pub pending_withdrawal_round: u64,
     pub bump: u8,
+    pub version: u8,        // Layout version, see ACCOUNT_VERSION
+    pub is_receipt: bool,   // Owned by a ticket receipt's mint; only redeem_receipt pays it out
+    pub reserved: [u8; 63], // Room for new fields without a realloc
+    pub extra_ranges: Vec<TicketRange>, // Later deposits that could not extend the range above, in ticket order
 }
 
 impl UserAccount {
     // owner (32) + balance (8) + ticket_start (8) + ticket_end (8) + snapshot_balances (3*8) + mask (1)
     // + round_joined (8) + pending_withdrawal_amount (8) + pending_withdrawal_round (8) + bump (1)
-    pub const SIZE: usize = 32 + 8 + 8 + 8 + (3 * 8) + 1 + 8 + 8 + 8 + 1;
+    // + version (1) + is_receipt (1) + reserved (63) + extra_ranges (4), without any extra range
+    pub const SIZE: usize = 32 + 8 + 8 + 8 + (3 * 8) + 1 + 8 + 8 + 8 + 1 + 1 + 1 + 63 + 4;
+
+    /// Account size holding `extra_ranges` ranges past the first
+    pub fn space(extra_ranges: usize) -> usize {
+        Self::SIZE + extra_ranges * TicketRange::SIZE
+    }
+
+    /// `(first, last)` of every range still in the round's ledger, in ticket order. Settled users
+    /// keep stale ranges, and forfeiting every ticket leaves an empty one (`ticket_end < ticket_start`).
+    pub fn ranges(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        let staked = self.balance > 0 || self.pending_withdrawal_amount > 0;
+        let first = (self.ticket_end >= self.ticket_start).then_some((self.ticket_start, self.ticket_end));
+        first
+            .into_iter()
+            .chain(self.extra_ranges.iter().map(|range| (range.first, range.last)))
+            .filter(move |_| staked)
+    }
+
+    /// Tickets the user holds across all ranges
+    pub fn tickets_held(&self) -> u64 {
+        self.ranges().map(|(first, last)| last - first + 1).sum()
+    }
+
+    pub fn holds_tickets(&self) -> bool {
+        self.tickets_held() > 0
+    }
+
+    /// Forget every range, for a settled position or one starting over in a new round
+    fn clear_ranges(&mut self) {
+        self.ticket_start = 0;
+        self.ticket_end = 0;
+        self.extra_ranges.clear();
+    }
+
+    /// Hand `first..=last` to the user: it extends the last range when it follows right on,
+    /// otherwise it becomes a range of its own
+    fn add_range(&mut self, first: u64, last: u64) {
+        match self.extra_ranges.last_mut() {
+            Some(range) if range.last + 1 == first => range.last = last,
+            Some(_) => self.extra_ranges.push(TicketRange { first, last }),
+            // Empty after forfeiting everything: nothing left to extend
+            None if self.ticket_end < self.ticket_start => {
+                self.ticket_start = first;
+                self.ticket_end = last;
+            }
+            None if self.ticket_end + 1 == first => self.ticket_end = last,
+            None => self.extra_ranges.push(TicketRange { first, last }),
+        }
+    }
+}
+
+/// Tickets `first..=last` of a UserAccount's position, both ends inclusive
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TicketRange {
+    pub first: u64,
+    pub last: u64,
+}
+
+impl TicketRange {
+    pub const SIZE: usize = 8 + 8;
+}
+
+/// ChangeQueue: Timelocked config changes waiting for execute_change
+/// PDA seeds: [b"changes", protocol_state.as_ref()]
+#[account]
+pub struct ChangeQueue {
+    pub next_change_id: u64,
+    pub changes: Vec<PendingChange>,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 64],
+}
+
+impl ChangeQueue {
+    // next_change_id (8) + changes (4 + MAX_PENDING_CHANGES * PendingChange) + bump (1) + version (1)
+    // + reserved (64)
+    pub const SIZE: usize = 8 + (4 + MAX_PENDING_CHANGES * PendingChange::SIZE) + 1 + 1 + 64;
+}
+
+/// WithdrawalQueue: Owners who requested a withdrawal from a round, paid in request order by
+/// process_withdrawals_batch once the round is complete
+/// PDA seeds: [b"withdrawals", protocol_state.as_ref(), round_id.to_le_bytes()]
+#[account]
+pub struct WithdrawalQueue {
+    pub round_id: u64,
+    pub head: u32,              // Index of the next entry to process; everything before is done
+    pub entries: Vec<Pubkey>,   // Grows by one slot per request, paid by the requester
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 64],
+}
+
+impl WithdrawalQueue {
+    // round_id (8) + head (4) + entries (4 + n * 32) + bump (1) + version (1) + reserved (64)
+    pub fn space(entries: usize) -> usize {
+        8 + 4 + (4 + entries * 32) + 1 + 1 + 64
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PendingChange {
+    pub id: u64,
+    pub change: ConfigChange,
+    pub proposed_at: i64,
+    pub execute_after: i64,
+}
+
+impl PendingChange {
+    // id (8) + change + proposed_at (8) + execute_after (8)
+    pub const SIZE: usize = 8 + ConfigChange::SIZE + 8 + 8;
+}
+
+/// A ProtocolState config update that has to sit in the ChangeQueue before taking effect
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigChange {
+    Validator(Pubkey),
+    TimelockDelay(i64),
+    LiquidBufferBps(u16),
+    ExitFeeBps(u16),
+    MaxTicketsPerUser(u32),
+    MaxTicketsPerRound(u32),
+    MinTicketsPerDeposit(u32),
+}
+
+impl ConfigChange {
+    // tag (1) + largest variant: Validator (32)
+    pub const SIZE: usize = 1 + 32;
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ConfigChange::Validator(_) => {}
+            ConfigChange::TimelockDelay(delay) => {
+                require!(*delay >= 0, ErrorCode::InvalidConfigValue);
+            }
+            ConfigChange::LiquidBufferBps(bps) => {
+                require!(u64::from(*bps) <= BPS_DENOMINATOR, ErrorCode::InvalidConfigValue);
+            }
+            ConfigChange::ExitFeeBps(bps) => {
+                require!(*bps <= MAX_EXIT_FEE_BPS, ErrorCode::InvalidConfigValue);
+            }
+            // Caps of 0 lift the limit; a minimum of 0 would still mean one ticket
+            ConfigChange::MaxTicketsPerUser(_) | ConfigChange::MaxTicketsPerRound(_) => {}
+            ConfigChange::MinTicketsPerDeposit(min) => {
+                require!(*min >= 1, ErrorCode::InvalidConfigValue);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, protocol: &mut ProtocolState) {
+        match self {
+            ConfigChange::Validator(validator) => protocol.validator = *validator,
+            ConfigChange::TimelockDelay(delay) => protocol.timelock_delay_seconds = *delay,
+            // Takes effect for new deposits and from the next round on
+            ConfigChange::LiquidBufferBps(bps) => protocol.liquid_buffer_bps = *bps,
+            ConfigChange::ExitFeeBps(bps) => protocol.exit_fee_bps = *bps,
+            // Caps only bind new deposits; tickets already bought stay in play
+            ConfigChange::MaxTicketsPerUser(max) => protocol.max_tickets_per_user = *max,
+            ConfigChange::MaxTicketsPerRound(max) => protocol.max_tickets_per_round = *max,
+            ConfigChange::MinTicketsPerDeposit(min) => protocol.min_tickets_per_deposit = *min,
+        }
+    }
+}
+
+/// Multisig: M-of-N owners approving proposals executed with the `multisig_signer` PDA
+/// PDA seeds: [b"multisig", protocol_state.as_ref()]
+#[account]
+pub struct Multisig {
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub owner_set_seqno: u32,   // Bumped on owner changes to invalidate older proposals
+    pub proposal_count: u64,
+    pub signer_bump: u8,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 64],
+}
+
+impl Multisig {
+    // owners (4 + MAX_MULTISIG_OWNERS * 32) + threshold (1) + owner_set_seqno (4) + proposal_count (8)
+    // + signer_bump (1) + bump (1) + version (1) + reserved (64)
+    pub const SIZE: usize = (4 + MAX_MULTISIG_OWNERS * 32) + 1 + 4 + 8 + 1 + 1 + 1 + 64;
+}
+
+/// MultisigProposal: A rafa instruction waiting for multisig approvals
+/// PDA seeds: [b"proposal", multisig.as_ref(), id.to_le_bytes()]
+#[account]
+pub struct MultisigProposal {
+    pub multisig: Pubkey,
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub accounts: Vec<ProposalAccount>,
+    pub data: Vec<u8>,
+    pub approvals: Vec<bool>,   // Indexed like Multisig.owners
+    pub owner_set_seqno: u32,
+    pub executed: bool,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; 64],
+}
+
+impl MultisigProposal {
+    // multisig (32) + id (8) + proposer (32) + accounts (4 + n * ProposalAccount) + data (4 + len)
+    // + approvals (4 + owners) + owner_set_seqno (4) + executed (1) + bump (1) + version (1)
+    // + reserved (64)
+    pub fn space(num_accounts: usize, data_len: usize, num_owners: usize) -> usize {
+        32 + 8 + 32 + (4 + num_accounts * ProposalAccount::SIZE) + (4 + data_len) + (4 + num_owners) + 4 + 1 + 1
+            + 1 + 64
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ProposalAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl ProposalAccount {
+    // pubkey (32) + is_signer (1) + is_writable (1)
+    pub const SIZE: usize = 32 + 1 + 1;
 }
 
 /// ClaimTicket: Represents a winner's right to claim prize from a completed round
@@ -967,11 +4304,323 @@ pub struct ClaimTicket {
     pub stake_amount: u64,       // Original stake to return
     pub claimed: bool,
     pub bump: u8,
+    pub version: u8,            // Layout version, see ACCOUNT_VERSION
+    pub rent_payer: Pubkey,     // Gets the rent back on close_claim_ticket, see rent_receiver
+    pub reserved: [u8; 32],     // Room for new fields without a realloc
 }
 
 impl ClaimTicket {
     // round_id (8) + winner (32) + prize_amount (8) + stake_amount (8) + claimed (1) + bump (1)
-    pub const SIZE: usize = 8 + 32 + 8 + 8 + 1 + 1;
+    // + version (1) + rent_payer (32) + reserved (32)
+    pub const SIZE: usize = 8 + 32 + 8 + 8 + 1 + 1 + 1 + 32 + 32;
+}
+
+// Events: emitted through emit_cpi! so they are recorded as inner instructions and survive
+// log truncation. Every state transition emits one, amounts in lamports.
+
+#[event]
+pub struct PrizeSeeded {
+    pub treasurer: Pubkey,
+    pub amount: u64,
+    pub prize_seed_amount: u64,
+}
+
+#[event]
+pub struct RoundStarted {
+    pub round_id: u64,
+    pub start_time_ms: u64,
+    pub stake_account: Pubkey,
+    pub opened_by: Pubkey,      // Pubkey::default() when the treasury opened it through crank
+}
+
+#[event]
+pub struct DepositMade {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub tickets: u64,
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+    pub total_tickets_sold: u64,
+    pub total_staked_lamports: u64,
+}
+
+#[event]
+pub struct DepositFunded {
+    pub round_id: u64,
+    pub payer: Pubkey,
+    pub beneficiary: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReceiptMinted {
+    pub round_id: u64,
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+    pub amount: u64,
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+}
+
+#[event]
+pub struct ReceiptRedeemed {
+    pub round_id: u64,
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+    pub stake_amount: u64,
+    pub prize_amount: u64,
+}
+
+#[event]
+pub struct WithdrawalRequested {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub remaining_balance: u64,
+}
+
+#[event]
+pub struct EpochAdvanced {
+    pub round_id: u64,
+    pub from_epoch: u8,
+    pub to_epoch: u8,
+}
+
+#[event]
+pub struct RoundFinalized {
+    pub round_id: u64,
+    pub winner: Pubkey,
+    pub winning_ticket: u64,
+    pub total_tickets_sold: u64,
+    pub total_staked_lamports: u64,
+    pub prize_amount: u64,
+    pub end_time_ms: u64,
+    pub draw_slot: u64,
+    pub draw_seed: u64,
+}
+
+#[event]
+pub struct ClaimTicketCreated {
+    pub round_id: u64,
+    pub winner: Pubkey,
+    pub prize_amount: u64,
+    pub stake_amount: u64,
+}
+
+#[event]
+pub struct PrizeClaimed {
+    pub round_id: u64,
+    pub winner: Pubkey,
+    pub stake_amount: u64,
+    pub prize_amount: u64,
+}
+
+#[event]
+pub struct TicketsForfeited {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub first_ticket: u64,
+    pub last_ticket: u64,
+    pub total_tickets_sold: u64,
+    pub voided_tickets: u64,
+}
+
+#[event]
+pub struct InstantWithdrawal {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub exit_fee: u64,
+    pub remaining_balance: u64,
+}
+
+#[event]
+pub struct PositionRolledOver {
+    pub from_round: u64,
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+    pub total_tickets_sold: u64,
+    pub total_staked_lamports: u64,
+}
+
+#[event]
+pub struct PositionTransferred {
+    pub round_id: u64,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub ticket_start: u64,
+    pub ticket_end: u64,
+}
+
+#[event]
+pub struct WithdrawalProcessed {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RoundCancelled {
+    pub round_id: u64,
+    pub total_tickets_sold: u64,
+    pub total_staked_lamports: u64,
+}
+
+#[event]
+pub struct RefundProcessed {
+    pub round_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ProtocolInitialized {
+    pub admin: Pubkey,          // Also keeper, guardian and treasurer until set_role
+    pub validator: Pubkey,
+}
+
+#[event]
+pub struct ProtocolStatusChanged {
+    pub authority: Pubkey,
+    pub previous: ProtocolStatus,
+    pub status: ProtocolStatus,
+}
+
+#[event]
+pub struct RoleChanged {
+    pub role: ProtocolRole,
+    pub previous: Pubkey,
+    pub key: Pubkey,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub previous: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct ChangeQueueCreated {
+    pub change_queue: Pubkey,
+}
+
+#[event]
+pub struct ChangeProposed {
+    pub id: u64,
+    pub change: ConfigChange,
+    pub execute_after: i64,
+}
+
+#[event]
+pub struct ChangeExecuted {
+    pub id: u64,
+    pub change: ConfigChange,
+}
+
+#[event]
+pub struct ChangeCancelled {
+    pub id: u64,
+    pub change: ConfigChange,
+    pub cancelled_by: Pubkey,
+}
+
+/// Emitted by create_multisig and set_multisig_owners
+#[event]
+pub struct MultisigOwnersChanged {
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    pub owner_set_seqno: u32,
+}
+
+#[event]
+pub struct MultisigTxProposed {
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub data: Vec<u8>,          // Instruction data of the proposed rafa instruction
+}
+
+#[event]
+pub struct MultisigTxApproved {
+    pub proposal_id: u64,
+    pub owner: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct MultisigTxExecuted {
+    pub proposal_id: u64,
+}
+
+#[event]
+pub struct WindDownBegun {
+    pub admin: Pubkey,
+    pub status: ProtocolStatus,
+    pub closable_at: i64,       // close_protocol_state refused before this (unix seconds)
+}
+
+#[event]
+pub struct ProtocolClosed {
+    pub admin: Pubkey,
+    pub returned: u64,          // Rent and unspent seed sent to the admin
+}
+
+#[event]
+pub struct RoundClosed {
+    pub round_id: u64,
+    pub rent_receiver: Pubkey,
+    pub rent: u64,
+}
+
+#[event]
+pub struct ClaimTicketClosed {
+    pub round_id: u64,
+    pub winner: Pubkey,
+    pub rent_receiver: Pubkey,
+    pub rent: u64,
+}
+
+#[event]
+pub struct WithdrawalQueueClosed {
+    pub round_id: u64,
+    pub rent: u64,              // Back to the protocol's seeded funds
+}
+
+#[event]
+pub struct UserAccountClosed {
+    pub owner: Pubkey,
+    pub rent: u64,
+}
+
+#[event]
+pub struct RoundReconciled {
+    pub round_id: u64,
+    pub open_positions: u32,
+}
+
+#[event]
+pub struct LiabilitiesReconciled {
+    pub outstanding_stake: u64,
+    pub positions: u32,
+}
+
+/// Emitted by every migrate_* instruction
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+    pub extra_rent: u64,        // Paid by the caller for the bytes the account gained
 }
 
 #[error_code]
@@ -1014,4 +4663,109 @@ pub enum ErrorCode {
     RoundComplete,
     #[msg("Cannot close protocol: unclaimed prizes exist")]
     UnclaimedPrizesExist,
+    #[msg("Round account already exists")]
+    RoundAlreadyExists,
+    #[msg("System program must be provided to open the next round")]
+    MissingSystemProgram,
+    #[msg("Round has been cancelled")]
+    RoundCancelled,
+    #[msg("Round is not cancelled")]
+    RoundNotCancelled,
+    #[msg("Round already has a winner and cannot be cancelled")]
+    RoundAlreadyFinalized,
+    #[msg("Deposits are paused")]
+    DepositsPaused,
+    #[msg("Protocol is in withdraw-only mode, rounds cannot progress")]
+    RoundProgressionPaused,
+    #[msg("Protocol is frozen")]
+    ProtocolFrozen,
+    #[msg("Signer is not authorized for this instruction")]
+    Unauthorized,
+    #[msg("Guardian can only make the protocol status more restrictive")]
+    GuardianCannotUnpause,
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+    #[msg("Config change queue is full")]
+    ChangeQueueFull,
+    #[msg("Config change not found in queue")]
+    ChangeNotFound,
+    #[msg("Config change timelock has not expired yet")]
+    TimelockNotExpired,
+    #[msg("Invalid config value")]
+    InvalidConfigValue,
+    #[msg("Multisig owners must be unique and at most MAX_MULTISIG_OWNERS")]
+    InvalidMultisigOwners,
+    #[msg("Multisig threshold must be between 1 and the number of owners")]
+    InvalidMultisigThreshold,
+    #[msg("Signer is not a multisig owner")]
+    NotMultisigOwner,
+    #[msg("Multisig proposal does not have enough approvals")]
+    NotEnoughApprovals,
+    #[msg("Multisig proposal already executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Multisig owners changed since this proposal was created")]
+    StaleProposal,
+    #[msg("Withdraw or claim your previous round before joining a new one")]
+    UnsettledPosition,
+    // No longer raised: a top-up after others bought opens another range
+    #[msg("Others bought tickets since your last deposit; your tickets must stay one range")]
+    TicketRangeNotContiguous,
+    #[msg("Account is already on the current layout version")]
+    AccountAlreadyMigrated,
+    #[msg("The current round cannot be closed")]
+    RoundIsCurrent,
+    #[msg("Round still has participants to pay out or an unclaimed prize")]
+    RoundNotSettled,
+    #[msg("Claim ticket has not been claimed yet")]
+    PrizeNotClaimed,
+    #[msg("Withdraw or claim your stake before closing the account")]
+    UserAccountNotEmpty,
+    #[msg("Protocol is winding down")]
+    WindingDown,
+    #[msg("Protocol is not winding down; call begin_wind_down first")]
+    NotWindingDown,
+    #[msg("Wind-down grace period has not ended yet")]
+    WindDownGracePeriod,
+    #[msg("Participants still have stake in the protocol")]
+    OutstandingStake,
+    #[msg("Accounts do not match the next entries of the withdrawal queue")]
+    InvalidQueueEntry,
+    #[msg("Withdrawal queue still has requests to process")]
+    WithdrawalQueueNotEmpty,
+    #[msg("Liquid buffer cannot cover this instant withdrawal")]
+    InsufficientLiquidity,
+    #[msg("Round is over; withdraw without a fee through process_withdrawal")]
+    InstantWithdrawClosed,
+    #[msg("Pending withdrawal must be paid out before the position moves")]
+    PendingWithdrawal,
+    #[msg("Recipient holds a position in another round")]
+    RecipientHasPosition,
+    #[msg("Round is over; positions only change hands while it runs")]
+    TransferClosed,
+    #[msg("Deposit buys fewer tickets than the configured minimum")]
+    BelowMinimumTickets,
+    #[msg("Deposit would take this wallet over its ticket cap for the round")]
+    WalletTicketCapExceeded,
+    #[msg("Deposit would take the round over its ticket cap")]
+    RoundTicketCapExceeded,
+    #[msg("Participant passed more than once")]
+    DuplicateParticipant,
+    #[msg("Participants' ticket ranges overlap or run past the tickets sold")]
+    OverlappingTicketRanges,
+    #[msg("Participants passed do not hold every live ticket of the round")]
+    IncompleteTicketLedger,
+    #[msg("Position belongs to a ticket receipt; it only changes through redeem_receipt")]
+    ReceiptPosition,
+    #[msg("Account is not a ticket receipt position")]
+    NotAReceipt,
+    #[msg("Signer does not hold the ticket receipt")]
+    NotReceiptHolder,
+    #[msg("Ticket receipts are disabled while a per-wallet ticket cap is set")]
+    ReceiptsCapped,
+    #[msg("Round already has as many participants as one crank can draw from")]
+    RoundFull,
+    #[msg("Counts are already tracked on chain")]
+    AlreadyReconciled,
+    #[msg("Account is not a UserAccount that still holds stake to count")]
+    NotAnOpenPosition,
 }
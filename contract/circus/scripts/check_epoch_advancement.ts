@@ -113,7 +113,7 @@ async function checkEpochAdvancement() {
 
     console.log("\n✅ Participants in Round", currentRoundId + ":");
     participants.forEach((p, i) => {
-      const numTickets = p.ticketEnd - p.ticketStart + 1;
+      const numTickets = p.ticketCount;
       console.log(`  ${i + 1}. ${p.owner.toString().slice(0, 8)}... - ${numTickets} tickets (${p.balance / LAMPORTS_PER_SOL} SOL)`);
     });
 
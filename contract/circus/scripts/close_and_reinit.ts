@@ -144,7 +144,7 @@ async function closeAndReinit() {
     const seedTx = await program.methods
       .seedPrize(prizeSeed)
       .accounts({
-        admin: admin,
+        treasurer: admin,
         protocolState: protocolPda,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
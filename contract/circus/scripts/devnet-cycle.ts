@@ -84,7 +84,7 @@ async function runWinScenario(
       await program.methods
         .advanceEpoch()
         .accounts({
-          admin: provider.wallet.publicKey,
+          keeper: provider.wallet.publicKey,
           protocolState: protocolPda,
           roundState: roundPda,
         })
@@ -98,7 +98,7 @@ async function runWinScenario(
   await program.methods
     .selectWinnerLocal(new anchor.BN(Date.now()))
     .accounts({
-      admin: provider.wallet.publicKey,
+      keeper: provider.wallet.publicKey,
       protocolState: protocolPda,
       roundState: roundPda,
     })
@@ -230,7 +230,7 @@ async function runLoseScenario(
       await program.methods
         .advanceEpoch()
         .accounts({
-          admin: provider.wallet.publicKey,
+          keeper: provider.wallet.publicKey,
           protocolState: protocolPda,
           roundState: roundPda,
         })
@@ -244,7 +244,7 @@ async function runLoseScenario(
   await program.methods
     .selectWinnerLocal(new anchor.BN(Date.now()))
     .accounts({
-      admin: provider.wallet.publicKey,
+      keeper: provider.wallet.publicKey,
       protocolState: protocolPda,
       roundState: roundPda,
     })
@@ -321,7 +321,7 @@ async function main() {
     await program.methods
       .seedPrize(new anchor.BN(100_000_000)) // 0.1 SOL
       .accounts({
-        admin: provider.wallet.publicKey,
+        treasurer: provider.wallet.publicKey,
         protocolState: protocolPda,
         systemProgram: SystemProgram.programId,
       })
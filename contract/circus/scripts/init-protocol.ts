@@ -68,7 +68,7 @@ async function main() {
     const tx = await program.methods
       .seedPrize(prizeAmount)
       .accountsPartial({
-        admin: provider.wallet.publicKey,
+        treasurer: provider.wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
@@ -121,7 +121,7 @@ async function main() {
     const tx = await program.methods
       .seedPrize(new anchor.BN(prizeLamports))
       .accounts({
-        admin: provider.wallet.publicKey,
+        treasurer: provider.wallet.publicKey,
         protocolState: protocolPda,
         systemProgram: SystemProgram.programId,
       })
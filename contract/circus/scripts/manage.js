@@ -102,7 +102,7 @@ async function advanceEpoch(program, provider, config) {
     const tx = await program.methods
       .advanceEpoch()
       .accounts({
-        admin: provider.wallet.publicKey,
+        keeper: provider.wallet.publicKey,
         protocolState: protocolPda,
         roundState: roundPda,
       })
@@ -133,7 +133,7 @@ async function selectWinner(program, provider, config) {
     const tx = await program.methods
       .selectWinnerLocal(new anchor.BN(seed))
       .accounts({
-        admin: provider.wallet.publicKey,
+        keeper: provider.wallet.publicKey,
         protocolState: protocolPda,
         roundState: roundPda,
       })
@@ -102,7 +102,7 @@ async function simpleReinit() {
     const seedTx = await program.methods
       .seedPrize(prizeSeed)
       .accounts({
-        admin: admin,
+        treasurer: admin,
         protocolState: protocolPda,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
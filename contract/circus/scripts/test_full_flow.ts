@@ -90,7 +90,7 @@ async function testFullFlow() {
 
     console.log("Found", participants.length, "participant(s)");
     participants.forEach((p, i) => {
-      const numTickets = p.ticketEnd - p.ticketStart + 1;
+      const numTickets = p.ticketCount;
       console.log(`  ${i + 1}. ${p.owner.toString().slice(0, 8)}... - ${numTickets} tickets`);
     });
 
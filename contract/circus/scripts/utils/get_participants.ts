@@ -8,6 +8,8 @@ export interface ParticipantInfo {
   balance: number;
   ticketStart: number;
   ticketEnd: number;
+  extraRanges: { first: number; last: number }[];
+  ticketCount: number;
   roundJoined: number;
 }
 
@@ -37,12 +39,25 @@ export async function getParticipants(
       console.log(`  - UserAccount ${account.publicKey.toString().slice(0, 8)}... round=${roundJoined}, balance=${balance / 1e9} SOL`);
 
       if (roundJoined === roundId && balance > 0) {
+        const ticketStart = account.account.ticketStart.toNumber();
+        const ticketEnd = account.account.ticketEnd.toNumber();
+        // Deposits made after someone else bought tickets open further ranges
+        const extraRanges = (account.account.extraRanges ?? []).map((r: any) => ({
+          first: r.first.toNumber(),
+          last: r.last.toNumber(),
+        }));
+        const ticketCount = extraRanges.reduce(
+          (sum: number, r: { first: number; last: number }) => sum + r.last - r.first + 1,
+          Math.max(ticketEnd - ticketStart + 1, 0)
+        );
         participants.push({
           pubkey: account.publicKey,
           owner: account.account.owner,
           balance: balance,
-          ticketStart: account.account.ticketStart.toNumber(),
-          ticketEnd: account.account.ticketEnd.toNumber(),
+          ticketStart,
+          ticketEnd,
+          extraRanges,
+          ticketCount,
           roundJoined: roundJoined,
         });
       }
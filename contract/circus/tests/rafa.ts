@@ -42,7 +42,7 @@ describe("rafa mvp flow", () => {
     await program.methods
       .seedPrize(new anchor.BN(1_000_000)) // 0.001 SOL
       .accounts({
-        admin: provider.wallet.publicKey,
+        treasurer: provider.wallet.publicKey,
         protocolState: protocolPda,
         systemProgram: SystemProgram.programId,
       })
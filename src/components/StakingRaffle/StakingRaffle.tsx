@@ -6,7 +6,7 @@ import { useRaffleState } from '../../hooks/useRaffleState';
 import { useRaffleTransactions } from '../../hooks/useRaffleTransactions';
 import { useParticipantCount } from '../../hooks/useParticipantCount';
 import { useAutoCrank } from '../../hooks/useAutoCrank';
-import { formatTicketRange, getTicketCount, hasTickets } from '../../utils/tickets';
+import { formatTicketRange, getTicketCount, getTicketRanges } from '../../utils/tickets';
 import './StakingRaffle.css';
 
 interface StakingRaffleProps {
@@ -145,13 +145,12 @@ export const StakingRaffle: React.FC<StakingRaffleProps> = ({ onBack }) => {
   const userWon = roundState?.winner === publicKey?.toString();
 
   // User's ticket information
-  const userHasTickets = userAccount ? hasTickets(userAccount.ticketStart, userAccount.ticketEnd) : false;
-  const userTicketRange = userAccount && userHasTickets
-    ? formatTicketRange(userAccount.ticketStart, userAccount.ticketEnd)
+  const userTicketRanges = userAccount ? getTicketRanges(userAccount) : [];
+  const userHasTickets = userTicketRanges.length > 0;
+  const userTicketRange = userHasTickets
+    ? userTicketRanges.map(([start, end]) => formatTicketRange(start, end)).join(', ')
     : null;
-  const userTicketCount = userAccount && userHasTickets
-    ? getTicketCount(userAccount.ticketStart, userAccount.ticketEnd)
-    : 0;
+  const userTicketCount = userTicketRanges.reduce((sum, [start, end]) => sum + getTicketCount(start, end), 0);
 
   // Debug: Log user account info
   useEffect(() => {
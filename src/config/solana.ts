@@ -51,6 +51,18 @@ export const getRoundStatePDA = (protocolStatePDA: PublicKey, roundId: number |
   );
 };
 
+// Helper to derive a round's WithdrawalQueue PDA
+export const getWithdrawalQueuePDA = (protocolStatePDA: PublicKey, roundId: number | import('@coral-xyz/anchor').BN): [PublicKey, number] => {
+  const roundIdBuffer = Buffer.alloc(8);
+  const roundIdNum = typeof roundId === 'number' ? roundId : roundId.toNumber();
+  roundIdBuffer.writeBigUInt64LE(BigInt(roundIdNum));
+
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from('withdrawals'), protocolStatePDA.toBuffer(), roundIdBuffer],
+    PROGRAM_ID
+  );
+};
+
 // Helper to derive ClaimTicket PDA
 export const getClaimTicketPDA = (roundId: number, winnerPubkey: PublicKey): [PublicKey, number] => {
   const roundIdBuffer = Buffer.alloc(8);
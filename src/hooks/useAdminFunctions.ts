@@ -71,7 +71,7 @@ export const useAdminFunctions = () => {
       const tx = await program.methods
         .seedPrize(amountLamports)
         .accountsPartial({
-          admin: publicKey,
+          treasurer: publicKey,
           systemProgram: SystemProgram.programId,
         })
         .rpc();
@@ -150,7 +150,7 @@ export const useAdminFunctions = () => {
       const tx = await program.methods
         .advanceEpoch()
         .accountsPartial({
-          admin: publicKey,
+          keeper: publicKey,
           protocolState: protocolPda,
           roundState: roundPda,
         })
@@ -261,7 +261,7 @@ export const useAdminFunctions = () => {
       const tx = await program.methods
         .selectWinnerLocal(seed)
         .accountsPartial({
-          admin: publicKey,
+          keeper: publicKey,
           protocolState: protocolPda,
           roundState: roundPda,
         })
@@ -32,11 +32,17 @@ export interface RoundState {
   bump: number;
 }
 
+export interface TicketRange {
+  first: number;
+  last: number;
+}
+
 export interface UserAccount {
   owner: string;
   balance: number;
   ticketStart: number;
   ticketEnd: number;
+  extraRanges: TicketRange[];  // Later deposits that could not extend ticketStart-ticketEnd
   snapshotBalances: number[];
   snapshotsRecordedMask: number;
   roundJoined: number;
@@ -156,6 +162,7 @@ export const useRaffleState = () => {
             balance: user.balance.toNumber(),
             ticketStart: user.ticketStart.toNumber(),
             ticketEnd: user.ticketEnd.toNumber(),
+            extraRanges: (user.extraRanges ?? []).map((r: any) => ({ first: r.first.toNumber(), last: r.last.toNumber() })),
             snapshotBalances: user.snapshotBalances.map((b: any) => b.toNumber()),
             snapshotsRecordedMask: user.snapshotsRecordedMask,
             roundJoined: user.roundJoined.toNumber(),
@@ -152,9 +152,18 @@ export const useRaffleTransactions = () => {
       const [protocolPda] = getProtocolStatePDA();
       const [userPda] = getUserAccountPDA(publicKey);
 
+      // The request joins the withdrawal queue of the round the position is in; the keeper
+      // pays it out once that round is complete
+      const userAccount = await program.account.userAccount.fetch(userPda);
+      const { getRoundStatePDA, getWithdrawalQueuePDA } = await import('../config/solana');
+      const [roundPda] = getRoundStatePDA(protocolPda, userAccount.roundJoined);
+      const [withdrawalQueuePda] = getWithdrawalQueuePDA(protocolPda, userAccount.roundJoined);
+
       console.log('Requesting withdrawal:', {
         amount: amountSOL,
         lamports: amountLamports.toString(),
+        roundJoined: userAccount.roundJoined.toString(),
+        withdrawalQueuePda: withdrawalQueuePda.toString(),
       });
 
       const tx = await program.methods
@@ -163,6 +172,9 @@ export const useRaffleTransactions = () => {
           user: publicKey,
           protocolState: protocolPda,
           userAccount: userPda,
+          roundState: roundPda,
+          withdrawalQueue: withdrawalQueuePda,
+          systemProgram: SystemProgram.programId,
         })
         .rpc();
 
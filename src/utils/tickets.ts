@@ -82,6 +82,22 @@ export function getTicketCount(ticketStart: number, ticketEnd: number): number {
   return ticketEnd - ticketStart + 1;
 }
 
+/**
+ * Every ticket range a user holds, in ticket order: the first range plus the extra ones opened
+ * by deposits made after someone else bought tickets
+ */
+export function getTicketRanges(user: {
+  ticketStart: number;
+  ticketEnd: number;
+  extraRanges: { first: number; last: number }[];
+}): [number, number][] {
+  const ranges: [number, number][] = [];
+  if (hasTickets(user.ticketStart, user.ticketEnd)) {
+    ranges.push([user.ticketStart, user.ticketEnd]);
+  }
+  return ranges.concat(user.extraRanges.map((r): [number, number] => [r.first, r.last]));
+}
+
 /**
  * Check if user has any tickets
  * @param ticketStart First ticket number